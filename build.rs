@@ -0,0 +1,75 @@
+//! Bundles the Pdfium library that renders PDF pages, so that the viewer
+//! works without Pdfium being installed. The prebuilt library for the target
+//! is downloaded from the pdfium-binaries project, or taken from the file
+//! named by `PDFIUM_LIBRARY` for offline and distribution builds. It ends up
+//! in `$OUT_DIR/libpdfium.so`, which `src/pdf.rs` embeds.
+
+use flate2::read::GzDecoder;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Release of https://github.com/bblanchon/pdfium-binaries to download.
+/// `PDFIUM_RELEASE` overrides it, e.g. with `chromium/7350`.
+const PDFIUM_RELEASE: &str = "latest";
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-env-changed=PDFIUM_LIBRARY");
+    println!("cargo:rerun-if-env-changed=PDFIUM_RELEASE");
+
+    let out = PathBuf::from(env::var_os("OUT_DIR").expect("OUT_DIR is set by cargo"));
+    let library = out.join("libpdfium.so");
+    let result = match env::var_os("PDFIUM_LIBRARY") {
+        Some(path) => fs::copy(&path, &library).map(|_| ()),
+        None if library.exists() => Ok(()),
+        None => download(&library),
+    };
+    if let Err(e) = result {
+        panic!(
+            "Failed to bundle Pdfium: {}. Set PDFIUM_LIBRARY to the path of a libpdfium.so to build offline.",
+            e
+        );
+    }
+}
+
+/// Downloads the library for the target architecture to `library`.
+fn download(library: &Path) -> io::Result<()> {
+    let arch = match env::var("CARGO_CFG_TARGET_ARCH").as_deref() {
+        Ok("x86_64") => "x64",
+        Ok("aarch64") => "arm64",
+        Ok("arm") => "arm",
+        Ok("x86") => "x86",
+        _ => return Err(io::Error::other("no prebuilt Pdfium for this architecture")),
+    };
+    let release = env::var("PDFIUM_RELEASE").unwrap_or_else(|_| PDFIUM_RELEASE.to_string());
+    let url = if release == "latest" {
+        format!(
+            "https://github.com/bblanchon/pdfium-binaries/releases/latest/download/pdfium-linux-{}.tgz",
+            arch
+        )
+    } else {
+        format!(
+            "https://github.com/bblanchon/pdfium-binaries/releases/download/{}/pdfium-linux-{}.tgz",
+            release, arch
+        )
+    };
+
+    let response = ureq::get(&url).call().map_err(io::Error::other)?;
+    let mut archive = tar::Archive::new(GzDecoder::new(response.into_reader()));
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.as_ref() == Path::new("lib/libpdfium.so") {
+            // Unpack next to the final name so that an interrupted download
+            // is not taken for the library by the next build.
+            let partial = library.with_extension("so.partial");
+            entry.unpack(&partial)?;
+            return fs::rename(partial, library);
+        }
+    }
+    Err(io::Error::other(format!(
+        "{} holds no lib/libpdfium.so",
+        url
+    )))
+}
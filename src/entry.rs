@@ -0,0 +1,137 @@
+//! Entries of a directory listing and the metadata shown for them.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    Dir,
+    File,
+    /// Sockets, devices, FIFOs and broken symlinks.
+    Other,
+}
+
+/// One entry of a directory listing with the metadata shown for it,
+/// gathered once when the listing is read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntryInfo {
+    pub path: PathBuf,
+    pub name: String,
+    /// Kind of the entry, or of its target for a symlink.
+    pub kind: EntryKind,
+    /// Size in bytes; `0` for folders.
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub permissions: fs::Permissions,
+    pub symlink_target: Option<PathBuf>,
+    /// Dot files, hidden by convention.
+    pub hidden: bool,
+    /// Number of entries in a folder, if it could be read.
+    pub child_count: Option<usize>,
+}
+
+impl DirEntryInfo {
+    /// Reads the metadata of `path`, following a symlink to describe its
+    /// target. A broken symlink is described by the link itself.
+    pub fn read(path: PathBuf) -> io::Result<Self> {
+        let link_meta = fs::symlink_metadata(&path)?;
+        let symlink_target = if link_meta.file_type().is_symlink() {
+            fs::read_link(&path).ok()
+        } else {
+            None
+        };
+        let (meta, broken) = match symlink_target {
+            Some(_) => match fs::metadata(&path) {
+                Ok(meta) => (meta, false),
+                Err(_) => (link_meta, true),
+            },
+            None => (link_meta, false),
+        };
+
+        let kind = if broken {
+            EntryKind::Other
+        } else if meta.is_dir() {
+            EntryKind::Dir
+        } else if meta.is_file() {
+            EntryKind::File
+        } else {
+            EntryKind::Other
+        };
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let child_count = match kind {
+            EntryKind::Dir => fs::read_dir(&path).ok().map(|dir| dir.count()),
+            _ => None,
+        };
+
+        Ok(Self {
+            hidden: name.starts_with('.'),
+            size: if kind == EntryKind::Dir { 0 } else { meta.len() },
+            modified: meta.modified().ok(),
+            permissions: meta.permissions(),
+            path,
+            name,
+            kind,
+            symlink_target,
+            child_count,
+        })
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.kind == EntryKind::Dir
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use std::os::unix::fs::symlink;
+
+    #[test]
+    fn reads_files_and_folders() {
+        let dir = TestDir::new();
+        let file = DirEntryInfo::read(dir.file(".hidden", "12345")).unwrap();
+        assert_eq!(file.kind, EntryKind::File);
+        assert_eq!(file.name, ".hidden");
+        assert_eq!(file.size, 5);
+        assert!(file.hidden);
+        assert_eq!(file.child_count, None);
+
+        let folder = dir.dir("folder");
+        dir.file("folder/a", "");
+        dir.file("folder/b", "");
+        let folder = DirEntryInfo::read(folder).unwrap();
+        assert!(folder.is_dir() && !folder.hidden);
+        assert_eq!(folder.size, 0);
+        assert_eq!(folder.child_count, Some(2));
+
+        assert!(DirEntryInfo::read(dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn describes_symlinks_by_their_target() {
+        let dir = TestDir::new();
+        let target = dir.dir("target");
+        let link = dir.path().join("link");
+        symlink(&target, &link).unwrap();
+        let entry = DirEntryInfo::read(link).unwrap();
+        assert!(entry.is_dir());
+        assert_eq!(entry.symlink_target, Some(target));
+
+        let broken = dir.path().join("broken");
+        symlink("missing", &broken).unwrap();
+        let entry = DirEntryInfo::read(broken).unwrap();
+        assert_eq!(entry.kind, EntryKind::Other);
+        assert_eq!(entry.symlink_target, Some(PathBuf::from("missing")));
+    }
+}
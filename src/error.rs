@@ -0,0 +1,320 @@
+//! Typed errors of filesystem operations.
+//!
+//! A [`FileOpError`] keeps the failed operation, the path it failed on and
+//! the underlying [`io::Error`], and tells the common causes apart so that
+//! the UI can explain them and suggest a remedy.
+
+use crate::format::format_size;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Operation a [`FileOpError`] happened in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileOp {
+    Copy,
+    Move,
+    Rename,
+    CreateFolder,
+    Delete,
+    Trash,
+    Restore,
+    Read,
+}
+
+impl fmt::Display for FileOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let verb = match self {
+            FileOp::Copy => "copy",
+            FileOp::Move => "move",
+            FileOp::Rename => "rename",
+            FileOp::CreateFolder => "create folder",
+            FileOp::Delete => "delete",
+            FileOp::Trash => "move to trash",
+            FileOp::Restore => "restore",
+            FileOp::Read => "read",
+        };
+        write!(f, "{}", verb)
+    }
+}
+
+#[derive(Debug)]
+pub enum FileOpError {
+    PermissionDenied {
+        op: FileOp,
+        path: PathBuf,
+        source: io::Error,
+    },
+    NotFound {
+        op: FileOp,
+        path: PathBuf,
+        source: io::Error,
+    },
+    AlreadyExists {
+        op: FileOp,
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// The destination filesystem is full. `needed` and `available` are
+    /// known when the shortage was detected before writing.
+    NoSpace {
+        op: FileOp,
+        path: PathBuf,
+        needed: Option<u64>,
+        available: Option<u64>,
+        source: io::Error,
+    },
+    CrossDevice {
+        op: FileOp,
+        path: PathBuf,
+        source: io::Error,
+    },
+    NameTooLong {
+        op: FileOp,
+        path: PathBuf,
+        source: io::Error,
+    },
+    ReadOnly {
+        op: FileOp,
+        path: PathBuf,
+        source: io::Error,
+    },
+    NotEmpty {
+        op: FileOp,
+        path: PathBuf,
+        source: io::Error,
+    },
+    /// A folder was to be copied or moved into itself.
+    IntoItself {
+        op: FileOp,
+        path: PathBuf,
+        source: io::Error,
+    },
+    Other {
+        op: FileOp,
+        path: PathBuf,
+        source: io::Error,
+    },
+}
+
+impl FileOpError {
+    /// Classifies `source`, raised while performing `op` on `path`.
+    pub fn new(op: FileOp, path: impl Into<PathBuf>, source: io::Error) -> Self {
+        let path = path.into();
+        match source.kind() {
+            io::ErrorKind::PermissionDenied => FileOpError::PermissionDenied { op, path, source },
+            io::ErrorKind::NotFound => FileOpError::NotFound { op, path, source },
+            io::ErrorKind::AlreadyExists => FileOpError::AlreadyExists { op, path, source },
+            io::ErrorKind::StorageFull | io::ErrorKind::QuotaExceeded => FileOpError::NoSpace {
+                op,
+                path,
+                needed: None,
+                available: None,
+                source,
+            },
+            io::ErrorKind::CrossesDevices => FileOpError::CrossDevice { op, path, source },
+            io::ErrorKind::InvalidFilename => FileOpError::NameTooLong { op, path, source },
+            io::ErrorKind::ReadOnlyFilesystem => FileOpError::ReadOnly { op, path, source },
+            io::ErrorKind::DirectoryNotEmpty => FileOpError::NotEmpty { op, path, source },
+            _ => FileOpError::Other { op, path, source },
+        }
+    }
+
+    /// Not enough free space on the filesystem of `path` for `needed` bytes.
+    pub fn no_space(op: FileOp, path: impl Into<PathBuf>, needed: u64, available: u64) -> Self {
+        FileOpError::NoSpace {
+            op,
+            path: path.into(),
+            needed: Some(needed),
+            available: Some(available),
+            source: io::ErrorKind::StorageFull.into(),
+        }
+    }
+
+    /// `path` cannot be copied or moved into its own subtree.
+    pub fn into_itself(op: FileOp, path: impl Into<PathBuf>) -> Self {
+        FileOpError::IntoItself {
+            op,
+            path: path.into(),
+            source: io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot copy an item onto or into itself",
+            ),
+        }
+    }
+
+    pub fn op(&self) -> FileOp {
+        match self {
+            FileOpError::PermissionDenied { op, .. }
+            | FileOpError::NotFound { op, .. }
+            | FileOpError::AlreadyExists { op, .. }
+            | FileOpError::NoSpace { op, .. }
+            | FileOpError::CrossDevice { op, .. }
+            | FileOpError::NameTooLong { op, .. }
+            | FileOpError::ReadOnly { op, .. }
+            | FileOpError::NotEmpty { op, .. }
+            | FileOpError::IntoItself { op, .. }
+            | FileOpError::Other { op, .. } => *op,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            FileOpError::PermissionDenied { path, .. }
+            | FileOpError::NotFound { path, .. }
+            | FileOpError::AlreadyExists { path, .. }
+            | FileOpError::NoSpace { path, .. }
+            | FileOpError::CrossDevice { path, .. }
+            | FileOpError::NameTooLong { path, .. }
+            | FileOpError::ReadOnly { path, .. }
+            | FileOpError::NotEmpty { path, .. }
+            | FileOpError::IntoItself { path, .. }
+            | FileOpError::Other { path, .. } => path,
+        }
+    }
+
+    pub fn io_error(&self) -> &io::Error {
+        match self {
+            FileOpError::PermissionDenied { source, .. }
+            | FileOpError::NotFound { source, .. }
+            | FileOpError::AlreadyExists { source, .. }
+            | FileOpError::NoSpace { source, .. }
+            | FileOpError::CrossDevice { source, .. }
+            | FileOpError::NameTooLong { source, .. }
+            | FileOpError::ReadOnly { source, .. }
+            | FileOpError::NotEmpty { source, .. }
+            | FileOpError::IntoItself { source, .. }
+            | FileOpError::Other { source, .. } => source,
+        }
+    }
+
+    /// What the user can do about the error, if anything.
+    pub fn remedy(&self) -> Option<&'static str> {
+        match self {
+            FileOpError::PermissionDenied { .. } => {
+                Some("Check the permissions of the item and its folder.")
+            }
+            FileOpError::NotFound { .. } => {
+                Some("It may have been moved or deleted elsewhere; refresh the folder.")
+            }
+            FileOpError::AlreadyExists { .. } => Some("Choose another name."),
+            FileOpError::NoSpace { .. } => {
+                Some("Free up space on the destination or choose another location.")
+            }
+            FileOpError::CrossDevice { .. } => Some("Copy the item, then delete the original."),
+            FileOpError::NameTooLong { .. } => Some("Use a shorter name."),
+            FileOpError::ReadOnly { .. } => Some("Choose a destination on a writable drive."),
+            FileOpError::NotEmpty { .. } => Some("Empty the folder first."),
+            FileOpError::IntoItself { .. } => Some("Choose a destination outside the folder."),
+            FileOpError::Other { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for FileOpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = self.op();
+        let name = self
+            .path()
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_else(|| self.path().to_string_lossy());
+        match self {
+            FileOpError::PermissionDenied { .. } => {
+                write!(f, "Permission denied: cannot {} '{}'.", op, name)
+            }
+            FileOpError::NotFound { .. } => write!(f, "'{}' was not found.", name),
+            FileOpError::AlreadyExists { .. } => write!(f, "'{}' already exists.", name),
+            FileOpError::NoSpace {
+                needed: Some(needed),
+                available: Some(available),
+                ..
+            } => write!(
+                f,
+                "Not enough space: need {}, {} free.",
+                format_size(*needed),
+                format_size(*available)
+            ),
+            FileOpError::NoSpace { .. } => {
+                write!(f, "Not enough space to {} '{}'.", op, name)
+            }
+            FileOpError::CrossDevice { .. } => {
+                write!(f, "Cannot {} '{}' to another filesystem.", op, name)
+            }
+            FileOpError::NameTooLong { .. } => write!(f, "The name '{}' is too long.", name),
+            FileOpError::ReadOnly { .. } => {
+                write!(f, "Cannot {} '{}': the filesystem is read-only.", op, name)
+            }
+            FileOpError::NotEmpty { .. } => write!(f, "Folder '{}' is not empty.", name),
+            FileOpError::IntoItself { .. } => {
+                write!(f, "Cannot {} '{}' into itself.", op, name)
+            }
+            FileOpError::Other { source, .. } => {
+                write!(f, "Could not {} '{}': {}", op, name, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FileOpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(self.io_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use std::fs;
+
+    #[test]
+    fn classifies_io_errors() {
+        let dir = TestDir::new();
+        let missing = dir.path().join("missing");
+        let e = fs::read(&missing).unwrap_err();
+        let error = FileOpError::new(FileOp::Read, &missing, e);
+        assert!(matches!(error, FileOpError::NotFound { .. }));
+        assert_eq!(error.op(), FileOp::Read);
+        assert_eq!(error.path(), missing);
+        assert_eq!(error.to_string(), "'missing' was not found.");
+        assert!(error.remedy().is_some());
+
+        let full = dir.dir("full");
+        dir.file("full/a", "");
+        let e = fs::remove_dir(&full).unwrap_err();
+        let error = FileOpError::new(FileOp::Delete, &full, e);
+        assert!(matches!(error, FileOpError::NotEmpty { .. }));
+
+        let e = io::Error::other("disk on fire");
+        let error = FileOpError::new(FileOp::CreateFolder, "/a/b", e);
+        assert_eq!(
+            error.to_string(),
+            "Could not create folder 'b': disk on fire"
+        );
+        assert_eq!(error.remedy(), None);
+    }
+
+    #[test]
+    fn explains_detected_problems() {
+        let error = FileOpError::no_space(FileOp::Copy, "/mnt/usb", 3 << 20, 1 << 20);
+        assert_eq!(
+            error.to_string(),
+            "Not enough space: need 3.0 MB, 1.0 MB free."
+        );
+        assert_eq!(error.io_error().kind(), io::ErrorKind::StorageFull);
+
+        let error = FileOpError::new(FileOp::Move, "/a", io::ErrorKind::StorageFull.into());
+        assert_eq!(error.to_string(), "Not enough space to move 'a'.");
+
+        let error = FileOpError::into_itself(FileOp::Move, "/home/me/Photos");
+        assert_eq!(error.to_string(), "Cannot move 'Photos' into itself.");
+        assert!(std::error::Error::source(&error).is_some());
+
+        let error = FileOpError::new(FileOp::Trash, "/", io::ErrorKind::PermissionDenied.into());
+        assert_eq!(
+            error.to_string(),
+            "Permission denied: cannot move to trash '/'."
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Which entries of a directory are listed.
+
+use crate::entry::DirEntryInfo;
+
+/// Broad kind of file, recognised by extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Category {
+    Images,
+    Documents,
+    Audio,
+    Video,
+    Archives,
+}
+
+impl Category {
+    pub const ALL: [Category; 5] = [
+        Category::Images,
+        Category::Documents,
+        Category::Audio,
+        Category::Video,
+        Category::Archives,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Category::Images => "Images",
+            Category::Documents => "Documents",
+            Category::Audio => "Audio",
+            Category::Video => "Video",
+            Category::Archives => "Archives",
+        }
+    }
+
+    /// Lowercase extensions of the category.
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            Category::Images => &[
+                "png", "jpg", "jpeg", "gif", "bmp", "webp", "svg", "tif", "tiff", "ico", "heic",
+            ],
+            Category::Documents => &[
+                "pdf", "txt", "md", "doc", "docx", "odt", "rtf", "xls", "xlsx", "ods", "ppt",
+                "pptx", "odp", "csv", "epub",
+            ],
+            Category::Audio => &["mp3", "wav", "flac", "ogg", "oga", "opus", "m4a", "aac"],
+            Category::Video => &["mp4", "mkv", "webm", "avi", "mov", "m4v", "ogv"],
+            Category::Archives => &["zip", "tar", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar"],
+        }
+    }
+}
+
+/// A rule files must match to be listed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterRule {
+    /// Shell-style pattern on the name, with `*` and `?`, ignoring case.
+    Glob(String),
+    /// Lowercase extensions without the dot.
+    Extensions(Vec<String>),
+    Category(Category),
+}
+
+impl FilterRule {
+    /// Reads a rule typed by the user: a pattern if it has wildcards,
+    /// otherwise a list of extensions separated by commas or spaces, such
+    /// as `jpg, .png`. Returns `None` for blank input.
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        if input.is_empty() {
+            return None;
+        }
+        if input.contains(['*', '?']) {
+            return Some(FilterRule::Glob(input.to_string()));
+        }
+        let extensions: Vec<String> = input
+            .split([',', ' '])
+            .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+            .filter(|ext| !ext.is_empty())
+            .collect();
+        (!extensions.is_empty()).then_some(FilterRule::Extensions(extensions))
+    }
+
+    /// Short description for the header chip.
+    pub fn label(&self) -> String {
+        match self {
+            FilterRule::Glob(pattern) => pattern.clone(),
+            FilterRule::Extensions(extensions) => extensions
+                .iter()
+                .map(|ext| format!(".{}", ext))
+                .collect::<Vec<_>>()
+                .join(" "),
+            FilterRule::Category(category) => category.label().to_string(),
+        }
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            FilterRule::Glob(pattern) => glob_match(&pattern.to_lowercase(), &name.to_lowercase()),
+            FilterRule::Extensions(extensions) => {
+                extension(name).is_some_and(|ext| extensions.contains(&ext))
+            }
+            FilterRule::Category(category) => {
+                extension(name).is_some_and(|ext| category.extensions().contains(&ext.as_str()))
+            }
+        }
+    }
+}
+
+fn extension(name: &str) -> Option<String> {
+    let (stem, ext) = name.rsplit_once('.')?;
+    (!stem.is_empty()).then(|| ext.to_lowercase())
+}
+
+/// Matches `name` against `pattern`, where `*` stands for any run of
+/// characters and `?` for one character.
+pub fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position after the last `*` and the name position it was tried at.
+    let mut backtrack = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                p += 1;
+                backtrack = Some((p, n));
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star_p, star_n)) => {
+                    p = star_p;
+                    n = star_n + 1;
+                    backtrack = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Visibility of entries: hidden files and an optional rule. Folders stay
+/// listed whatever the rule so that the filter does not prevent browsing.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    pub show_hidden: bool,
+    pub rule: Option<FilterRule>,
+}
+
+impl Filter {
+    pub fn matches(&self, entry: &DirEntryInfo) -> bool {
+        if entry.hidden && !self.show_hidden {
+            return false;
+        }
+        match &self.rule {
+            Some(rule) if !entry.is_dir() => rule.matches(&entry.name),
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn matches_globs() {
+        assert!(glob_match("*.txt", "notes.txt"));
+        assert!(glob_match("*.txt", ".txt"));
+        assert!(!glob_match("*.txt", "notes.txt.bak"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(glob_match("img_????.jpg", "img_0042.jpg"));
+        assert!(!glob_match("img_????.jpg", "img_042.jpg"));
+        assert!(glob_match("**", ""));
+        assert!(glob_match("é?", "éé"));
+        assert!(!glob_match("", "a"));
+    }
+
+    #[test]
+    fn parses_rules() {
+        assert_eq!(FilterRule::parse("  "), None);
+        assert_eq!(FilterRule::parse(", ."), None);
+        assert_eq!(
+            FilterRule::parse(" *.RS "),
+            Some(FilterRule::Glob("*.RS".into()))
+        );
+        let rule = FilterRule::parse("JPG, .png  gif").unwrap();
+        assert_eq!(
+            rule,
+            FilterRule::Extensions(vec!["jpg".into(), "png".into(), "gif".into()])
+        );
+        assert_eq!(rule.label(), ".jpg .png .gif");
+    }
+
+    #[test]
+    fn rules_match_names() {
+        assert!(FilterRule::Glob("*.RS".into()).matches("main.rs"));
+        let extensions = FilterRule::parse("png").unwrap();
+        assert!(extensions.matches("a.PNG"));
+        assert!(!extensions.matches(".png"));
+        assert!(!extensions.matches("png"));
+        let images = FilterRule::Category(Category::Images);
+        assert!(images.matches("photo.JPeG"));
+        assert!(!images.matches("song.mp3"));
+    }
+
+    #[test]
+    fn keeps_folders_and_hides_dot_files() {
+        let dir = TestDir::new();
+        let folder = DirEntryInfo::read(dir.dir("folder")).unwrap();
+        let text = DirEntryInfo::read(dir.file("a.txt", "")).unwrap();
+        let hidden = DirEntryInfo::read(dir.file(".b.txt", "")).unwrap();
+        let mut filter = Filter {
+            show_hidden: false,
+            rule: FilterRule::parse("md"),
+        };
+        assert!(filter.matches(&folder));
+        assert!(!filter.matches(&text));
+
+        filter.rule = FilterRule::parse("*.TXT");
+        assert!(filter.matches(&text));
+        assert!(!filter.matches(&hidden));
+        filter.show_hidden = true;
+        assert!(filter.matches(&hidden));
+    }
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, Local};
+use std::time::SystemTime;
+
+/// Formats a byte count with binary units, e.g. `1.2 MB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024. && unit < UNITS.len() - 1 {
+        value /= 1024.;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Formats a folder's entry count, e.g. `1 item` or `12 items`.
+pub fn format_count(count: usize) -> String {
+    if count == 1 {
+        "1 item".to_string()
+    } else {
+        format!("{} items", count)
+    }
+}
+
+/// Formats how long ago `time` was, e.g. `5 min ago`, `yesterday` or, for
+/// older times, the date.
+pub fn format_relative_time(time: SystemTime) -> String {
+    let Ok(elapsed) = SystemTime::now().duration_since(time) else {
+        // Timestamps in the future are shown as they are.
+        return format_date(time);
+    };
+    let secs = elapsed.as_secs();
+    match secs {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{} min ago", secs / 60),
+        3600..=86_399 => format!("{} h ago", secs / 3600),
+        86_400..=172_799 => "yesterday".to_string(),
+        172_800..=604_799 => format!("{} days ago", secs / 86_400),
+        _ => format_date(time),
+    }
+}
+
+fn format_date(time: SystemTime) -> String {
+    DateTime::<Local>::from(time).format("%Y-%m-%d").to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn formats_sizes_and_counts() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(5 << 40), "5.0 TB");
+        assert_eq!(format_size(2048 << 40), "2048.0 TB");
+        assert_eq!(format_count(1), "1 item");
+        assert_eq!(format_count(0), "0 items");
+    }
+
+    #[test]
+    fn formats_ages() {
+        let ago = |secs| format_relative_time(SystemTime::now() - Duration::from_secs(secs));
+        assert_eq!(ago(5), "just now");
+        assert_eq!(ago(5 * 60), "5 min ago");
+        assert_eq!(ago(3 * 3600), "3 h ago");
+        assert_eq!(ago(30 * 3600), "yesterday");
+        assert_eq!(ago(3 * 86_400), "3 days ago");
+        let old = SystemTime::UNIX_EPOCH + Duration::from_secs(86_400 * 365);
+        assert_eq!(format_relative_time(old), format_date(old));
+        assert_eq!(format_date(old).len(), "1971-01-01".len());
+    }
+}
@@ -1,4 +1,24 @@
-use fs_extra::dir::CopyOptions;
+use file_manager::error::{FileOp, FileOpError};
+use file_manager::entry::DirEntryInfo;
+use file_manager::filter::{Category, Filter, FilterRule};
+use file_manager::format::{format_count, format_relative_time, format_size};
+use file_manager::hexdump::{self, HexFile, ROW_BYTES};
+use file_manager::index::{IndexConfig, IndexReader, IndexService, SearchIndex};
+use file_manager::journal::Journal;
+use file_manager::model::{ClipboardMode, FileManagerModel, ModelError, ModelResult, MAX_TABS};
+use file_manager::paste::{ConflictResolution, PasteJob};
+use file_manager::pdf::{self, PdfError, PdfPages, RenderedPage};
+use file_manager::picture::{self, Frame, ImageView, Transform};
+use file_manager::places::{self, Bookmarks, Place};
+use file_manager::recent::{RecentFile, RecentFiles, Usage};
+use file_manager::search::{LineMatch, Search, SearchMatch, SearchQuery, SearchSummary};
+use file_manager::sort::{SortKey, SortOrder, SortPreferences};
+use file_manager::textfile::{LineIndex, TextError, TextFile};
+use file_manager::toast::{Severity, Toast, Toasts};
+use file_manager::transfer::{CancelToken, TransferProgress};
+use file_manager::trash::{self, TrashedItem};
+use file_manager::watch::{DirChange, DirWatcher};
+use image::DynamicImage;
 use mctk_core::component::{self, Component, RootComponent};
 use mctk_core::event;
 use mctk_core::layout::{Alignment, Dimension, Direction, Size};
@@ -11,111 +31,908 @@ use mctk_core::{lay, msg, rect, size, size_pct, txt, Color};
 use mctk_macros::{component, state_component_impl};
 use std::fs;
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Minimum delay between two progress updates sent by a running transfer.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+/// Characters of the current path shown in the header before its middle
+/// is collapsed.
+const MAX_BREADCRUMB_CHARS: usize = 24;
+/// Characters of a folder name shown on its tab.
+const TAB_LABEL_CHARS: usize = 8;
+/// Width of each pane in dual-pane mode, with a small gap between them.
+const PANE_WIDTH: u32 = 218;
+const PANE_LABEL_HEIGHT: u32 = 28;
+/// Height of a row of the listing, its divider included.
+const ENTRY_ROW_HEIGHT: u32 = 56;
+/// Longest name shown whole in a pane.
+const PANE_NAME_CHARS: usize = 16;
+/// Most matches shown for a search answered by the index.
+const MAX_INDEX_RESULTS: usize = 500;
+/// Lines shown above the line the text viewer is opened at.
+const FILE_LINE_CONTEXT: usize = 3;
+/// Longest file name shown whole above the file viewer.
+const MAX_FILE_NAME_CHARS: usize = 20;
+/// Lines of a text file shown at a time.
+const TEXT_PAGE_LINES: usize = 100;
+/// Last lines shown of a followed text file, as many as fit the viewer.
+const TEXT_FOLLOW_LINES: usize = 13;
+/// How often a followed text file is checked for new lines.
+const TEXT_FOLLOW_INTERVAL: Duration = Duration::from_secs(1);
+/// Rows of a hex dump shown at a time.
+const HEX_PAGE_ROWS: u64 = 24;
+/// Width PDF pages are rendered at to fit the viewer.
+const PDF_FIT_WIDTH: u32 = 440;
+/// Zoom levels of the PDF viewer, relative to the fitted width. They are
+/// stepped through with the toolbar; as in the image viewer, there is no
+/// pinch-to-zoom.
+const PDF_ZOOM_STEPS: [f32; 5] = [1.0, 1.5, 2.0, 3.0, 4.0];
+/// Size of the area the image viewer shows images in.
+const IMAGE_VIEWPORT: (u32, u32) = (440, 300);
+/// Zoom factor of one zoom button press or scroll step.
+const IMAGE_ZOOM_STEP: f32 = 1.5;
+/// Zoom a double tap on a fitted image zooms to.
+const IMAGE_DOUBLE_TAP_ZOOM: f32 = 2.5;
+/// Horizontal drag over a fitted image that turns to the next image.
+const IMAGE_SWIPE_DISTANCE: f32 = 80.;
 
 #[derive(Clone)]
-pub struct FileManagerParams {}
+pub struct FileManagerParams {
+    /// Channel used by background tasks to send messages back to the app.
+    pub app_channel: Option<UnboundedSender<Message>>,
+}
 
 #[derive(Debug, Clone)]
 pub enum Message {
     GoBack,
+    GoForward,
+    GoUp,
+    NavigateTo(PathBuf),
     SelectEntry(PathBuf),
     DeleteSelected,
+    DeletePermanentlySelected,
     CreateFolder,
     RenameSelected,
     CopySelected,
+    CutSelected,
     Paste,
+    SetSelectionMode(bool),
+    ToggleSelection(PathBuf),
+    SelectAll,
+    InvertSelection,
+    OpenTrash,
+    SelectTrashItem(Option<usize>),
+    RestoreTrashItem,
+    DeleteTrashItem,
+    OpenEmptyTrashModal(bool),
+    ConfirmEmptyTrash,
     OpenModal(bool),
+    OpenEntryOptions(PathBuf),
+    ScrollEntries(f32),
     OpenFolerModal(bool),
     OpenActionModal(bool),
     OpenDeleteModal(bool),
     ConfirmAction,
     ConfirmDelete,
     UpdateFolderName(String),
-
+    TransferProgress(TransferProgress),
+    ResolveConflict(ConflictResolution),
+    ToggleConflictApplyAll,
+    CancelPaste,
+    StartPaste,
+    PasteFinished(PasteJob, Arc<ModelResult<Vec<PathBuf>>>),
+    /// A PDF page rendered into a directory at a width.
+    PdfRendered(PathBuf, usize, u32, Arc<Result<RenderedPage, PdfError>>),
+    PdfGoToPage(usize),
+    PdfZoom(usize),
+    ImageDecoded(PathBuf, Result<Arc<DynamicImage>, String>),
+    /// A frame rendered for the image view with a frame directory.
+    ImageRendered(PathBuf, Arc<io::Result<Frame>>),
+    /// Shows the image `n` places after the current one in its folder.
+    ImageStep(isize),
+    ImageTransform(Transform),
+    /// Zooms the image to a zoom level around a point of the viewport.
+    ImageZoomTo(f32, f32, f32),
+    ImageDrag(f32, f32),
+    ImageDragEnd,
+    CancelTransfer,
+    Undo,
+    Redo,
+    DismissToast(u64),
+    OpenToastHistory(bool),
+    ClearToastHistory,
+    DirChanged(PathBuf, Vec<DirChange>),
+    OpenSortModal(bool),
+    SetSortOrder(SortOrder),
+    ToggleHiddenFiles,
+    OpenFilterModal(bool),
+    EditCustomFilter,
+    SetFilterRule(Option<FilterRule>),
+    OpenSearch(bool),
+    UpdateSearchText(String),
+    UpdateSearchExcludes(String),
+    CycleSearchMode,
+    CancelSearch,
+    SearchResults(u64, Vec<SearchMatch>),
+    SearchFinished(u64, SearchSummary),
+    OpenSearchResult(PathBuf),
+    OpenSearchLine(PathBuf, usize),
+    /// The rest of a text file indexed in the background.
+    TextIndexed(PathBuf, Arc<io::Result<LineIndex>>),
+    /// Shows the text file from a 0-based line.
+    TextGoToLine(usize),
+    UpdateTextJump(String),
+    TextJump,
+    TextFollow(bool),
+    TextFollowTick,
+    ToggleHexView,
+    /// Shows the hex dump from the offset of a row.
+    HexGoToOffset(u64),
+    HexSelect(u64),
+    UpdateHexGoTo(String),
+    HexGoTo,
+    OpenPlaces(bool),
+    OpenPlace(PathBuf),
+    ToggleBookmark,
+    SelectBookmark(Option<usize>),
+    RemoveBookmark,
+    OpenRecent(bool),
+    SelectRecent(Option<usize>),
+    RemoveRecent,
+    OpenClearRecentModal(bool),
+    ConfirmClearRecent,
+    NewTab,
+    OpenInNewTab,
+    SwitchTab(usize),
+    CloseTab(usize),
+    OpenTabTransferModal(Option<ClipboardMode>),
+    TransferToTab(usize, ClipboardMode),
+    ToggleDualPane,
+    FocusOtherPane,
+    OpenInOtherPane(PathBuf),
+    SyncPanes,
 }
 
 #[derive(Debug)]
 pub struct FileManagerState {
-    current_path: PathBuf,
-    entries: Vec<PathBuf>,
-    selected_file: Option<PathBuf>,
-    copied_file: Option<PathBuf>,
-    message: String,
+    model: FileManagerModel,
     file_viewer_open: bool,
     view_file: Option<PathBuf>,
-    file_content: Option<String>,
+    /// The text file shown, read a page at a time.
+    text: Option<TextFile>,
+    /// 1-based line the text viewer highlights.
+    file_line: Option<usize>,
+    /// 0-based line the text page shown starts at.
+    text_first_line: usize,
+    text_lines: Vec<String>,
+    /// Line number typed to jump to.
+    text_jump: String,
+    /// Stops the timer of a followed text file.
+    text_follow: Option<CancelToken>,
+    /// The text file is being indexed in the background.
+    text_indexing: bool,
+    /// The file shown as a hex dump, opened when first dumped.
+    hex: Option<HexFile>,
+    /// The hex dump is shown instead of the file's preview.
+    hex_view: bool,
+    /// Offset of the first row shown.
+    hex_offset: u64,
+    hex_bytes: Vec<u8>,
+    /// Selected byte and the bytes from it, for the values they stand for.
+    hex_selection: Option<(u64, Vec<u8>)>,
+    /// Offset typed to go to.
+    hex_goto: String,
     file_is_image: bool,
     file_is_pdf: bool,
+    /// Pages of the PDF shown, rendered as they are needed.
+    pdf: Option<PdfPages>,
+    pdf_page: usize,
+    /// Index into `PDF_ZOOM_STEPS`.
+    pdf_zoom: usize,
+    pdf_error: Option<String>,
+    /// The image shown, once decoded.
+    image_view: Option<ImageView>,
+    image_frame: Option<Frame>,
+    image_error: Option<String>,
+    /// Horizontal drag over the fitted image so far, to detect swipes.
+    image_swipe: f32,
+    /// A frame is being rendered in the background.
+    image_rendering: bool,
+    /// The image was moved or zoomed since that frame was started.
+    image_stale: bool,
     file_no_preview: bool,
     is_modal_open: bool,
     is_folder_options_modal:bool,
+    is_sort_modal_open: bool,
+    is_filter_modal_open: bool,
     is_action_modal_open: bool, // New field for the action modal
     action_modal_title: String,
     is_delete_modal_open: bool, // New field for the delete modal
     delete_item_name: String,
     folder_name: String,
     disable_click: bool,
+    app_channel: Option<UnboundedSender<Message>>,
+    transfer: Option<TransferProgress>,
+    transfer_cancel: Option<CancelToken>,
+    pending_paste: Option<PasteJob>,
+    conflict_apply_all: bool,
+    selection_mode: bool,
+    delete_permanently: bool,
+    trash_view_open: bool,
+    trash_items: Vec<TrashedItem>,
+    selected_trash_item: Option<usize>,
+    is_empty_trash_modal_open: bool,
+    places_open: bool,
+    user_dirs: Vec<Place>,
+    /// Bookmarks shared with GTK applications, reloaded before each change.
+    bookmarks: Bookmarks,
+    /// Index in `bookmarks` of the bookmark whose options are shown.
+    selected_bookmark: Option<usize>,
+    /// Files opened and modified, shared with other applications.
+    recent: RecentFiles,
+    recent_open: bool,
+    recent_files: Vec<RecentFile>,
+    selected_recent: Option<usize>,
+    is_clear_recent_modal_open: bool,
+    /// Copy or move of the selection whose target tab is being chosen.
+    tab_transfer: Option<ClipboardMode>,
+    /// Shows the active tab side by side with another one.
+    dual_pane: bool,
+    /// Tab of the other pane; see [`FileManagerState::other_pane`].
+    other_pane: usize,
+    other_pane_entries: Vec<DirEntryInfo>,
+    /// Scrolling of the listing that does not add up to a whole row yet.
+    scroll_rest: f32,
+    toasts: Toasts<Message>,
+    toast_history_open: bool,
+    /// Directory the watcher was last started for, even if that failed.
+    watched_dir: Option<PathBuf>,
+    watcher: Option<DirWatcher>,
+    search_open: bool,
+    search_query: SearchQuery,
+    search_results: Vec<SearchMatch>,
+    /// Identifies the latest search; results of earlier ones are dropped.
+    search_id: u64,
+    search_cancel: Option<CancelToken>,
+    /// Outcome of the latest search, `None` while it runs.
+    search_summary: Option<SearchSummary>,
+    index: Option<IndexService>,
 }
 
-#[component(State = "FileManagerState")]
-#[derive(Debug, Default)]
-pub struct FileManager {}
-
-pub fn read_entries(path: PathBuf) -> Vec<PathBuf> {
-    let mut entries = Vec::new();
+impl FileManagerState {
+    /// Queues a notice about the result of an operation.
+    fn notify(&mut self, severity: Severity, text: impl Into<String>, action: Option<Message>) {
+        let was_idle = self.toasts.current().is_none();
+        self.toasts.push(severity, text, action);
+        if was_idle {
+            self.start_toast_timer();
+        }
+    }
 
-    if let Ok(dir) = fs::read_dir(&path) {
-        for entry in dir.flatten() {
-            entries.push(entry.path());
+    /// Adds a use of the file at `path` to the recent files.
+    fn record_recent(&self, path: &Path, usage: Usage) {
+        if let Err(e) = self.recent.record(path, usage) {
+            tracing::warn!("Cannot record recent file {:?}: {}", path, e);
         }
-    } else {
-        eprintln!("Failed to read directory: {:?}", path);
     }
 
-    entries.sort_by(|a, b| {
-        let a_is_dir = a.is_dir();
-        let b_is_dir = b.is_dir();
+    /// Rereads the recent files, which other applications change too.
+    fn reload_recent(&mut self) {
+        self.recent_files = match self.recent.list() {
+            Ok(files) => files,
+            Err(e) => {
+                let message = format!("Cannot read recent files: {}", e);
+                self.notify(Severity::Error, message, None);
+                Vec::new()
+            }
+        };
+    }
+
+    /// Tab shown next to the active one in dual-pane mode: the remembered
+    /// one while it is open and not active, otherwise a neighbour.
+    fn other_pane(&self) -> Option<usize> {
+        let count = self.model.tab_count();
+        if !self.dual_pane || count < 2 {
+            return None;
+        }
+        let active = self.model.active_tab();
+        if self.other_pane != active && self.other_pane < count {
+            Some(self.other_pane)
+        } else if active + 1 < count {
+            Some(active + 1)
+        } else {
+            Some(active - 1)
+        }
+    }
 
-        if a_is_dir && !b_is_dir {
-            std::cmp::Ordering::Less
-        } else if !a_is_dir && b_is_dir {
-            std::cmp::Ordering::Greater
+    /// Height of the listing, shared by both panes in dual-pane mode. The
+    /// tab strip, when shown, takes room from it.
+    fn list_height(&self) -> u32 {
+        if self.model.tab_count() > 1 {
+            348
         } else {
-            a.file_name().cmp(&b.file_name())
+            380
+        }
+    }
+
+    /// Height of the listing of the active tab. In dual-pane mode each
+    /// pane starts with a bar naming its folder.
+    fn pane_height(&self) -> u32 {
+        match self.other_pane() {
+            Some(_) => self.list_height() - PANE_LABEL_HEIGHT,
+            None => self.list_height(),
+        }
+    }
+
+    /// Rows of the listing that fit in the pane, the ".." row included.
+    fn visible_rows(&self) -> usize {
+        (self.pane_height() / ENTRY_ROW_HEIGHT) as usize
+    }
+
+    /// Last row that can be shown at the top with the pane still full.
+    fn max_scroll(&self) -> usize {
+        (self.model.entries().len() + 1).saturating_sub(self.visible_rows())
+    }
+
+    /// First row shown. The listing may have shrunk since it was scrolled.
+    fn first_row(&self) -> usize {
+        self.model.scroll().min(self.max_scroll())
+    }
+
+    /// Scrolls the listing of the active tab by `delta` pixels, a whole row
+    /// at a time.
+    fn scroll_entries(&mut self, delta: f32) {
+        let row_height = ENTRY_ROW_HEIGHT as f32;
+        self.scroll_rest += delta;
+        let rows = (self.scroll_rest / row_height).trunc();
+        self.scroll_rest -= rows * row_height;
+        let first = (self.first_row() as f32 + rows).clamp(0., self.max_scroll() as f32);
+        self.model.set_scroll(first as usize);
+    }
+
+    /// Lists the other pane again; it is not watched.
+    fn reload_other_pane(&mut self) {
+        self.other_pane_entries = match self.other_pane() {
+            Some(index) => self.model.tab_entries(index),
+            None => Vec::new(),
+        };
+    }
+
+    /// Moves the directory watch to the current directory. Changes are sent
+    /// back as [`Message::DirChanged`].
+    fn watch_current_dir(&mut self) {
+        let dir = self.model.current_path().to_path_buf();
+        if self.watched_dir.as_ref() == Some(&dir) {
+            return;
+        }
+        self.watcher = None;
+        self.watched_dir = None;
+        let Some(app_channel) = self.app_channel.clone() else {
+            return;
+        };
+        let watched = dir.clone();
+        let on_changes = move |changes| {
+            let _ = app_channel.send(Message::DirChanged(watched.clone(), changes));
+        };
+        // Only a watch that was set up counts, so a failed one is retried
+        // the next time.
+        match DirWatcher::new(dir.clone(), on_changes) {
+            Ok(watcher) => {
+                self.watcher = Some(watcher);
+                self.watched_dir = Some(dir);
+            }
+            Err(e) => tracing::warn!("Cannot watch {:?}: {}", dir, e),
+        }
+    }
+
+    /// Starts searching the current directory for the query, replacing the
+    /// running search. Results are sent back as [`Message::SearchResults`].
+    fn start_search(&mut self) {
+        self.cancel_search();
+        self.search_id += 1;
+        self.search_results.clear();
+        self.search_summary = None;
+        if self.search_query.text.trim().is_empty() {
+            return;
+        }
+        self.search_query.show_hidden = self.model.filter().show_hidden;
+        let root = self.model.current_path().to_path_buf();
+        let search = match Search::new(root, &self.search_query) {
+            Ok(search) => search,
+            Err(e) => {
+                tracing::debug!("Invalid search pattern: {}", e);
+                self.search_summary = Some(SearchSummary::default());
+                self.notify(Severity::Warning, "Invalid regular expression.", None);
+                return;
+            }
+        };
+        let index = self.index.as_ref().map(IndexService::reader);
+        let Some(app_channel) = self.app_channel.clone() else {
+            // No background channel, search on the UI thread.
+            let mut results = Vec::new();
+            let summary = run_search(&search, index, &CancelToken::new(), |matches| {
+                results.extend(matches)
+            });
+            self.search_results = results;
+            self.search_summary = Some(summary);
+            return;
+        };
+        let id = self.search_id;
+        let cancel = CancelToken::new();
+        self.search_cancel = Some(cancel.clone());
+        tokio::task::spawn_blocking(move || {
+            let summary = run_search(&search, index, &cancel, |matches| {
+                let _ = app_channel.send(Message::SearchResults(id, matches));
+            });
+            let _ = app_channel.send(Message::SearchFinished(id, summary));
+        });
+    }
+
+    /// Shows the first page of the PDF at `path`. Its pages are rendered by
+    /// a worker that keeps the document loaded until it is closed.
+    fn open_pdf(&mut self, path: PathBuf) {
+        let mut pages = PdfPages::new(path);
+        if let Some(app_channel) = self.app_channel.clone() {
+            let dir = pages.dir().to_path_buf();
+            let spawned = pages.spawn_worker(move |index, width, result| {
+                let result = Arc::new(result);
+                let _ = app_channel.send(Message::PdfRendered(dir.clone(), index, width, result));
+            });
+            if let Err(e) = spawned {
+                tracing::warn!("Cannot start the PDF renderer: {}", e);
+            }
+        }
+        self.pdf = Some(pages);
+        self.pdf_page = 0;
+        self.pdf_zoom = 0;
+        self.pdf_error = None;
+        self.render_pdf_pages();
+    }
+
+    /// Renders the PDF page shown, then the pages around it so that
+    /// turning to them is instant.
+    fn render_pdf_pages(&mut self) {
+        let width = pdf_width(self.pdf_zoom);
+        let page = self.pdf_page;
+        let Some(pages) = self.pdf.as_mut() else {
+            return;
+        };
+        let indices = [Some(page), page.checked_add(1), page.checked_sub(1)];
+        let mut jobs = Vec::new();
+        for index in indices.into_iter().flatten() {
+            if pages.start(index, width) && !pages.render(index, width) {
+                jobs.push(index);
+            }
+        }
+        // No worker, render on the UI thread.
+        let document = pages.document().to_path_buf();
+        let dir = pages.dir().to_path_buf();
+        for index in jobs {
+            let result = pdf::render_page(&document, index, width, &dir);
+            self.pdf_rendered(&dir, index, width, &result);
+        }
+    }
+
+    fn pdf_rendered(
+        &mut self,
+        dir: &Path,
+        index: usize,
+        width: u32,
+        result: &Result<RenderedPage, PdfError>,
+    ) {
+        let Some(pages) = self.pdf.as_mut().filter(|pages| pages.dir() == dir) else {
+            // The document was closed meanwhile.
+            if let Ok(page) = result {
+                let _ = fs::remove_file(&page.image);
+            }
+            return;
+        };
+        pages.finish(index, width, result);
+        if let Err(e) = result {
+            if index == self.pdf_page && width == pdf_width(self.pdf_zoom) {
+                self.pdf_error = Some(e.to_string());
+            }
+        }
+    }
+
+    /// Decodes the image to show in the viewer, in the background.
+    fn load_image(&mut self, path: PathBuf) {
+        self.image_view = None;
+        self.image_frame = None;
+        self.image_error = None;
+        self.image_swipe = 0.;
+        self.image_stale = false;
+        let decode = |path: &Path| {
+            picture::decode(path)
+                .map(Arc::new)
+                .map_err(|e| e.to_string())
+        };
+        match self.app_channel.clone() {
+            Some(app_channel) => {
+                tokio::task::spawn_blocking(move || {
+                    let result = decode(&path);
+                    let _ = app_channel.send(Message::ImageDecoded(path, result));
+                });
+            }
+            None => {
+                // No background channel, decode on the UI thread.
+                let result = decode(&path);
+                self.image_decoded(&path, &result);
+            }
+        }
+    }
+
+    fn image_decoded(&mut self, path: &Path, result: &Result<Arc<DynamicImage>, String>) {
+        // Another file may be shown by now.
+        if !self.file_viewer_open || self.view_file.as_deref() != Some(path) {
+            return;
+        }
+        match result {
+            Ok(image) => {
+                self.image_view = Some(ImageView::new(image.clone(), IMAGE_VIEWPORT));
+                self.show_image_frame();
+            }
+            Err(e) => self.image_error = Some(e.clone()),
+        }
+    }
+
+    /// Renders the visible part of the image after it was moved or zoomed.
+    /// One frame is rendered at a time; moves made meanwhile are rendered
+    /// together once it is done, so drags do not queue up frames.
+    fn show_image_frame(&mut self) {
+        let Some(view) = self.image_view.as_mut() else {
+            return;
+        };
+        let Some(app_channel) = self.app_channel.clone() else {
+            // No background channel, render on the UI thread.
+            match view.render() {
+                Ok(frame) => self.image_frame = Some(frame),
+                Err(e) => self.image_error = Some(e.to_string()),
+            }
+            return;
+        };
+        if self.image_rendering {
+            self.image_stale = true;
+            return;
+        }
+        self.image_rendering = true;
+        let dir = view.dir().to_path_buf();
+        let job = view.frame_job();
+        tokio::task::spawn_blocking(move || {
+            let result = job.render();
+            let _ = app_channel.send(Message::ImageRendered(dir, Arc::new(result)));
+        });
+    }
+
+    fn image_rendered(&mut self, dir: &Path, result: &io::Result<Frame>) {
+        self.image_rendering = false;
+        match self.image_view.as_mut().filter(|view| view.dir() == dir) {
+            Some(view) => match result {
+                Ok(frame) => {
+                    view.set_frame(frame.clone());
+                    self.image_frame = Some(frame.clone());
+                }
+                Err(e) => self.image_error = Some(e.to_string()),
+            },
+            // The view was closed while rendering, its frame is left over.
+            None => {
+                let _ = fs::remove_dir_all(dir);
+            }
+        }
+        if std::mem::take(&mut self.image_stale) {
+            self.show_image_frame();
+        }
+    }
+
+    /// Shows a text file in the viewer, at the 1-based `line` if given,
+    /// which is highlighted.
+    fn open_text(&mut self, path: &Path, line: Option<usize>) -> Result<(), TextError> {
+        let text = TextFile::open(path)?;
+        self.close_text();
+        self.text = Some(text);
+        self.file_line = line;
+        self.text_first_line = line.map_or(0, |line| line.saturating_sub(1 + FILE_LINE_CONTEXT));
+        self.load_text_page();
+        self.index_text();
+        Ok(())
+    }
+
+    fn close_text(&mut self) {
+        self.stop_following();
+        self.text = None;
+        self.text_indexing = false;
+        self.file_line = None;
+        self.text_lines.clear();
+        self.text_jump.clear();
+    }
+
+    /// Indexes the part of the text file shown that was not indexed on
+    /// opening, in the background. Lines past it are shown once it is done.
+    fn index_text(&mut self) {
+        if self.text_indexing {
+            return;
+        }
+        let Some(text) = self.text.as_ref().filter(|text| !text.is_indexed()) else {
+            return;
+        };
+        let path = text.path().to_path_buf();
+        let index = text.index().clone();
+        match self.app_channel.clone() {
+            Some(app_channel) => {
+                self.text_indexing = true;
+                tokio::task::spawn_blocking(move || {
+                    let result = Arc::new(TextFile::index_rest(&path, index));
+                    let _ = app_channel.send(Message::TextIndexed(path, result));
+                });
+            }
+            None => {
+                // No background channel, index on the UI thread.
+                let result = TextFile::index_rest(&path, index);
+                self.text_indexed(&path, &result);
+            }
+        }
+    }
+
+    fn text_indexed(&mut self, path: &Path, result: &io::Result<LineIndex>) {
+        // Another file may be shown by now.
+        let Some(text) = self.text.as_mut().filter(|text| text.path() == path) else {
+            return;
+        };
+        self.text_indexing = false;
+        match result {
+            Ok(index) => {
+                text.set_index(index.clone());
+                self.load_text_page();
+                // The file may have been reopened or have grown meanwhile.
+                self.index_text();
+            }
+            Err(e) => {
+                let message = format!("Cannot read the whole file: {}", e);
+                self.notify(Severity::Error, message, None);
+            }
+        }
+    }
+
+    /// Reads the lines shown: the page at `text_first_line`, or the last
+    /// lines of a followed file.
+    fn load_text_page(&mut self) {
+        let Some(text) = self.text.as_ref() else {
+            return;
+        };
+        let mut count = TEXT_PAGE_LINES;
+        if self.text_follow.is_some() {
+            count = TEXT_FOLLOW_LINES;
+            self.text_first_line = text.index().line_count().saturating_sub(count);
+        }
+        match text.lines(self.text_first_line, count) {
+            Ok(lines) => self.text_lines = lines,
+            Err(e) => {
+                self.text_lines.clear();
+                let message = format!("Cannot read the file: {}", e);
+                self.notify(Severity::Error, message, None);
+            }
+        }
+    }
+
+    /// Shows the end of the text file as it grows, checking it every
+    /// `TEXT_FOLLOW_INTERVAL` until stopped.
+    fn follow_text(&mut self) {
+        self.stop_following();
+        let cancel = CancelToken::new();
+        self.text_follow = Some(cancel.clone());
+        self.file_line = None;
+        self.load_text_page();
+        let Some(app_channel) = self.app_channel.clone() else {
+            return;
+        };
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(TEXT_FOLLOW_INTERVAL).await;
+                if cancel.is_cancelled() || app_channel.send(Message::TextFollowTick).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+
+    fn stop_following(&mut self) {
+        if let Some(cancel) = self.text_follow.take() {
+            cancel.cancel();
+        }
+    }
+
+    /// Shows the hex dump of the file viewed, opening it unless it is open.
+    fn show_hex(&mut self) -> io::Result<()> {
+        let Some(path) = self.view_file.clone() else {
+            return Ok(());
+        };
+        if self.hex.as_ref().map(HexFile::path) != Some(path.as_path()) {
+            self.hex = Some(HexFile::open(&path)?);
+            self.hex_offset = 0;
+            self.hex_selection = None;
+            self.hex_goto.clear();
+        }
+        self.stop_following();
+        self.hex_view = true;
+        self.load_hex_page();
+        Ok(())
+    }
+
+    fn close_hex(&mut self) {
+        self.hex = None;
+        self.hex_view = false;
+        self.hex_bytes.clear();
+        self.hex_selection = None;
+        self.hex_goto.clear();
+    }
+
+    /// Reads the rows of the hex dump shown.
+    fn load_hex_page(&mut self) {
+        let Some(hex) = self.hex.as_ref() else {
+            return;
+        };
+        match hex.read(self.hex_offset, (HEX_PAGE_ROWS * ROW_BYTES) as usize) {
+            Ok(bytes) => self.hex_bytes = bytes,
+            Err(e) => {
+                self.hex_bytes.clear();
+                let message = format!("Cannot read the file: {}", e);
+                self.notify(Severity::Error, message, None);
+            }
+        }
+    }
+
+    fn select_hex_byte(&mut self, offset: u64) {
+        let Some(hex) = self.hex.as_ref() else {
+            return;
+        };
+        // As many bytes as the widest value read from them.
+        match hex.read(offset, 8) {
+            Ok(bytes) => self.hex_selection = Some((offset, bytes)),
+            Err(e) => {
+                let message = format!("Cannot read the file: {}", e);
+                self.notify(Severity::Error, message, None);
+            }
+        }
+    }
+
+    fn cancel_search(&mut self) {
+        if let Some(cancel) = self.search_cancel.take() {
+            cancel.cancel();
         }
-    });
+    }
+
+    /// Dismisses the notice on screen once its timeout has passed.
+    fn start_toast_timer(&self) {
+        let (Some(toast), Some(app_channel)) = (self.toasts.current(), self.app_channel.clone())
+        else {
+            return;
+        };
+        let (id, timeout) = (toast.id, toast.timeout());
+        tokio::spawn(async move {
+            tokio::time::sleep(timeout).await;
+            let _ = app_channel.send(Message::DismissToast(id));
+        });
+    }
+}
 
-    entries
+/// Looks `search` up in the index if it covers the folder, otherwise walks
+/// the folder. Index results stop at [`MAX_INDEX_RESULTS`].
+fn run_search(
+    search: &Search,
+    index: Option<IndexReader>,
+    cancel: &CancelToken,
+    mut on_matches: impl FnMut(Vec<SearchMatch>),
+) -> SearchSummary {
+    // One more than shown tells whether the results were cut off.
+    let indexed = index.and_then(|index| index.lookup(search, MAX_INDEX_RESULTS + 1));
+    let Some(mut matches) = indexed else {
+        return search.run(cancel, on_matches);
+    };
+    let truncated = matches.len() > MAX_INDEX_RESULTS;
+    matches.truncate(MAX_INDEX_RESULTS);
+    let summary = SearchSummary {
+        matches: matches.len(),
+        indexed: true,
+        truncated,
+        ..Default::default()
+    };
+    on_matches(matches);
+    summary
 }
 
+#[component(State = "FileManagerState")]
+#[derive(Debug, Default)]
+pub struct FileManager {}
+
 #[state_component_impl(FileManagerState)]
 impl Component for FileManager {
     fn init(&mut self) {
         let current_path = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/"));
-        let entries = read_entries(current_path.clone());
 
         self.state = Some(FileManagerState {
-            current_path,
-            entries,
-            selected_file: None,
-            copied_file: None,
-            message: String::new(),
+            model: FileManagerModel::new(current_path)
+                .with_journal(Journal::load(Journal::default_path()))
+                .with_sort_preferences(SortPreferences::load(SortPreferences::default_path())),
             file_viewer_open: false,
             view_file: None,
-            file_content: None,
+            text: None,
+            file_line: None,
+            text_first_line: 0,
+            text_lines: Vec::new(),
+            text_jump: String::new(),
+            text_follow: None,
+            text_indexing: false,
+            hex: None,
+            hex_view: false,
+            hex_offset: 0,
+            hex_bytes: Vec::new(),
+            hex_selection: None,
+            hex_goto: String::new(),
             file_is_image: false,
             file_is_pdf: false,
+            pdf: None,
+            pdf_page: 0,
+            pdf_zoom: 0,
+            pdf_error: None,
+            image_view: None,
+            image_frame: None,
+            image_error: None,
+            image_swipe: 0.,
+            image_rendering: false,
+            image_stale: false,
             file_no_preview: false,
             is_modal_open: false,
             is_folder_options_modal:false,
+            is_sort_modal_open: false,
+            is_filter_modal_open: false,
             is_action_modal_open: false, // Initialize action modal visibility
             action_modal_title: "".to_string(),
             is_delete_modal_open: false, // Initialize delete modal visibility
             delete_item_name: "".to_string(),
             folder_name: "".to_string(),
             disable_click: false,
+            app_channel: None,
+            transfer: None,
+            transfer_cancel: None,
+            pending_paste: None,
+            conflict_apply_all: false,
+            selection_mode: false,
+            delete_permanently: false,
+            trash_view_open: false,
+            trash_items: Vec::new(),
+            selected_trash_item: None,
+            is_empty_trash_modal_open: false,
+            places_open: false,
+            user_dirs: Vec::new(),
+            bookmarks: Bookmarks::load(Bookmarks::default_path()),
+            selected_bookmark: None,
+            recent: RecentFiles::new(RecentFiles::default_path()),
+            recent_open: false,
+            recent_files: Vec::new(),
+            selected_recent: None,
+            is_clear_recent_modal_open: false,
+            tab_transfer: None,
+            dual_pane: false,
+            other_pane: 0,
+            other_pane_entries: Vec::new(),
+            scroll_rest: 0.,
+            toasts: Toasts::new(),
+            toast_history_open: false,
+            watched_dir: None,
+            watcher: None,
+            search_open: false,
+            search_query: SearchQuery::default(),
+            search_results: Vec::new(),
+            search_id: 0,
+            search_cancel: None,
+            search_summary: None,
+            index: Some(IndexService::start(SearchIndex::load(
+                SearchIndex::default_path(),
+                IndexConfig::load(&IndexConfig::default_path()),
+            ))),
         });
 
         self.state_ref();
@@ -125,35 +942,84 @@ impl Component for FileManager {
         if let Some(m) = msg.downcast_ref::<Message>() {
             match m {
                 Message::GoBack => {
-                    if self.state_ref().file_viewer_open {
+                    if self.state_ref().toast_history_open {
+                        self.state_mut().toast_history_open = false;
+                    } else if self.state_ref().file_viewer_open {
                         self.state_mut().file_viewer_open = false;
                         self.state_mut().view_file = None;
-                        self.state_mut().file_content = None;
+                        self.state_mut().close_text();
+                        self.state_mut().close_hex();
                         self.state_mut().file_is_image = false;
                         self.state_mut().file_is_pdf = false;
+                        self.state_mut().pdf = None;
+                        self.state_mut().image_view = None;
+                        self.state_mut().image_frame = None;
                         self.state_mut().file_no_preview = false;
-                    } else {
-                        if let Some(parent) = self.state_ref().current_path.parent() {
-                            self.state_mut().current_path = parent.to_path_buf();
-                            self.state_mut().message = "Went back.".to_string();
-                            self.state_mut().entries =
-                                read_entries(self.state_ref().current_path.clone());
+                    } else if self.state_ref().search_open {
+                        self.update(msg!(Message::OpenSearch(false)));
+                    } else if self.state_ref().trash_view_open {
+                        if self.state_ref().selected_trash_item.is_some() {
+                            self.state_mut().selected_trash_item = None;
+                        } else {
+                            self.state_mut().trash_view_open = false;
+                            self.state_mut().model.refresh();
+                        }
+                    } else if self.state_ref().recent_open {
+                        if self.state_ref().is_clear_recent_modal_open {
+                            self.state_mut().is_clear_recent_modal_open = false;
+                        } else if self.state_ref().selected_recent.is_some() {
+                            self.state_mut().selected_recent = None;
+                        } else {
+                            self.state_mut().recent_open = false;
+                        }
+                    } else if self.state_ref().places_open {
+                        if self.state_ref().selected_bookmark.is_some() {
+                            self.state_mut().selected_bookmark = None;
                         } else {
-                            self.state_mut().message = "No parent directory.".to_string();
+                            self.state_mut().places_open = false;
                         }
+                    } else if self.state_ref().tab_transfer.is_some() {
+                        self.state_mut().tab_transfer = None;
+                    } else if self.state_ref().selection_mode {
+                        self.state_mut().selection_mode = false;
+                        self.state_mut().model.clear_selection();
+                    } else if self.state_mut().model.go_back().is_err() {
+                        // Nothing to go back to, go up instead.
+                        if let Err(e) = self.state_mut().model.go_up() {
+                            self.state_mut().notify(Severity::Info, e.to_string(), None);
+                        }
+                    }
+                    self.state_ref();
+                }
+
+                Message::GoForward => {
+                    if let Err(e) = self.state_mut().model.go_forward() {
+                        self.state_mut().notify(Severity::Info, e.to_string(), None);
+                    }
+                    self.state_ref();
+                }
+
+                Message::GoUp => {
+                    if let Err(e) = self.state_mut().model.go_up() {
+                        self.state_mut().notify(Severity::Info, e.to_string(), None);
                     }
                     self.state_ref();
                 }
 
+                Message::NavigateTo(path) => {
+                    self.state_mut().model.navigate(path.clone());
+                    self.state_ref();
+                }
+
                 Message::SelectEntry(path) => {
+                    // Folders are entered without selecting them, which would
+                    // leave the folder shown selected.
                     if path.is_dir() {
-                        self.state_mut().selected_file = Some(path.clone());
-                        self.state_mut().current_path = path.clone();
-                        self.state_mut().message = "Entered directory.".to_string();
-                        self.state_mut().entries =
-                            read_entries(self.state_ref().current_path.clone());
+                        self.state_mut().model.navigate(path.clone());
                     } else {
-                        self.state_mut().selected_file = Some(path.clone());
+                        self.state_mut().model.select(path.clone());
+                        self.state_ref().record_recent(path, Usage::Opened);
+                        self.state_mut().selected_recent = None;
                         self.state_mut().file_viewer_open = true;
                         self.state_mut().view_file = Some(path.clone());
                         let ext = path
@@ -162,46 +1028,58 @@ impl Component for FileManager {
                             .unwrap_or("")
                             .to_lowercase();
 
-                        self.state_mut().file_is_image =
-                            matches!(ext.as_str(), "png" | "jpg" | "jpeg" | "gif");
+                        self.state_mut().file_is_image = picture::is_image(path);
                         self.state_mut().file_is_pdf = ext == "pdf";
                         self.state_mut().file_no_preview = false;
-                        self.state_mut().file_content = None;
+                        self.state_mut().close_text();
+                        self.state_mut().close_hex();
 
                         if self.state_mut().file_is_image {
-                            // Handle image loading if necessary
+                            self.state_mut().load_image(path.clone());
                         } else if self.state_mut().file_is_pdf {
-                            // Handle PDF loading if necessary
-                        } else if ext == "txt" {
-                            match fs::read_to_string(&path) {
-                                Ok(content) => {
-                                    self.state_mut().file_content = Some(content);
+                            self.state_mut().open_pdf(path.clone());
+                        } else {
+                            match self.state_mut().open_text(path, None) {
+                                Ok(()) => {}
+                                Err(TextError::Binary) => {
+                                    if self.state_mut().show_hex().is_err() {
+                                        self.state_mut().file_no_preview = true;
+                                    }
                                 }
-                                Err(_) => {
+                                Err(TextError::Io(e)) => {
                                     self.state_mut().file_no_preview = true;
+                                    let e = FileOpError::new(FileOp::Read, path, e);
+                                    let (severity, message) =
+                                        error_notice("Error opening file", &e.into());
+                                    self.state_mut().notify(severity, message, None);
                                 }
                             }
-                        } else {
-                            if let Ok(content) = fs::read_to_string(&path) {
-                                self.state_mut().file_content = Some(content);
-                            } else {
-                                self.state_mut().file_no_preview = true;
-                            }
                         }
                     }
                     self.state_ref();
                 }
 
-                Message::DeleteSelected => {
-                    if let Some(selected) = &self.state_ref().selected_file {
-                        self.state_mut().delete_item_name = selected
-                            .file_name()
-                            .unwrap_or_default()
-                            .to_string_lossy()
-                            .to_string();
-                        self.state_mut().is_delete_modal_open = true; // Open delete modal
+                Message::DeleteSelected | Message::DeletePermanentlySelected => {
+                    self.state_mut().delete_permanently =
+                        matches!(m, Message::DeletePermanentlySelected);
+                    let selection = self.state_ref().model.selection().clone();
+                    if selection.is_empty() {
+                        self.state_mut().notify(
+                            Severity::Warning,
+                            ModelError::NothingSelected.to_string(),
+                            None,
+                        );
                     } else {
-                        self.state_mut().message = "No file/folder selected.".to_string();
+                        self.state_mut().delete_item_name = match selection.first() {
+                            Some(selected) if selection.len() == 1 => selected
+                                .file_name()
+                                .unwrap_or_default()
+                                .to_string_lossy()
+                                .to_string(),
+                            _ => format!("{} items", selection.len()),
+                        };
+                        self.state_mut().is_delete_modal_open = true; // Open delete modal
+                        self.state_mut().is_folder_options_modal = false;
                     }
                     self.state_ref();
                 }
@@ -213,9 +1091,15 @@ impl Component for FileManager {
                 }
 
                 Message::RenameSelected => {
-                    if let Some(_selected) = &self.state_ref().selected_file {
+                    if self.state_ref().model.selected().is_some() {
                         self.state_mut().action_modal_title = "Rename".to_string();
                         self.state_mut().is_action_modal_open = true;
+                    } else if self.state_ref().model.selection().len() > 1 {
+                        self.state_mut().notify(
+                            Severity::Warning,
+                            ModelError::MultipleSelected.to_string(),
+                            None,
+                        );
                     }
                     self.state_ref();
                 }
@@ -226,1017 +1110,3925 @@ impl Component for FileManager {
                 }
 
                 Message::CopySelected => {
-                    if let Some(selected) = &self.state_ref().selected_file {
-                        self.state_mut().copied_file = Some(selected.clone());
-                        self.state_mut().message = "Copied to clipboard.".to_string();
-                    } else {
-                        self.state_mut().message = "No file/folder selected.".to_string();
-                    }
+                    let (severity, message) = match self.state_mut().model.copy_selected() {
+                        Ok(paths) if paths.len() > 1 => (
+                            Severity::Info,
+                            format!("Copied {} items to clipboard.", paths.len()),
+                        ),
+                        Ok(_) => (Severity::Info, "Copied to clipboard.".to_string()),
+                        Err(e) => (Severity::Warning, e.to_string()),
+                    };
+                    self.state_mut().notify(severity, message, None);
                     self.state_ref();
                 }
 
-                Message::Paste => {
-                    let state = self.state_mut();
-                    if let Some(copied) = &state.copied_file {
-                        let dest = state.current_path.join(copied.file_name().unwrap());
+                Message::CutSelected => {
+                    let (severity, message) = match self.state_mut().model.cut_selected() {
+                        Ok(paths) if paths.len() > 1 => (
+                            Severity::Info,
+                            format!("Cut {} items to clipboard.", paths.len()),
+                        ),
+                        Ok(_) => (Severity::Info, "Cut to clipboard.".to_string()),
+                        Err(e) => (Severity::Warning, e.to_string()),
+                    };
+                    self.state_mut().notify(severity, message, None);
+                    self.state_ref();
+                }
 
-                        let res: io::Result<()> = if copied.is_dir() {
-                            let opts = CopyOptions::new();
-                            fs_extra::dir::copy(copied, &dest, &opts)
-                                .map(|_| ())
-                                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
-                        } else {
-                            fs::copy(copied, &dest)
-                                .map(|_| ())
-                                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))
-                        };
+                Message::SetSelectionMode(value) => {
+                    self.state_mut().selection_mode = *value;
+                    self.state_mut().model.clear_selection();
+                    self.state_mut().is_folder_options_modal = false;
+                    self.state_ref();
+                }
+
+                Message::ToggleSelection(path) => {
+                    self.state_mut().model.toggle_selection(path.clone());
+                    self.state_ref();
+                }
+
+                Message::SelectAll => {
+                    self.state_mut().model.select_all();
+                    self.state_ref();
+                }
 
-                        match res {
-                            Ok(_) => {
-                                state.message = "Pasted successfully.".to_string();
+                Message::InvertSelection => {
+                    self.state_mut().model.invert_selection();
+                    self.state_ref();
+                }
+
+                Message::Paste | Message::TransferToTab(..) => {
+                    self.state_mut().tab_transfer = None;
+                    if self.state_ref().transfer.is_some() {
+                        self.state_mut().notify(
+                            Severity::Warning,
+                            "A transfer is already running.",
+                            None,
+                        );
+                    } else {
+                        let job = match m {
+                            Message::TransferToTab(tab, mode) => {
+                                self.state_ref().model.transfer_job(*tab, *mode)
+                            }
+                            _ => self.state_ref().model.paste_job(),
+                        };
+                        match job {
+                            Ok(job) => {
+                                self.state_mut().pending_paste = Some(job);
+                                self.state_mut().conflict_apply_all = false;
+                                self.update(msg!(Message::StartPaste));
                             }
                             Err(e) => {
-                                state.message = format!("Error pasting: {}", e);
+                                self.state_mut().notify(Severity::Warning, e.to_string(), None);
                             }
                         }
-                    } else {
-                        state.message = "No file/folder copied.".to_string();
                     }
-
-                    self.state_mut().entries = read_entries(self.state_ref().current_path.clone());
                     self.state_ref();
                 }
 
-                Message::OpenModal(value) => {
-                    self.state_mut().is_modal_open = *value;
-                    if *value {
-                        self.state_mut().disable_click = true; // Disable clicks when modal is open
-                    } else {
-                        self.state_mut().disable_click = false; // Enable clicks when modal is closed
+                Message::ResolveConflict(resolution) => {
+                    let apply_to_all = self.state_ref().conflict_apply_all;
+                    let result = match self.state_mut().pending_paste.as_mut() {
+                        Some(job) => job.resolve(*resolution, apply_to_all),
+                        None => Ok(()),
+                    };
+                    if let Err(e) = result {
+                        let (severity, message) = error_notice("Error pasting", &e);
+                        self.state_mut().notify(severity, message, None);
+                        self.state_mut().pending_paste = None;
                     }
+                    self.update(msg!(Message::StartPaste));
                     self.state_ref();
                 }
 
-                Message::OpenFolerModal(value) => {
-                    self.state_mut().is_folder_options_modal = *value;
+                Message::ToggleConflictApplyAll => {
+                    self.state_mut().conflict_apply_all = !self.state_ref().conflict_apply_all;
                     self.state_ref();
                 }
 
-                Message::OpenActionModal(value) => {
-                    self.state_mut().is_action_modal_open = *value; // Open or close the action modal
-                    self.state_mut().is_modal_open = false;
+                Message::CancelPaste => {
+                    self.state_mut().pending_paste = None;
                     self.state_ref();
-                    
                 }
 
-                Message::OpenDeleteModal(value) => {
-                    self.state_mut().is_delete_modal_open = *value; // Open or close the action modal
-                    self.state_mut().is_modal_open = false;
+                // Starts the pending paste once it has no unresolved conflicts
+                Message::StartPaste => {
+                    let ready = matches!(
+                        &self.state_ref().pending_paste,
+                        Some(job) if job.next_conflict().is_none()
+                    );
+                    if ready {
+                        let job = self.state_mut().pending_paste.take().unwrap();
+                        if let Some(app_channel) = self.state_ref().app_channel.clone() {
+                            let cancel = CancelToken::new();
+                            self.state_mut().transfer = Some(TransferProgress::default());
+                            self.state_mut().transfer_cancel = Some(cancel.clone());
+                            tokio::task::spawn_blocking(move || {
+                                let mut last_sent = Instant::now();
+                                let result = job.run(&cancel, |progress| {
+                                    if last_sent.elapsed() >= PROGRESS_INTERVAL {
+                                        last_sent = Instant::now();
+                                        let _ = app_channel
+                                            .send(Message::TransferProgress(progress.clone()));
+                                    }
+                                });
+                                let _ = app_channel
+                                    .send(Message::PasteFinished(job, Arc::new(result)));
+                            });
+                        } else {
+                            // No background channel, transfer on the UI thread.
+                            let result = job.run(&CancelToken::new(), |_| {});
+                            self.update(msg!(Message::PasteFinished(job, Arc::new(result))));
+                        }
+                    }
                     self.state_ref();
                 }
 
-                Message::ConfirmAction => {
-                    match self.state_ref().action_modal_title.as_str() {
-                        "Create Folder" => {
-                            let folder_name = self.state_ref().folder_name.trim().to_string(); // Get the folder name and trim whitespace
-                            if folder_name.is_empty() {
-                                self.state_mut().message = "Folder name cannot be empty.".to_string();
-                            } else {
-                                let new_folder_path = self.state_ref().current_path.join(&folder_name);
-                                if let Err(e) = fs::create_dir(&new_folder_path) {
-                                    self.state_mut().message = format!("Error creating folder: {}", e);
-                                } else {
-                                    self.state_mut().message = format!("Created folder: {:?}", new_folder_path);
-                                    self.state_mut().entries = read_entries(self.state_ref().current_path.clone());
-                                }
-                            }
+                Message::TransferProgress(progress) => {
+                    if self.state_ref().transfer.is_some() {
+                        self.state_mut().transfer = Some(progress.clone());
+                    }
+                    self.state_ref();
+                }
+
+                Message::PasteFinished(job, result) => {
+                    self.state_mut().transfer = None;
+                    self.state_mut().transfer_cancel = None;
+                    let (severity, message) = match (job.mode, result.as_ref()) {
+                        (ClipboardMode::Copy, Ok(_)) => {
+                            (Severity::Success, "Pasted successfully.".to_string())
                         }
-                        "Rename" => {
-                            let new_name = self.state_ref().folder_name.trim().to_string(); // Get the new name and trim whitespace
-                            if new_name.is_empty() {
-                                self.state_mut().message = "New name cannot be empty.".to_string();
-                            } else if let Some(selected) = self.state_ref().selected_file.clone() {
-                                let new_path = selected.with_file_name(new_name.clone());
-                                if new_path.exists() {
-                                    self.state_mut().message = format!("A file or folder named '{}' already exists.", new_name);
-                                } else {
-                                    if let Err(e) = fs::rename(&selected, &new_path) {
-                                        self.state_mut().message = format!("Error renaming file: {}", e);
-                                    } else {
-                                        self.state_mut().message = format!("Renamed to: {:?}", new_path);
-                                        self.state_mut().entries = read_entries(self.state_ref().current_path.clone());
-                                    }
-                                }
-                            }
+                        (ClipboardMode::Cut, Ok(_)) => {
+                            (Severity::Success, "Moved successfully.".to_string())
+                        }
+                        // Cancelling undoes what was already pasted.
+                        (ClipboardMode::Copy, Err(ModelError::Cancelled)) => (
+                            Severity::Warning,
+                            "Paste cancelled, the copies made were removed.".to_string(),
+                        ),
+                        (ClipboardMode::Cut, Err(ModelError::Cancelled)) => (
+                            Severity::Warning,
+                            "Move cancelled, the moved entries were put back.".to_string(),
+                        ),
+                        (ClipboardMode::Copy, Err(e)) => error_notice("Error pasting", e),
+                        (ClipboardMode::Cut, Err(e)) => error_notice("Error moving", e),
+                    };
+                    if let Ok(pasted) = result.as_ref() {
+                        for path in pasted.iter().filter(|path| path.is_file()) {
+                            self.state_ref().record_recent(path, Usage::Modified);
                         }
-                        _ => {}
                     }
-                    self.state_mut().is_action_modal_open = false; // Close modal after action
-                    self.state_ref(); 
+                    let journaled = self.state_mut().model.finish_paste(job, result);
+                    let action = journaled.then_some(Message::Undo);
+                    self.state_mut().notify(severity, message, action);
+                    self.state_ref();
                 }
-                // Handle deletion confirmation
-                Message::ConfirmDelete => {
-                    if let Some(selected) = self.state_ref().selected_file.clone() {
-                        let result = if selected.is_dir() {
-                            fs::remove_dir_all(&selected)
-                        } else {
-                            fs::remove_file(&selected)
-                        };
 
-                        match result {
-                            Ok(_) => {
-                                self.state_mut().message =
-                                    format!("Deleted: {:?}", self.state_ref().delete_item_name);
-                                self.state_mut().selected_file = None;
-                            }
-                            Err(e) => {
-                                self.state_mut().message = format!("Error deleting: {}", e);
-                            }
+                Message::Undo | Message::Redo => {
+                    let undo = matches!(m, Message::Undo);
+                    let result = if undo {
+                        self.state_mut().model.undo()
+                    } else {
+                        self.state_mut().model.redo()
+                    };
+                    let (severity, message, action) = match result {
+                        Ok(op) if undo => (
+                            Severity::Success,
+                            format!("Undone: {}", op.describe()),
+                            Some(Message::Redo),
+                        ),
+                        Ok(op) => (
+                            Severity::Success,
+                            format!("Redone: {}", op.describe()),
+                            Some(Message::Undo),
+                        ),
+                        Err(e @ (ModelError::NothingToUndo | ModelError::NothingToRedo)) => {
+                            (Severity::Info, e.to_string(), None)
                         }
-                    }
-                    self.state_mut().is_delete_modal_open = false; // Close delete modal
-                    self.state_mut().entries = read_entries(self.state_ref().current_path.clone());
+                        Err(e) => {
+                            let context = if undo { "Error undoing" } else { "Error redoing" };
+                            let (severity, message) = error_notice(context, &e);
+                            (severity, message, None)
+                        }
+                    };
+                    self.state_mut().notify(severity, message, action);
+                    self.state_mut().is_folder_options_modal = false;
                     self.state_ref();
                 }
-            }
-        }
 
-        vec![]
+                Message::DismissToast(id) => {
+                    if self.state_mut().toasts.dismiss(*id) {
+                        self.state_ref().start_toast_timer();
+                    }
+                    self.state_ref();
+                }
+
+                Message::OpenToastHistory(value) => {
+                    self.state_mut().toast_history_open = *value;
+                    self.state_mut().is_folder_options_modal = false;
+                    self.state_ref();
+                }
+
+                Message::ClearToastHistory => {
+                    self.state_mut().toasts.clear_history();
+                    self.state_ref();
+                }
+
+                Message::DirChanged(dir, changes) => {
+                    if dir == self.state_ref().model.current_path() {
+                        self.state_mut().model.apply_changes(changes);
+                        if changes.contains(&DirChange::Gone) {
+                            let name = dir.file_name().unwrap_or_default().to_string_lossy();
+                            let message = format!("'{}' was removed or moved away.", name);
+                            self.state_mut().notify(Severity::Warning, message, None);
+                        }
+                    }
+                    self.state_ref();
+                }
+
+                Message::PdfRendered(dir, index, width, result) => {
+                    self.state_mut().pdf_rendered(dir, *index, *width, result);
+                    self.state_ref();
+                }
+
+                Message::PdfGoToPage(page) => {
+                    let last = self.state_ref().pdf.as_ref().and_then(PdfPages::page_count);
+                    let page = match last {
+                        Some(count) => (*page).min(count.saturating_sub(1)),
+                        None => *page,
+                    };
+                    self.state_mut().pdf_page = page;
+                    self.state_mut().pdf_error = None;
+                    self.state_mut().render_pdf_pages();
+                    self.state_ref();
+                }
+
+                Message::PdfZoom(zoom) => {
+                    self.state_mut().pdf_zoom = (*zoom).min(PDF_ZOOM_STEPS.len() - 1);
+                    self.state_mut().pdf_error = None;
+                    self.state_mut().render_pdf_pages();
+                    self.state_ref();
+                }
+
+                Message::ImageDecoded(path, result) => {
+                    self.state_mut().image_decoded(path, result);
+                    self.state_ref();
+                }
+
+                Message::ImageRendered(dir, result) => {
+                    self.state_mut().image_rendered(dir, result);
+                    self.state_ref();
+                }
+
+                Message::ImageStep(step) => {
+                    if let Some(current) = self.state_ref().view_file.clone() {
+                        let images = picture::folder_images(&current).unwrap_or_default();
+                        let next = images
+                            .iter()
+                            .position(|image| *image == current)
+                            .and_then(|i| i.checked_add_signed(*step))
+                            .and_then(|i| images.get(i));
+                        if let Some(next) = next {
+                            self.state_ref().record_recent(next, Usage::Opened);
+                            self.state_mut().view_file = Some(next.clone());
+                            self.state_mut().load_image(next.clone());
+                        }
+                    }
+                    self.state_ref();
+                }
+
+                Message::ImageTransform(transform) => {
+                    if let Some(view) = self.state_mut().image_view.as_mut() {
+                        view.set_transform(*transform);
+                    }
+                    self.state_mut().show_image_frame();
+                    self.state_ref();
+                }
+
+                Message::ImageZoomTo(zoom, x, y) => {
+                    if let Some(view) = self.state_mut().image_view.as_mut() {
+                        view.zoom_at(*zoom, (*x, *y));
+                    }
+                    self.state_mut().show_image_frame();
+                    self.state_ref();
+                }
+
+                // Pans a zoomed image, swipes a fitted one
+                Message::ImageDrag(dx, dy) => {
+                    let zoomed = self
+                        .state_ref()
+                        .image_view
+                        .as_ref()
+                        .is_some_and(|view| view.zoom() > 1.);
+                    if zoomed {
+                        if let Some(view) = self.state_mut().image_view.as_mut() {
+                            view.pan(*dx, *dy);
+                        }
+                        self.state_mut().show_image_frame();
+                    } else {
+                        self.state_mut().image_swipe += dx;
+                    }
+                    self.state_ref();
+                }
+
+                Message::ImageDragEnd => {
+                    let swipe = self.state_ref().image_swipe;
+                    self.state_mut().image_swipe = 0.;
+                    if swipe.abs() >= IMAGE_SWIPE_DISTANCE {
+                        // Swiping left brings in the next image.
+                        let step = if swipe < 0. { 1 } else { -1 };
+                        self.update(msg!(Message::ImageStep(step)));
+                    }
+                    self.state_ref();
+                }
+
+                Message::CancelTransfer => {
+                    if let Some(cancel) = &self.state_ref().transfer_cancel {
+                        cancel.cancel();
+                    }
+                    self.state_ref();
+                }
+
+                Message::OpenModal(value) => {
+                    self.state_mut().is_modal_open = *value;
+                    if *value {
+                        self.state_mut().disable_click = true; // Disable clicks when modal is open
+                    } else {
+                        self.state_mut().disable_click = false; // Enable clicks when modal is closed
+                    }
+                    self.state_ref();
+                }
+
+                // Selects the entry first, so that its options act on it
+                Message::OpenEntryOptions(path) => {
+                    self.state_mut().model.select(path.clone());
+                    self.update(msg!(Message::OpenModal(true)));
+                    self.state_ref();
+                }
+
+                Message::ScrollEntries(delta) => {
+                    self.state_mut().scroll_entries(*delta);
+                    self.state_ref();
+                }
+
+                Message::OpenFolerModal(value) => {
+                    self.state_mut().is_folder_options_modal = *value;
+                    self.state_ref();
+                }
+
+                Message::OpenSortModal(value) => {
+                    self.state_mut().is_sort_modal_open = *value;
+                    self.state_mut().is_folder_options_modal = false;
+                    self.state_ref();
+                }
+
+                Message::SetSortOrder(order) => {
+                    self.state_mut().model.set_sort_order(*order);
+                    self.state_ref();
+                }
+
+                Message::OpenSearch(value) => {
+                    self.state_mut().search_open = *value;
+                    self.state_mut().is_folder_options_modal = false;
+                    if *value {
+                        self.state_mut().start_search();
+                    } else {
+                        self.state_mut().cancel_search();
+                        self.state_mut().search_results.clear();
+                    }
+                    self.state_ref();
+                }
+
+                Message::UpdateSearchText(text) => {
+                    self.state_mut().search_query.text = text.clone();
+                    self.state_mut().start_search();
+                    self.state_ref();
+                }
+
+                Message::UpdateSearchExcludes(text) => {
+                    self.state_mut().search_query.excludes = text
+                        .split(',')
+                        .map(|pattern| pattern.trim().to_string())
+                        .filter(|pattern| !pattern.is_empty())
+                        .collect();
+                    self.state_mut().start_search();
+                    self.state_ref();
+                }
+
+                Message::CycleSearchMode => {
+                    let mode = self.state_ref().search_query.mode.next();
+                    self.state_mut().search_query.mode = mode;
+                    self.state_mut().start_search();
+                    self.state_ref();
+                }
+
+                Message::CancelSearch => {
+                    self.state_mut().cancel_search();
+                    self.state_ref();
+                }
+
+                Message::SearchResults(id, matches) => {
+                    if *id == self.state_ref().search_id {
+                        self.state_mut()
+                            .search_results
+                            .extend(matches.iter().cloned());
+                    }
+                    self.state_ref();
+                }
+
+                Message::SearchFinished(id, summary) => {
+                    if *id == self.state_ref().search_id {
+                        self.state_mut().search_cancel = None;
+                        self.state_mut().search_summary = Some(*summary);
+                    }
+                    self.state_ref();
+                }
+
+                // Folders are entered and end the search; files open on top of it
+                Message::OpenSearchResult(path) => {
+                    if path.is_dir() {
+                        self.update(msg!(Message::OpenSearch(false)));
+                    }
+                    self.update(msg!(Message::SelectEntry(path.clone())));
+                    self.state_ref();
+                }
+
+                // Opens any text file in the text viewer at `line`
+                Message::OpenSearchLine(path, line) => {
+                    match self.state_mut().open_text(path, Some(*line)) {
+                        Ok(()) => {
+                            self.state_ref().record_recent(path, Usage::Opened);
+                            self.state_mut().file_viewer_open = true;
+                            self.state_mut().view_file = Some(path.clone());
+                            self.state_mut().file_is_image = false;
+                            self.state_mut().file_is_pdf = false;
+                            self.state_mut().pdf = None;
+                            self.state_mut().image_view = None;
+                            self.state_mut().close_hex();
+                            self.state_mut().file_no_preview = false;
+                        }
+                        Err(TextError::Io(e)) => {
+                            let e = FileOpError::new(FileOp::Read, path, e);
+                            let (severity, message) = error_notice("Error opening file", &e.into());
+                            self.state_mut().notify(severity, message, None);
+                        }
+                        Err(e) => {
+                            let message = e.to_string();
+                            self.state_mut().notify(Severity::Warning, message, None);
+                        }
+                    }
+                    self.state_ref();
+                }
+
+                Message::TextIndexed(path, result) => {
+                    self.state_mut().text_indexed(path, result);
+                    self.state_ref();
+                }
+
+                Message::TextGoToLine(line) => {
+                    let last = self
+                        .state_ref()
+                        .text
+                        .as_ref()
+                        .filter(|text| text.is_indexed())
+                        .map(|text| text.index().line_count().saturating_sub(1));
+                    self.state_mut().stop_following();
+                    self.state_mut().text_first_line = last.map_or(*line, |last| (*line).min(last));
+                    self.state_mut().load_text_page();
+                    self.state_ref();
+                }
+
+                Message::UpdateTextJump(text) => {
+                    self.state_mut().text_jump = text.clone();
+                    self.state_ref();
+                }
+
+                // Shows the typed line a few lines down and highlights it
+                Message::TextJump => {
+                    let jump = self.state_ref().text_jump.trim().parse::<usize>();
+                    match jump {
+                        Ok(line) if line > 0 => {
+                            let first = line.saturating_sub(1 + FILE_LINE_CONTEXT);
+                            self.update(msg!(Message::TextGoToLine(first)));
+                            self.state_mut().file_line = Some(line);
+                        }
+                        _ => {
+                            let message = "Enter a line number from 1.";
+                            self.state_mut().notify(Severity::Warning, message, None);
+                        }
+                    }
+                    self.state_ref();
+                }
+
+                Message::TextFollow(follow) => {
+                    if *follow {
+                        self.state_mut().follow_text();
+                    } else {
+                        self.state_mut().stop_following();
+                    }
+                    self.state_ref();
+                }
+
+                Message::ToggleHexView => {
+                    if self.state_ref().hex_view {
+                        self.state_mut().hex_view = false;
+                    } else if let Err(e) = self.state_mut().show_hex() {
+                        let path = self.state_ref().view_file.clone().unwrap_or_default();
+                        let e = FileOpError::new(FileOp::Read, &path, e);
+                        let (severity, message) = error_notice("Error reading file", &e.into());
+                        self.state_mut().notify(severity, message, None);
+                    }
+                    self.state_ref();
+                }
+
+                Message::HexGoToOffset(offset) => {
+                    let len = self.state_ref().hex.as_ref().map_or(0, HexFile::len);
+                    let last_row = hexdump::row_start(len.saturating_sub(1));
+                    self.state_mut().hex_offset = (*offset).min(last_row);
+                    self.state_mut().load_hex_page();
+                    self.state_ref();
+                }
+
+                Message::HexSelect(offset) => {
+                    self.state_mut().select_hex_byte(*offset);
+                    self.state_ref();
+                }
+
+                Message::UpdateHexGoTo(text) => {
+                    self.state_mut().hex_goto = text.clone();
+                    self.state_ref();
+                }
+
+                // Shows the row of the typed offset first and selects its byte
+                Message::HexGoTo => {
+                    let len = self.state_ref().hex.as_ref().map_or(0, HexFile::len);
+                    let offset = hexdump::parse_offset(&self.state_ref().hex_goto);
+                    match offset.filter(|offset| *offset < len) {
+                        Some(offset) => {
+                            self.update(msg!(Message::HexGoToOffset(hexdump::row_start(offset))));
+                            self.state_mut().select_hex_byte(offset);
+                        }
+                        None => {
+                            let message =
+                                format!("Enter an offset below {}, e.g. 4096 or 0x1000.", len);
+                            self.state_mut().notify(Severity::Warning, message, None);
+                        }
+                    }
+                    self.state_ref();
+                }
+
+                Message::TextFollowTick => {
+                    let following = self.state_ref().text_follow.is_some();
+                    let refreshed = match self.state_mut().text.as_mut() {
+                        Some(text) if following => text.refresh(),
+                        _ => Ok(false),
+                    };
+                    match refreshed {
+                        Ok(true) => {
+                            self.state_mut().load_text_page();
+                            self.state_mut().index_text();
+                        }
+                        Ok(false) => {}
+                        Err(e) => {
+                            self.state_mut().stop_following();
+                            let message = format!("Stopped following the file: {}", e);
+                            self.state_mut().notify(Severity::Error, message, None);
+                        }
+                    }
+                    self.state_ref();
+                }
+
+                Message::ToggleHiddenFiles => {
+                    let filter = self.state_ref().model.filter().clone();
+                    self.state_mut().model.set_filter(Filter {
+                        show_hidden: !filter.show_hidden,
+                        ..filter
+                    });
+                    self.state_mut().is_folder_options_modal = false;
+                    self.state_ref();
+                }
+
+                Message::OpenFilterModal(value) => {
+                    self.state_mut().is_filter_modal_open = *value;
+                    self.state_mut().is_folder_options_modal = false;
+                    self.state_ref();
+                }
+
+                Message::EditCustomFilter => {
+                    self.state_mut().action_modal_title = "Filter".to_string();
+                    self.state_mut().is_action_modal_open = true;
+                    self.state_mut().is_filter_modal_open = false;
+                    self.state_ref();
+                }
+
+                Message::SetFilterRule(rule) => {
+                    let filter = self.state_ref().model.filter().clone();
+                    self.state_mut().model.set_filter(Filter {
+                        rule: rule.clone(),
+                        ..filter
+                    });
+                    self.state_mut().is_filter_modal_open = false;
+                    self.state_ref();
+                }
+
+                Message::OpenActionModal(value) => {
+                    self.state_mut().is_action_modal_open = *value; // Open or close the action modal
+                    self.state_mut().is_modal_open = false;
+                    self.state_ref();
+                    
+                }
+
+                Message::OpenDeleteModal(value) => {
+                    self.state_mut().is_delete_modal_open = *value; // Open or close the action modal
+                    self.state_mut().is_modal_open = false;
+                    self.state_ref();
+                }
+
+                Message::ConfirmAction => {
+                    let name = self.state_ref().folder_name.clone();
+                    match self.state_ref().action_modal_title.as_str() {
+                        "Create Folder" => {
+                            let result = self.state_mut().model.create_folder(&name);
+                            let (severity, message) = match &result {
+                                Ok(new_folder_path) => (
+                                    Severity::Success,
+                                    format!("Created folder: {:?}", new_folder_path),
+                                ),
+                                Err(ModelError::EmptyName) => {
+                                    (Severity::Warning, "Folder name cannot be empty.".to_string())
+                                }
+                                Err(e) => error_notice("Error creating folder", e),
+                            };
+                            let action = result.is_ok().then_some(Message::Undo);
+                            self.state_mut().notify(severity, message, action);
+                        }
+                        "Rename" => {
+                            let result = self.state_mut().model.rename_selected(&name);
+                            let (severity, message) = match &result {
+                                Ok(new_path) => {
+                                    if new_path.is_file() {
+                                        self.state_ref().record_recent(new_path, Usage::Modified);
+                                    }
+                                    (Severity::Success, format!("Renamed to: {:?}", new_path))
+                                }
+                                Err(ModelError::EmptyName) => {
+                                    (Severity::Warning, "New name cannot be empty.".to_string())
+                                }
+                                Err(e) => error_notice("Error renaming file", e),
+                            };
+                            let action = result.is_ok().then_some(Message::Undo);
+                            self.state_mut().notify(severity, message, action);
+                        }
+                        "Filter" => match FilterRule::parse(&name) {
+                            Some(rule) => self.update(msg!(Message::SetFilterRule(Some(rule)))),
+                            None => self.state_mut().notify(
+                                Severity::Warning,
+                                "Enter a pattern such as *.txt or extensions such as jpg, png.",
+                                None,
+                            ),
+                        },
+                        _ => {}
+                    }
+                    self.state_mut().is_action_modal_open = false; // Close modal after action
+                    self.state_ref(); 
+                }
+                // Handle deletion confirmation
+                Message::ConfirmDelete => {
+                    let name = self.state_ref().delete_item_name.clone();
+                    let permanently = self.state_ref().delete_permanently;
+                    let result = if permanently {
+                        self.state_mut()
+                            .model
+                            .delete_selected()
+                            .map(|_| format!("Deleted: {:?}", name))
+                    } else {
+                        self.state_mut()
+                            .model
+                            .trash_selected()
+                            .map(|_| format!("Moved {:?} to Trash.", name))
+                    };
+                    match result {
+                        Ok(message) => {
+                            // A permanent delete cannot be undone.
+                            let action = (!permanently).then_some(Message::Undo);
+                            self.state_mut().notify(Severity::Success, message, action);
+                        }
+                        Err(ModelError::NothingSelected) => {}
+                        Err(e) => {
+                            let (severity, message) = error_notice("Error deleting", &e);
+                            self.state_mut().notify(severity, message, None);
+                        }
+                    }
+                    self.state_mut().is_delete_modal_open = false; // Close delete modal
+                    self.state_ref();
+                }
+
+                Message::OpenTrash => {
+                    self.state_mut().trash_items = trash::list();
+                    self.state_mut().trash_view_open = true;
+                    self.state_mut().selected_trash_item = None;
+                    self.state_mut().is_folder_options_modal = false;
+                    self.state_ref();
+                }
+
+                Message::SelectTrashItem(index) => {
+                    self.state_mut().selected_trash_item = *index;
+                    self.state_ref();
+                }
+
+                Message::RestoreTrashItem | Message::DeleteTrashItem => {
+                    let selected = self.state_ref().selected_trash_item;
+                    if let Some(item) = selected.and_then(|i| self.state_ref().trash_items.get(i).cloned()) {
+                        let name = item
+                            .original_path
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .to_string();
+                        let (severity, message) = if matches!(m, Message::RestoreTrashItem) {
+                            match trash::restore(&item) {
+                                Ok(path) => (Severity::Success, format!("Restored: {:?}", path)),
+                                Err(e) => {
+                                    let e = FileOpError::new(FileOp::Restore, &item.original_path, e);
+                                    error_notice("Error restoring", &e.into())
+                                }
+                            }
+                        } else {
+                            match trash::delete_permanently(&item) {
+                                Ok(()) => (Severity::Success, format!("Deleted: {:?}", name)),
+                                Err(e) => {
+                                    let e = FileOpError::new(FileOp::Delete, &item.original_path, e);
+                                    error_notice("Error deleting", &e.into())
+                                }
+                            }
+                        };
+                        self.state_mut().notify(severity, message, None);
+                    }
+                    self.state_mut().selected_trash_item = None;
+                    self.state_mut().trash_items = trash::list();
+                    self.state_ref();
+                }
+
+                Message::OpenEmptyTrashModal(value) => {
+                    self.state_mut().is_empty_trash_modal_open = *value;
+                    self.state_ref();
+                }
+
+                Message::ConfirmEmptyTrash => {
+                    let (severity, message) = match trash::empty() {
+                        Ok(()) => (Severity::Success, "Trash emptied.".to_string()),
+                        Err(e) => error_notice("Error emptying trash", &e.into()),
+                    };
+                    self.state_mut().notify(severity, message, None);
+                    self.state_mut().is_empty_trash_modal_open = false;
+                    self.state_mut().trash_items = trash::list();
+                    self.state_ref();
+                }
+
+                Message::OpenPlaces(value) => {
+                    if *value {
+                        self.state_mut().user_dirs = places::user_dirs();
+                        self.state_mut().bookmarks = Bookmarks::load(Bookmarks::default_path());
+                    }
+                    self.state_mut().places_open = *value;
+                    self.state_mut().selected_bookmark = None;
+                    self.state_mut().is_folder_options_modal = false;
+                    self.state_ref();
+                }
+
+                Message::OpenPlace(path) => {
+                    self.state_mut().selected_bookmark = None;
+                    self.state_mut().selected_recent = None;
+                    if path.is_dir() {
+                        self.state_mut().places_open = false;
+                        self.state_mut().recent_open = false;
+                        self.state_mut().model.navigate(path.clone());
+                    } else {
+                        let message = format!("Folder not found: {:?}", path);
+                        self.state_mut().notify(Severity::Warning, message, None);
+                    }
+                    self.state_ref();
+                }
+
+                Message::ToggleBookmark => {
+                    let dir = self.state_ref().model.current_path().to_path_buf();
+                    let name = dir
+                        .file_name()
+                        .unwrap_or(dir.as_os_str())
+                        .to_string_lossy()
+                        .to_string();
+                    // Pick up bookmarks other applications changed meanwhile.
+                    let mut bookmarks = Bookmarks::load(Bookmarks::default_path());
+                    let (result, done) = if bookmarks.contains(&dir) {
+                        (bookmarks.remove(&dir), "Bookmark removed")
+                    } else {
+                        (bookmarks.add(&dir), "Bookmarked")
+                    };
+                    let (severity, message) = match result {
+                        Ok(()) => (Severity::Success, format!("{}: {:?}", done, name)),
+                        Err(e) => error_notice("Error saving bookmarks", &e.into()),
+                    };
+                    self.state_mut().bookmarks = bookmarks;
+                    self.state_mut().is_folder_options_modal = false;
+                    self.state_mut().notify(severity, message, None);
+                    self.state_ref();
+                }
+
+                Message::NewTab => {
+                    let path = self.state_ref().model.current_path().to_path_buf();
+                    if let Err(e) = self.state_mut().model.open_tab(path) {
+                        let message = e.to_string();
+                        self.state_mut().notify(Severity::Warning, message, None);
+                    }
+                    self.state_mut().is_folder_options_modal = false;
+                    self.state_ref();
+                }
+
+                Message::OpenInNewTab => {
+                    let result = match self.state_ref().model.selected().cloned() {
+                        Some(path) if path.is_dir() => self.state_mut().model.open_tab(path),
+                        _ => Err(ModelError::NothingSelected),
+                    };
+                    if let Err(e) = result {
+                        let message = match e {
+                            ModelError::NothingSelected => {
+                                "Select a folder to open in a new tab.".to_string()
+                            }
+                            e => e.to_string(),
+                        };
+                        self.state_mut().notify(Severity::Warning, message, None);
+                    }
+                    self.state_mut().is_modal_open = false;
+                    self.state_mut().disable_click = false;
+                    self.state_ref();
+                }
+
+                Message::SwitchTab(index) => {
+                    let previous = self.state_ref().model.active_tab();
+                    self.state_mut().model.switch_tab(*index);
+                    // Picking the other pane's tab swaps the panes.
+                    if self.state_ref().other_pane == *index {
+                        self.state_mut().other_pane = previous;
+                    }
+                    self.state_ref();
+                }
+
+                Message::CloseTab(index) => {
+                    if let Err(e) = self.state_mut().model.close_tab(*index) {
+                        self.state_mut().notify(Severity::Info, e.to_string(), None);
+                    } else if self.state_ref().other_pane > *index {
+                        self.state_mut().other_pane -= 1;
+                    }
+                    if self.state_ref().model.tab_count() < 2 {
+                        self.state_mut().dual_pane = false;
+                    }
+                    self.state_ref();
+                }
+
+                Message::ToggleDualPane => {
+                    let dual_pane = !self.state_ref().dual_pane;
+                    if dual_pane && self.state_ref().model.tab_count() == 1 {
+                        // Start with the current folder in both panes.
+                        let path = self.state_ref().model.current_path().to_path_buf();
+                        match self.state_mut().model.open_tab(path) {
+                            Ok(()) => self.state_mut().other_pane = 0,
+                            Err(e) => {
+                                let message = e.to_string();
+                                self.state_mut().notify(Severity::Warning, message, None);
+                            }
+                        }
+                    }
+                    let has_two_tabs = self.state_ref().model.tab_count() > 1;
+                    self.state_mut().dual_pane = dual_pane && has_two_tabs;
+                    self.state_mut().is_folder_options_modal = false;
+                    self.state_ref();
+                }
+
+                Message::FocusOtherPane => {
+                    if let Some(other) = self.state_ref().other_pane() {
+                        let previous = self.state_ref().model.active_tab();
+                        self.state_mut().model.switch_tab(other);
+                        self.state_mut().other_pane = previous;
+                    }
+                    self.state_ref();
+                }
+
+                Message::OpenInOtherPane(path) => {
+                    self.update(msg!(Message::FocusOtherPane));
+                    self.update(msg!(Message::SelectEntry(path.clone())));
+                    self.state_ref();
+                }
+
+                Message::SyncPanes => {
+                    if let Some(other) = self.state_ref().other_pane() {
+                        let path = self.state_ref().model.current_path().to_path_buf();
+                        self.state_mut().model.navigate_tab(other, path);
+                    }
+                    self.state_mut().is_folder_options_modal = false;
+                    self.state_ref();
+                }
+
+                Message::OpenTabTransferModal(mode) => {
+                    if mode.is_some() && self.state_ref().model.selection().is_empty() {
+                        let message = ModelError::NothingSelected.to_string();
+                        self.state_mut().notify(Severity::Warning, message, None);
+                    } else {
+                        self.state_mut().tab_transfer = *mode;
+                    }
+                    self.state_mut().is_modal_open = false;
+                    self.state_mut().disable_click = false;
+                    self.state_ref();
+                }
+
+                Message::OpenRecent(value) => {
+                    if *value {
+                        self.state_mut().reload_recent();
+                    }
+                    self.state_mut().recent_open = *value;
+                    self.state_mut().selected_recent = None;
+                    self.state_ref();
+                }
+
+                Message::SelectRecent(index) => {
+                    self.state_mut().selected_recent = *index;
+                    self.state_ref();
+                }
+
+                Message::RemoveRecent => {
+                    let selected = self.state_ref().selected_recent;
+                    let file = selected.and_then(|i| self.state_ref().recent_files.get(i).cloned());
+                    if let Some(file) = file {
+                        if let Err(e) = self.state_ref().recent.remove(&file.path) {
+                            let message = format!("Cannot update recent files: {}", e);
+                            self.state_mut().notify(Severity::Error, message, None);
+                        }
+                    }
+                    self.state_mut().selected_recent = None;
+                    self.state_mut().reload_recent();
+                    self.state_ref();
+                }
+
+                Message::OpenClearRecentModal(value) => {
+                    self.state_mut().is_clear_recent_modal_open = *value;
+                    self.state_ref();
+                }
+
+                Message::ConfirmClearRecent => {
+                    let (severity, message) = match self.state_ref().recent.clear() {
+                        Ok(()) => (Severity::Success, "Recent files cleared.".to_string()),
+                        Err(e) => (Severity::Error, format!("Cannot clear recent files: {}", e)),
+                    };
+                    self.state_mut().notify(severity, message, None);
+                    self.state_mut().is_clear_recent_modal_open = false;
+                    self.state_mut().reload_recent();
+                    self.state_ref();
+                }
+
+                Message::SelectBookmark(index) => {
+                    self.state_mut().selected_bookmark = *index;
+                    self.state_ref();
+                }
+
+                Message::RemoveBookmark => {
+                    let selected = self.state_ref().selected_bookmark;
+                    let dir = selected
+                        .and_then(|i| self.state_ref().bookmarks.entries().get(i))
+                        .and_then(|bookmark| bookmark.path());
+                    if let Some(dir) = dir {
+                        let mut bookmarks = Bookmarks::load(Bookmarks::default_path());
+                        if let Err(e) = bookmarks.remove(&dir) {
+                            let (severity, message) =
+                                error_notice("Error saving bookmarks", &e.into());
+                            self.state_mut().notify(severity, message, None);
+                        }
+                        self.state_mut().bookmarks = bookmarks;
+                    }
+                    self.state_mut().selected_bookmark = None;
+                    self.state_ref();
+                }
+            }
+        }
+
+        // Whatever navigated, follow the current directory with the watch.
+        let s = self.state_ref();
+        if s.watched_dir.as_deref() != Some(s.model.current_path()) {
+            self.state_mut().watch_current_dir();
+        }
+
+        // The other pane is listed again only when files changed or it
+        // shows another tab, not on every message.
+        let other_pane_changed = matches!(
+            msg.downcast_ref::<Message>(),
+            Some(
+                Message::PasteFinished(..)
+                    | Message::ConfirmAction
+                    | Message::ConfirmDelete
+                    | Message::Undo
+                    | Message::Redo
+                    | Message::RestoreTrashItem
+                    | Message::DeleteTrashItem
+                    | Message::ConfirmEmptyTrash
+                    | Message::DirChanged(..)
+                    | Message::NewTab
+                    | Message::OpenInNewTab
+                    | Message::SwitchTab(_)
+                    | Message::CloseTab(_)
+                    | Message::ToggleDualPane
+                    | Message::FocusOtherPane
+                    | Message::OpenInOtherPane(_)
+                    | Message::SyncPanes
+            )
+        );
+        if self.state_ref().dual_pane && other_pane_changed {
+            self.state_mut().reload_other_pane();
+        }
+
+        vec![]
+    }
+
+    fn view(&self) -> Option<mctk_core::Node> {
+        let s = self.state_ref();
+
+        if s.toast_history_open {
+            return Some(toast_history_view(s));
+        }
+
+        if s.file_viewer_open {
+            return Some(with_toast(file_viewer_view(s), s));
+        }
+
+        if s.search_open {
+            return Some(with_toast(search_view(s), s));
+        }
+
+        if s.trash_view_open {
+            return Some(with_toast(trash_view(s), s));
+        }
+
+        if s.recent_open {
+            return Some(with_toast(recent_view(s), s));
+        }
+
+        if s.places_open {
+            return Some(with_toast(places_view(s), s));
+        }
+
+        let current_path = s.model.current_path().to_path_buf();
+        let entries = s.model.entries().to_vec();
+
+        let mut root = node!(
+            Div::new().bg(Color::BLACK),
+            lay![
+                size_pct: [100],
+                direction: Direction::Column,
+                cross_alignment: Alignment::Stretch,
+                padding: [5., 20., 5., 20.],
+            ]
+        );
+
+        let text_node = if s.selection_mode {
+            let header_title = format!("{} selected", s.model.selection().len());
+            node!(Text::new(txt!(header_title))
+                .style("color", Color::rgb(197.0, 197.0, 197.0))
+                .style("size", 28.0)
+                .style("line_height", 20.)
+                .style("font", "Space Grotesk")
+                .style("font_weight", FontWeight::Normal))
+        } else {
+            breadcrumb_bar(&current_path, s.model.history().can_go_forward())
+        };
+
+            let header_node = node!(
+                Div::new(),
+                lay![
+                    size_pct: [100, 10],
+                    direction: Direction::Row,
+                    axis_alignment: Alignment::Stretch,
+                    cross_alignment: Alignment::Center,
+                    margin: [5., 0., 0., 0.],
+                ]
+            )
+            .push(
+                node!(
+                    Div::new(),
+                    lay![
+                        size_pct: [70, Auto],
+                        axis_alignment: Alignment::Start,
+                        cross_alignment: Alignment::Center,
+                    ],
+                )
+                // .push(node!(
+                //     Div::new(),
+                //     lay![
+                //             size_pct: [20, Auto],
+                //             direction: Direction::Column,
+                //             axis_alignment: Alignment::Start,
+                //         ]
+                // ))
+                // .push(node!(
+                //     Image::new(""),
+                //     lay![
+                //         size:[24,24],
+                //         margin:[20.,0.,0.,10.]
+                //     ]
+                // ))
+                .push(
+                    node!(
+                        Div::new(),
+                        lay![
+                            size_pct: [80, Auto],
+                            direction: Direction::Column,
+                            axis_alignment: Alignment::Start,
+                        ]
+                    )
+                    .push(text_node)
+                    .push(match &s.model.filter().rule {
+                        Some(rule) if !s.selection_mode => filter_chip(rule),
+                        _ => node!(Div::new()),
+                    }),
+                ),
+            )
+            .push(
+                node!(
+                    Div::new(),
+                    lay![
+                        size_pct: [30, Auto],
+                        axis_alignment: Alignment::End,
+                        padding: [0, 0, 0, 8.],
+                    ]
+                )
+                .push(if s.selection_mode {
+                    node!(
+                        Button::new(txt!("Done"))
+                            .style("background_color", Color::TRANSPARENT)
+                            .style("active_color", Color::MID_GREY)
+                            .style("text_color", Color::WHITE)
+                            .style("font_size", 16.0)
+                            .style("line_height", 18.0)
+                            .on_click(Box::new(|| msg!(Message::SetSelectionMode(false)))),
+                        lay![
+                            size: [60, 42],
+                            axis_alignment: Alignment::End,
+                            cross_alignment: Alignment::Center,
+                        ]
+                    )
+                } else {
+                    node!(
+                    IconButton::new("add_icon")
+                        .on_click(Box::new(|| msg!(Message::CreateFolder)))
+                        .icon_type(IconType::Png)
+                        .style(
+                            "size",
+                            Size {
+                                width: Dimension::Px(30.0),
+                                height: Dimension::Px(30.0),
+                            }
+                        )
+                        .style("background_color", Color::TRANSPARENT)
+                        .style("border_color", Color::TRANSPARENT)
+                        .style("active_color", Color::rgba(85., 85., 85., 0.50))
+                        .style("radius", 10.),
+                    lay![
+                        size: [42, 42],
+                        axis_alignment: Alignment::End,
+                        cross_alignment: Alignment::Center,
+                    ]
+                    )
+                })
+                .push(node!(
+                    IconButton::new("dots_icon") // Add the three-dots icon
+                        .on_click(Box::new(|| msg!(Message::OpenFolerModal(true)))) // Open the options modal
+                        .icon_type(IconType::Png)
+                        .style(
+                            "size",
+                            Size {
+                                width: Dimension::Px(30.0),
+                                height: Dimension::Px(30.0),
+                            }
+                        )
+                        .style("background_color", Color::TRANSPARENT)
+                        .style("border_color", Color::TRANSPARENT)
+                        .style("active_color", Color::rgba(85., 85., 85., 0.50))
+                        .style("radius", 10.),
+                    lay![
+                        size: [42, 42],
+                        axis_alignment: Alignment::End,
+                        cross_alignment: Alignment::Center,
+                    ]
+                )),
+            );
+            let dual_pane = s.other_pane().is_some();
+            let pane_width = if dual_pane { PANE_WIDTH } else { 440 };
+            let mut entries_div = node!(
+                Div::new(),
+                lay![
+                    size: [pane_width, Auto],
+                    direction: Direction::Column,
+                    cross_alignment: Alignment::Stretch,
+                ]
+            );
+            // Laid over the listing, or over both panes in dual-pane mode.
+            let mut modals = Vec::new();
+        
+            if s.is_folder_options_modal {
+                let options = if s.selection_mode {
+                    let mut options = vec![
+                        ("Select All", Message::SelectAll),
+                        ("Invert Selection", Message::InvertSelection),
+                        ("Copy", Message::CopySelected),
+                        ("Cut", Message::CutSelected),
+                        ("Paste", Message::Paste),
+                        ("Delete", Message::DeleteSelected),
+                        ("Delete Permanently", Message::DeletePermanentlySelected),
+                    ];
+                    if let Some(other) = s.other_pane() {
+                        options.push((
+                            "Copy to Other Pane",
+                            Message::TransferToTab(other, ClipboardMode::Copy),
+                        ));
+                        options.push((
+                            "Move to Other Pane",
+                            Message::TransferToTab(other, ClipboardMode::Cut),
+                        ));
+                    }
+                    options.push(("Close", Message::OpenFolerModal(false)));
+                    options
+                } else {
+                    let mut options = vec![
+                        ("Search", Message::OpenSearch(true)),
+                        ("Places", Message::OpenPlaces(true)),
+                        ("New Tab", Message::NewTab),
+                        (
+                            if dual_pane {
+                                "Single Pane"
+                            } else {
+                                "Dual Pane"
+                            },
+                            Message::ToggleDualPane,
+                        ),
+                    ];
+                    if dual_pane {
+                        options.push(("Sync Panes", Message::SyncPanes));
+                    }
+                    options.extend([
+                        (
+                            if s.bookmarks.contains(s.model.current_path()) {
+                                "Remove Bookmark"
+                            } else {
+                                "Add Bookmark"
+                            },
+                            Message::ToggleBookmark,
+                        ),
+                        ("Select", Message::SetSelectionMode(true)),
+                        ("Paste", Message::Paste),
+                        ("Cut", Message::CutSelected),
+                        ("Delete", Message::DeleteSelected),
+                        ("Rename", Message::RenameSelected),
+                        ("Delete Permanently", Message::DeletePermanentlySelected),
+                        ("Undo", Message::Undo),
+                        ("Redo", Message::Redo),
+                        ("Sort", Message::OpenSortModal(true)),
+                        ("Filter", Message::OpenFilterModal(true)),
+                        (
+                            if s.model.filter().show_hidden {
+                                "Hide Hidden Files"
+                            } else {
+                                "Show Hidden Files"
+                            },
+                            Message::ToggleHiddenFiles,
+                        ),
+                        ("Trash", Message::OpenTrash),
+                        ("Messages", Message::OpenToastHistory(true)),
+                        ("Close", Message::OpenFolerModal(false)),
+                    ]);
+                    options
+                };
+                modals.push(options_modal("Folder Options", options));
+            }
+
+            if s.is_sort_modal_open {
+                modals.push(sort_modal(s.model.sort_order()));
+            }
+
+            if s.is_filter_modal_open {
+                modals.push(filter_modal(s.model.filter()));
+            }
+
+        let back_row = Btnrow {
+            title: "..".to_string(),
+            value: "".to_string(),
+            icon_1: "fold_icon".to_string(),
+            icon_2: "".to_string(),
+            color: Color::WHITE,
+            on_click: Some(Box::new(move || Message::GoUp)),
+            icon_2_message: Some(Message::OpenModal(true)),
+            is_modal_open: s.is_modal_open, // Pass the modal state
+            is_folder_options_modal: s.is_folder_options_modal
+                || s.is_sort_modal_open
+                || s.is_filter_modal_open
+                || s.tab_transfer.is_some(),
+            is_action_modal_open: s.is_action_modal_open,
+            is_delete_modal_open: s.is_delete_modal_open,
+            disable_click: s.pending_paste.is_some(), // Block clicks while a paste conflict is asked
+            selected: None,
+            scrolls_list: true,
+        };
+
+        // Only the rows that fit are shown, from the tab's scroll position.
+        let first_row = s.first_row();
+        if first_row == 0 {
+            entries_div = entries_div.push(node!(back_row));
+            entries_div = entries_div.push(node!(HDivider {
+                size: 0.5,
+                color: Color::MID_GREY
+            }));
+        }
+
+        let action_modal = node!(
+            Div::new().bg(Color::rgba(29., 29., 29., 1.)).border(
+                Color::rgba(127., 127., 135., 1.),
+                0.,
+                (10., 10., 10., 10.)
+            ),
+            lay![
+                size: [320, 160],
+                direction: Direction::Column,
+                position_type: Absolute,
+                position: [140., 60., 0., 0.],
+                cross_alignment: Alignment::Stretch,
+                axis_alignment: Alignment::Stretch,
+                padding: [15., 15.,  15., 10.]
+            ]
+        )
+        .push(
+            node!(
+                Div::new(),
+                lay![
+                    size_pct: [100, 42],
+                    cross_alignment: Alignment::Center,
+                    axis_alignment: Alignment::Center,
+                    direction: Direction::Column,
+                ]
+            )
+            .push(node!(
+                Text::new(txt!(s.action_modal_title.clone())) // Use the action modal title from state
+                    .style("color", Color::WHITE)
+                    .style("size", 18.)
+                    .style("line_height", 20.)
+                    .style("font", "Space Grotesk")
+                    .style("font_weight", FontWeight::Normal),
+                lay![
+                    size: [Auto],
+                ]
+            )),
+        )
+        .push(
+            // BUTTONS
+            node!(
+                Div::new(),
+                lay![
+                    size_pct: [100, 68],
+                    direction: Direction::Row,
+                    axis_alignment: Alignment::Stretch,
+                    cross_alignment: Alignment::Stretch,
+                ]
+            )
+            .push(node!(
+                Div::new(),
+                lay![
+                    // size_pct: [28, 100],
+                    axis_alignment: Alignment::Start,
+                ]
+            ))
+            .push(
+                node!(
+                    Div::new(),
+                    lay![
+                        size_pct: [100, 100],
+                        axis_alignment: Alignment::Center,
+                        cross_alignment:Alignment::Center,
+                        direction: Direction::Column,
+                    ]
+                )
+                .push(node!(
+                    TextBox::new(Some("".to_string()))
+                        .with_class("text-md border-1 bg-transparent")
+                        .placeholder(if s.action_modal_title == "Filter" {
+                            "*.txt or jpg, png"
+                        } else {
+                            "Enter Name"
+                        })
+                        .on_change(Box::new(|s| msg!(Message::UpdateFolderName(s.to_string())))),
+                    lay![
+                        size_pct: [80, 60],
+                        margin: [0., 0., 10., 0.]
+                    ]
+                ))
+                .push(
+                    node!(
+                        Div::new(),
+                        lay![
+                            size_pct: [100, 50],
+                            axis_alignment: Alignment::Stretch,
+                            direction: Direction::Row,
+                            margin: [0., 0., 10., 0.]
+                        ]
+                    )
+                    .push(node!(
+                        Button::new(txt!("Cancel"))
+                            .style("text_color", Color::WHITE)
+                            .style("background_color", Color::rgba(68., 68., 68., 1.))
+                            .style("active_color", Color::rgba(82., 81., 81., 1.))
+                            .style("font_size", 16.)
+                            .style("line_height", 18.)
+                            .style("radius", 8.)
+                            .on_click(Box::new(move || {
+                                msg!(Message::OpenActionModal(false)) // Close the action modal
+                            })),
+
+                            // .on_click(Box::new(move || {
+                            //     msg!(Message::OpenModal(false)) // Close modal
+                            // })),
+                        lay![
+                            size_pct: [48, 100],
+                            padding: [0., 0., 0., 12.],
+                            cross_alignment: Alignment::Start,
+                            axis_alignment: Alignment::Start,
+                        ]
+                    ))
+                    .push(node!(
+                        Button::new(txt!("Confirm"))
+                            .style("text_color", Color::BLACK)
+                            .style("background_color", Color::WHITE)
+                            .style("active_color", Color::rgba(194., 184., 184., 1.))
+                            .style("font_size", 16.)
+                            .style("line_height", 18.)
+                            .style("radius", 8.)
+                            .on_click(Box::new(move || {
+                                msg!(Message::ConfirmAction) // Trigger confirm action
+                            })),
+                        lay![
+                            size_pct: [48, 100],
+                            padding: [0., 12., 0., 0.],
+                            axis_alignment: Alignment::End,
+                        ]
+                    )),
+                ),
+            ),
+        );
+
+        if s.is_action_modal_open {
+            modals.push(action_modal);
+        }
+
+        let delete_modal = node!(
+            Div::new().bg(Color::rgba(29., 29., 29., 1.)).border(
+                Color::rgba(127., 127., 135., 1.),
+                0.,
+                (10., 10., 10., 10.)
+            ),
+            lay![
+                size: [320, 160],
+                direction: Direction::Column,
+                position_type: Absolute,
+                position: [140., 60., 0., 0.],
+                cross_alignment: Alignment::Stretch,
+                axis_alignment: Alignment::Stretch,
+                padding: [15., 15.,  15., 10.]
+            ]
+        )
+        .push(
+            node!(
+                Div::new(),
+                lay![
+                    size_pct: [100, 72],
+                    cross_alignment: Alignment::Center,
+                    axis_alignment: Alignment::Center,
+                ]
+            )
+            .push(node!(
+                Text::new(txt!(if s.delete_permanently {
+                    format!("Delete {} permanently?", s.delete_item_name)
+                } else {
+                    format!("Move {} to Trash?", s.delete_item_name)
+                }))
+                    .style("color", Color::WHITE)
+                    .style("size", 18.)
+                    .style("line_height", 20.)
+                    .style("font", "Space Grotesk")
+                    .style("font_weight", FontWeight::Normal),
+                lay![
+                    size: [Auto],
+                ]
+            )),
+        )
+        .push(
+            node!(
+                Div::new(),
+                lay![
+                    size_pct: [100, 28],
+                    direction: Direction::Row,
+                    axis_alignment: Alignment::Stretch,
+                    cross_alignment: Alignment::Stretch,
+                ]
+            )
+            .push(node!(
+                Div::new(),
+                lay![
+                    size_pct: [28, 100],
+                    axis_alignment: Alignment::Start,
+                ]
+            ))
+            .push(
+                node!(
+                    Div::new(),
+                    lay![
+                        size_pct: [72, 100],
+                        axis_alignment: Alignment::Stretch,
+                    ]
+                )
+                .push(node!(
+                    Button::new(txt!("Cancel"))
+                        .style("text_color", Color::WHITE)
+                        .style("background_color", Color::rgba(68., 68., 68., 1.))
+                        .style("active_color", Color::rgba(82., 81., 81., 1.))
+                        .style("font_size", 16.)
+                        .style("line_height", 18.)
+                        .style("radius", 8.)
+                        .on_click(Box::new(move || {
+                            msg!(Message::OpenDeleteModal(false)) // Close modal
+                        })),
+                    lay![
+                        size_pct: [48, 100],
+                        padding: [0., 0., 0., 12.],
+                        axis_alignment: Alignment::Start,
+                    ]
+                ))
+                .push(node!(
+                    Button::new(txt!("Confirm"))
+                        .style("text_color", Color::BLACK)
+                        .style("background_color", Color::WHITE)
+                        .style("active_color", Color::rgba(194., 184., 184., 1.))
+                        .style("font_size", 16.)
+                        .style("line_height", 18.)
+                        .style("radius", 8.)
+                        .on_click(Box::new(move || {
+                            msg!(Message::ConfirmDelete) // Trigger confirm action
+                        })),
+                    lay![
+                        size_pct: [48, 100],
+                        padding: [0., 12., 0., 0.],
+                        axis_alignment: Alignment::End,
+                    ]
+                )),
+            ),
+        );
+
+        if s.is_delete_modal_open {
+            modals.push(delete_modal);
+        }
+
+        if let Some(job) = &s.pending_paste {
+            if job.next_conflict().is_some() {
+                modals.push(conflict_modal(job, s.conflict_apply_all));
+            }
+        }
+
+        if s.is_modal_open {
+            let is_dir = s.model.selected().is_some_and(|path| path.is_dir());
+            let mut options = vec![
+                ("Rename", Message::RenameSelected),
+                ("Copy", Message::CopySelected),
+                ("Cut", Message::CutSelected),
+                ("Delete", Message::DeleteSelected),
+                ("Paste", Message::Paste),
+            ];
+            if is_dir {
+                options.push(("Open in New Tab", Message::OpenInNewTab));
+            }
+            if s.model.tab_count() > 1 {
+                options.push((
+                    "Copy to Tab",
+                    Message::OpenTabTransferModal(Some(ClipboardMode::Copy)),
+                ));
+                options.push((
+                    "Move to Tab",
+                    Message::OpenTabTransferModal(Some(ClipboardMode::Cut)),
+                ));
+            }
+            if let Some(other) = s.other_pane() {
+                options.push((
+                    "Copy to Other Pane",
+                    Message::TransferToTab(other, ClipboardMode::Copy),
+                ));
+                options.push((
+                    "Move to Other Pane",
+                    Message::TransferToTab(other, ClipboardMode::Cut),
+                ));
+            }
+            options.push(("Close", Message::OpenModal(false)));
+            let title = if is_dir {
+                "Folder Options"
+            } else {
+                "File Options"
+            };
+            modals.push(options_modal(title, options));
+        }
+
+        if let Some(mode) = s.tab_transfer {
+            let title = match mode {
+                ClipboardMode::Copy => "Copy to",
+                ClipboardMode::Cut => "Move to",
+            };
+            let mut options: Vec<(String, Message)> = s
+                .model
+                .tab_paths()
+                .into_iter()
+                .enumerate()
+                .filter(|(i, _)| *i != s.model.active_tab())
+                .map(|(i, path)| (tab_label(path), Message::TransferToTab(i, mode)))
+                .collect();
+            options.push(("Cancel".to_string(), Message::OpenTabTransferModal(None)));
+            modals.push(options_modal(title, options));
+        }
+
+        if !dual_pane {
+            for modal in modals.drain(..) {
+                entries_div = entries_div.push(modal);
+            }
+        }
+
+        let shown = entries
+            .iter()
+            .enumerate()
+            .skip(first_row.saturating_sub(1))
+            .take(s.visible_rows().saturating_sub(usize::from(first_row == 0)));
+        for (i, entry) in shown {
+            let name = entry.name.clone();
+            let entry_clone = Arc::new(entry.path.clone());
+            let main_icon = entry_icon(entry).to_string();
+            let selection_mode = s.selection_mode;
+
+            // Half the width leaves no room for the size and date.
+            let (title, value) = if dual_pane {
+                (shorten(&name, PANE_NAME_CHARS), String::new())
+            } else {
+                (name.to_string(), entry_summary(entry))
+            };
+            let btn_row = Btnrow {
+                title,
+                value,
+                icon_1: main_icon,
+                icon_2: if selection_mode { "" } else { "dots_icon" }.to_string(),
+                color: Color::WHITE,
+                on_click: Some(Box::new(move || {
+                    if selection_mode {
+                        Message::ToggleSelection((*entry_clone).clone())
+                    } else {
+                        Message::SelectEntry((*entry_clone).clone())
+                    }
+                })),
+                icon_2_message: Some(Message::OpenEntryOptions(entry.path.clone())),
+                is_modal_open: s.is_modal_open, // Pass the modal state
+                is_folder_options_modal: s.is_folder_options_modal
+                || s.is_sort_modal_open
+                || s.is_filter_modal_open
+                || s.tab_transfer.is_some(),
+                is_action_modal_open: s.is_action_modal_open,
+                is_delete_modal_open: s.is_delete_modal_open,
+                disable_click: s.pending_paste.is_some(), // Block clicks while a paste conflict is asked
+                selected: selection_mode.then(|| s.model.selection().contains(&entry.path)),
+                scrolls_list: true,
+            };
+
+            entries_div = entries_div.push(node!(btn_row).key(i as u64));
+            entries_div = entries_div.push(
+                node!(HDivider {
+                    size: 0.5,
+                    color: Color::MID_GREY
+                })
+                .key((i + 1) as u64),
+            );
+        }
+
+        // let create_folder_btn = node!(
+        //     Button::new(txt!("Create Folder"))
+        //         .style("font_size", 15.0)
+        //         .style("line_height", 24.0)
+        //         .style("color", Color::WHITE)
+        //         .on_click(Box::new(|| msg!(Message::CreateFolder))),
+        //     lay![padding: [5., 5., 5., 5.], size_pct: [22, Auto]]
+        // );
+
+        // let rename_btn = node!(
+        //     Button::new(txt!("Rename"))
+        //         .style("font_size", 15.0)
+        //         .style("line_height", 24.0)
+        //         .style("color", Color::WHITE)
+        //         .on_click(Box::new(|| msg!(Message::RenameSelected))),
+        //     lay![padding: [5., 5., 5., 5.], size_pct: [15, Auto]]
+        // );
+
+        // let delete_btn = node!(
+        //     Button::new(txt!("Delete"))
+        //         .style("font_size", 15.0)
+        //         .style("line_height", 24.0)
+        //         .style("color", Color::WHITE)
+        //         .on_click(Box::new(|| msg!(Message::DeleteSelected))),
+        //     lay![margin: [5., 5., 5., 5.], size_pct: [15, Auto]]
+        // );
+
+        // let copy_btn = node!(
+        //     Button::new(txt!("Copy"))
+        //         .style("font_size", 15.0)
+        //         .style("line_height", 24.0)
+        //         .style("color", Color::WHITE)
+        //         .on_click(Box::new(|| msg!(Message::CopySelected))),
+        //     lay![margin: [5., 5., 5., 5.], size_pct: [10, Auto]]
+        // );
+
+        // let paste_btn = node!(
+        //     Button::new(txt!("Paste"))
+        //         .style("font_size", 15.0)
+        //         .style("line_height", 24.0)
+        //         .style("color", Color::WHITE)
+        //         .on_click(Box::new(|| msg!(Message::Paste))),
+        //     lay![margin: [5., 5.,  5., 5.], size_pct: [10, Auto]]
+        // );
+
+        // let back_btn = node!(
+        //     Button::new(txt!("Go Back"))
+        //         .style("font_size", 15.0)
+        //         .style("line_height", 24.0)
+        //         .style("color", Color::DARK_GREY)
+        //         .on_click(Box::new(|| msg!(Message::GoBack))),
+        //     lay![margin: [5., 0., 5., 5.], size_pct: [15, Auto]]
+        // );
+
+        // let mut actions_row = node!(
+        //     Div::new().bg(Color::DARK_GREY),
+        //     lay![
+        //         direction: Direction::Row,
+        //         position_type: Absolute,
+        //         position: [Auto, 0., 0., 0.],
+        //         size_pct: [100, 10],
+        //         cross_alignment: Alignment::End,
+        //         axis_alignment: Alignment::Center,
+        //         padding: [0., 20., 0., 0.]
+        //     ]
+        // );
+
+        // actions_row = actions_row
+        //     .push(create_folder_btn)
+        //     .push(rename_btn)
+        //     .push(delete_btn)
+        //     .push(copy_btn)
+        //     .push(paste_btn)
+        //     .push(back_btn);
+
+        let show_tabs = s.model.tab_count() > 1;
+        let list_height = s.list_height();
+        let pane_height = s.pane_height();
+        // The rows scroll themselves, see `Btnrow::scrolls_list`.
+        let mut scrollable_section = node!(
+            Div::new(),
+            lay![
+                size: [pane_width, pane_height],
+                direction: Direction::Column,
+                cross_alignment: Alignment::Stretch,
+            ]
+        );
+        scrollable_section = scrollable_section.push(entries_div);
+
+        if let Some(other) = s.other_pane() {
+            let active_pane = node!(
+                Div::new(),
+                lay![
+                    size: [PANE_WIDTH, list_height],
+                    direction: Direction::Column,
+                ]
+            )
+            .push(pane_label(&current_path, true))
+            .push(scrollable_section);
+            let other_pane = other_pane_view(s, other, pane_height);
+            // Panes keep the order of their tabs.
+            let (left, right) = if other < s.model.active_tab() {
+                (other_pane, active_pane)
+            } else {
+                (active_pane, other_pane)
+            };
+            scrollable_section = node!(
+                Div::new(),
+                lay![
+                    size: [440, list_height],
+                    direction: Direction::Row,
+                    axis_alignment: Alignment::Stretch,
+                ]
+            )
+            .push(left.key(0))
+            .push(node!(Div::new(), lay![size: [440 - 2 * PANE_WIDTH, list_height]]).key(1))
+            .push(right.key(2));
+            for (i, modal) in modals.into_iter().enumerate() {
+                scrollable_section = scrollable_section.push(modal.key(3 + i as u64));
+            }
+        }
+
+        if show_tabs {
+            root = root.push(tab_strip(&s.model));
+        }
+        root = root.push(header_node);
+        root = root.push(node!(HDivider {
+            size: 1.,
+            color: Color::MID_GREY
+        }));
+        root = root.push(scrollable_section);
+        if let Some(progress) = &s.transfer {
+            root = root.push(transfer_progress_view(progress));
+        } else {
+            root = with_toast(root, s);
+        }
+        // root = root.push(actions_row);
+        Some(root)
+    }
+}
+
+impl RootComponent<FileManagerParams> for FileManager {
+    fn root(&mut self, _window: &dyn std::any::Any, app_params: &dyn std::any::Any) {
+        if let Some(params) = app_params.downcast_ref::<FileManagerParams>() {
+            self.state_mut().app_channel = params.app_channel.clone();
+            self.state_mut().watch_current_dir();
+        }
+    }
+}
+
+pub struct Btnrow {
+    pub title: String,
+    pub value: String,
+    pub icon_1: String,
+    pub icon_2: String,
+    pub color: Color,
+    pub on_click: Option<Box<dyn Fn() -> Message + Send + Sync>>,
+    pub icon_2_message: Option<Message>, // Sent by icon 2, `OpenModal(true)` if unset
+    pub is_modal_open: bool,
+    pub is_action_modal_open: bool,
+    pub is_delete_modal_open: bool,
+    is_folder_options_modal:bool,
+    pub disable_click: bool,
+    pub selected: Option<bool>, // Checkbox state, `None` outside selection mode
+    pub scrolls_list: bool,     // Scrolls and drags move the listing by rows
+}
+
+impl std::fmt::Debug for Btnrow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Btnrow ")
+            .field("title", &self.title)
+            .field("icon_1", &self.icon_1)
+            .field("icon_2", &self.icon_2)
+            .finish()
+    }
+}
+// self.disable_click 
+
+impl Component for Btnrow {
+    fn on_click(&mut self, event: &mut event::Event<event::Click>) {
+        // Check if the modal is open
+        if self.is_modal_open || self.is_action_modal_open || self.is_delete_modal_open || self.is_folder_options_modal || self.disable_click
+        {
+            return; // Ignore the click if the modal is open
+        }
+        
+
+        if let Some(f) = &self.on_click {
+            event.emit(Box::new(f()));
+        }
+    }
+
+    fn on_scroll(&mut self, event: &mut event::Event<event::Scroll>) {
+        if self.scrolls_list {
+            let delta = event.logical_delta();
+            event.stop_bubbling();
+            event.emit(msg!(Message::ScrollEntries(delta.y)));
+        }
+    }
+
+    fn on_drag_start(&mut self, event: &mut event::Event<event::DragStart>) {
+        if self.scrolls_list {
+            event.stop_bubbling();
+        }
+    }
+
+    fn on_drag(&mut self, event: &mut event::Event<event::Drag>) {
+        if self.scrolls_list {
+            let delta = event.logical_delta();
+            event.emit(msg!(Message::ScrollEntries(-delta.y)));
+        }
+    }
+
+    fn view(&self) -> Option<node::Node> {
+        let icon_2_message = self
+            .icon_2_message
+            .clone()
+            .unwrap_or(Message::OpenModal(true));
+
+        let text_node = node!(Text::new(txt!(self.title.clone()))
+            .style("color", self.color)
+            .style("size", 20.0)
+            .style("line_height", 20.)
+            .style("font", "Space Grotesk")
+            .style("font_weight", FontWeight::Normal));
+
+        let value_node = node!(Text::new(txt!(self.value.clone()))
+            .style("color", Color::rgb(197.0, 197.0, 197.0))
+            .style("size", 14.0)
+            .style("line_height", 16.)
+            .style("font", "Space Grotesk")
+            .style("font_weight", FontWeight::Normal));
+
+        Some(
+            node!(
+                Div::new(),
+                lay![
+                    padding: [10, 10, 10, 10],
+                    size_pct: [100, Auto],
+                    direction: Direction::Row,
+                    axis_alignment: Alignment::Stretch,
+                    cross_alignment: Alignment::Center,
+                ]
+            )
+            .push(
+                node!(
+                    Div::new(),
+                    lay![
+                        direction: Direction::Row,
+                        cross_alignment: Alignment::Center,
+                        axis_alignment: Alignment::Start,
+                    ]
+                )
+                .push(match self.selected {
+                    Some(selected) => node!(
+                        Div::new()
+                            .bg(if selected { Color::WHITE } else { Color::TRANSPARENT })
+                            .border(Color::WHITE, 1.5, (4., 4., 4., 4.)),
+                        lay![size: [20, 20], margin: [0., 0., 0., 10.]]
+                    ),
+                    None => node!(Div::new(), lay![size: [0, 0]]),
+                })
+                .push(node!(
+                    Image::new(self.icon_1.clone()),
+                    lay![
+                        size:[24,24],
+                        margin:[20.,0.,0.,10.]
+                    ]
+                ))
+                .push(text_node),
+            )
+            .push(
+                node!(
+                    Div::new(),
+                    lay![
+                        direction: Direction::Row,
+                        axis_alignment: Alignment::End,
+                        cross_alignment:Alignment::Center,
+                    ]
+                )
+                .push(value_node)
+                .push(node!(
+                    IconButton::new(self.icon_2.clone())
+                        .on_click(Box::new(move || Box::new(icon_2_message.clone())))
+                        .icon_type(IconType::Png)
+                        .style(
+                            "size",
+                            Size {
+                                width: Dimension::Px(34.0),
+                                height: Dimension::Px(34.0)
+                            }
+                        ),
+                    lay![size:[34,34], margin:[10.,0.,0.,0.]]
+                )),
+            ),
+        )
+    }
+}
+
+// File viewer layout
+// Button of a viewer toolbar, greyed out without a message to send
+fn toolbar_button(label: &str, message: Option<Message>) -> node::Node {
+    let mut button = Button::new(txt!(label.to_string()))
+        .style("background_color", Color::TRANSPARENT)
+        .style("active_color", Color::MID_GREY)
+        .style(
+            "text_color",
+            if message.is_some() {
+                Color::WHITE
+            } else {
+                Color::rgb(90., 90., 90.)
+            },
+        )
+        .style("font_size", 18.0)
+        .style("line_height", 20.0);
+    if let Some(message) = message {
+        button = button.on_click(Box::new(move || msg!(message.clone())));
+    }
+    node!(button, lay![size: [44, 36]])
+}
+
+// Image viewer: a bar to turn, flip, zoom and go through the folder's
+// images, above the image
+fn image_viewer(s: &FileManagerState) -> node::Node {
+    let button = toolbar_button;
+    let view = s.image_view.as_ref();
+    let transform = view.map(ImageView::transform);
+    let zoom = view.map_or(1., ImageView::zoom);
+    let center = (IMAGE_VIEWPORT.0 as f32 / 2., IMAGE_VIEWPORT.1 as f32 / 2.);
+    let zoom_to = |zoom: f32| Message::ImageZoomTo(zoom, center.0, center.1);
+
+    let toolbar = node!(
+        Div::new(),
+        lay![
+            size: [440, 36],
+            direction: Direction::Row,
+            cross_alignment: Alignment::Center,
+            axis_alignment: Alignment::Stretch,
+        ]
+    )
+    .push(button("‹", Some(Message::ImageStep(-1))))
+    .push(button(
+        "⟲",
+        transform.map(|t| Message::ImageTransform(t.rotate_counter_clockwise())),
+    ))
+    .push(button(
+        "⟳",
+        transform.map(|t| Message::ImageTransform(t.rotate_clockwise())),
+    ))
+    .push(button(
+        "⇋",
+        transform.map(|t| Message::ImageTransform(t.flip_horizontal())),
+    ))
+    .push(button(
+        "⇅",
+        transform.map(|t| Message::ImageTransform(t.flip_vertical())),
+    ))
+    .push(button(
+        "−",
+        (view.is_some() && zoom > 1.).then(|| zoom_to(zoom / IMAGE_ZOOM_STEP)),
+    ))
+    .push(button(
+        "+",
+        (view.is_some() && zoom < picture::MAX_ZOOM).then(|| zoom_to(zoom * IMAGE_ZOOM_STEP)),
+    ))
+    .push(button("Fit", (zoom > 1.).then(|| zoom_to(1.))))
+    .push(button("›", Some(Message::ImageStep(1))));
+
+    let message = match (&s.image_frame, &s.image_error) {
+        (_, Some(error)) => Some(error.clone()),
+        (None, None) => Some("Loading…".to_string()),
+        (Some(_), None) => None,
+    };
+    let surface = ImageSurface {
+        frame: s.image_frame.clone(),
+        message,
+        zoom,
+    };
+
+    node!(
+        Div::new(),
+        lay![
+            size: [440, Auto],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Center,
+        ]
+    )
+    .push(toolbar)
+    .push(node!(
+        surface,
+        lay![size: [IMAGE_VIEWPORT.0, IMAGE_VIEWPORT.1]]
+    ))
+}
+
+/// Shows the frame of the image viewer. Drags pan a zoomed image and swipe
+/// a fitted one, double taps zoom in and out, scrolling zooms in steps.
+/// There is no pinch-to-zoom: mctk delivers single pointer events only.
+#[derive(Debug)]
+pub struct ImageSurface {
+    pub frame: Option<Frame>,
+    /// Shown instead of the frame while loading or after an error.
+    pub message: Option<String>,
+    pub zoom: f32,
+}
+
+impl Component for ImageSurface {
+    fn on_drag_start(&mut self, event: &mut event::Event<event::DragStart>) {
+        event.stop_bubbling();
+    }
+
+    fn on_drag(&mut self, event: &mut event::Event<event::Drag>) {
+        let delta = event.logical_delta();
+        event.emit(msg!(Message::ImageDrag(delta.x, delta.y)));
+    }
+
+    fn on_drag_end(&mut self, event: &mut event::Event<event::DragEnd>) {
+        event.emit(msg!(Message::ImageDragEnd));
+    }
+
+    fn on_double_click(&mut self, event: &mut event::Event<event::DoubleClick>) {
+        let position = event.relative_logical_position();
+        let zoom = if self.zoom > 1. {
+            1.
+        } else {
+            IMAGE_DOUBLE_TAP_ZOOM
+        };
+        event.emit(msg!(Message::ImageZoomTo(zoom, position.x, position.y)));
+    }
+
+    fn on_scroll(&mut self, event: &mut event::Event<event::Scroll>) {
+        let position = event.relative_logical_position();
+        let zoom = if event.logical_delta().y < 0. {
+            self.zoom * IMAGE_ZOOM_STEP
+        } else {
+            self.zoom / IMAGE_ZOOM_STEP
+        };
+        event.emit(msg!(Message::ImageZoomTo(zoom, position.x, position.y)));
+    }
+
+    fn view(&self) -> Option<node::Node> {
+        let mut surface = node!(
+            Div::new().bg(Color::BLACK),
+            lay![
+                size: [IMAGE_VIEWPORT.0, IMAGE_VIEWPORT.1],
+                direction: Direction::Column,
+                cross_alignment: Alignment::Center,
+                axis_alignment: Alignment::Center,
+            ]
+        );
+        if let Some(message) = &self.message {
+            surface = surface.push(node!(Text::new(txt!(message.clone()))
+                .style("color", Color::WHITE)
+                .style("size", 18.0)
+                .style("line_height", 24.0)
+                .style("font", "Space Grotesk")));
+        } else if let Some(frame) = &self.frame {
+            surface = surface.push(node!(
+                Image::new(frame.image.to_string_lossy().to_string()),
+                lay![size: [frame.width, frame.height]]
+            ));
+        }
+        Some(surface)
+    }
+}
+
+/// Width PDF pages are rendered at for the zoom level `zoom`.
+fn pdf_width(zoom: usize) -> u32 {
+    (PDF_FIT_WIDTH as f32 * PDF_ZOOM_STEPS[zoom]) as u32
+}
+
+// PDF viewer: a bar to turn pages and zoom, above the page shown
+fn pdf_view(s: &FileManagerState) -> node::Node {
+    let width = pdf_width(s.pdf_zoom);
+    let pages = s.pdf.as_ref();
+    let page_count = pages.and_then(PdfPages::page_count);
+    let page = s.pdf_page;
+
+    let button = toolbar_button;
+    let counter = match page_count {
+        Some(count) => format!("Page {} of {}", page + 1, count),
+        None => format!("Page {}", page + 1),
+    };
+    let zoom = format!("{}%", (PDF_ZOOM_STEPS[s.pdf_zoom] * 100.) as u32);
+    let has_next = page_count.is_none_or(|count| page + 1 < count);
+    let max_zoom = PDF_ZOOM_STEPS.len() - 1;
+
+    let toolbar = node!(
+        Div::new(),
+        lay![
+            size: [440, 36],
+            direction: Direction::Row,
+            cross_alignment: Alignment::Center,
+            axis_alignment: Alignment::Stretch,
+        ]
+    )
+    .push(button("‹", page.checked_sub(1).map(Message::PdfGoToPage)))
+    .push(node!(
+        Text::new(txt!(counter))
+            .style("color", Color::rgb(197.0, 197.0, 197.0))
+            .style("size", 14.0)
+            .style("line_height", 16.)
+            .style("font", "Space Grotesk"),
+        lay![size: [120, Auto]]
+    ))
+    .push(button(
+        "›",
+        has_next.then_some(Message::PdfGoToPage(page + 1)),
+    ))
+    .push(button("−", s.pdf_zoom.checked_sub(1).map(Message::PdfZoom)))
+    .push(node!(
+        Text::new(txt!(zoom))
+            .style("color", Color::rgb(197.0, 197.0, 197.0))
+            .style("size", 14.0)
+            .style("line_height", 16.)
+            .style("font", "Space Grotesk"),
+        lay![size: [50, Auto]]
+    ))
+    .push(button(
+        "+",
+        (s.pdf_zoom < max_zoom).then_some(Message::PdfZoom(s.pdf_zoom + 1)),
+    ))
+    .push(button(
+        "Fit",
+        (s.pdf_zoom > 0).then_some(Message::PdfZoom(0)),
+    ));
+
+    let body = match (pages.and_then(|pages| pages.get(page, width)), &s.pdf_error) {
+        (Some(rendered), _) => {
+            let image = rendered.image.to_string_lossy().to_string();
+            // Keyed by page and zoom so that each starts scrolled to the top.
+            node!(
+                Scrollable::new(size!(440, 300)),
+                lay![
+                    size: [440, 300],
+                    direction: Direction::Column,
+                    cross_alignment: Alignment::Start,
+                ]
+            )
+            .key((page * PDF_ZOOM_STEPS.len() + s.pdf_zoom) as u64)
+            .push(node!(
+                Image::new(image),
+                lay![size: [rendered.width, rendered.height]]
+            ))
+        }
+        (None, error) => {
+            let message = match error {
+                Some(error) => error.clone(),
+                None => "Rendering page…".to_string(),
+            };
+            node!(
+                Text::new(txt!(message))
+                    .style("color", Color::WHITE)
+                    .style("size", 18.0)
+                    .style("line_height", 24.0)
+                    .style("font", "Space Grotesk"),
+                lay![margin: [20., 0., 0., 0.]]
+            )
+        }
+    };
+
+    node!(
+        Div::new(),
+        lay![
+            size: [440, Auto],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Center,
+        ]
+    )
+    .push(toolbar)
+    .push(body)
+}
+
+// Text viewer: a bar to page through the file above the lines of the page
+// with their numbers, and a bar with the file's format, jumping to a line
+// and following the file below
+fn text_view(s: &FileManagerState, text: &TextFile) -> node::Node {
+    let index = text.index();
+    let line_count = index.line_count();
+    let indexed = text.is_indexed();
+    let first = s.text_first_line;
+    let following = s.text_follow.is_some();
+
+    let button = toolbar_button;
+    let counter = if !s.text_lines.is_empty() {
+        let more = if indexed { "" } else { "+" };
+        let last = first + s.text_lines.len();
+        format!("Lines {}–{} of {}{}", first + 1, last, line_count, more)
+    } else if indexed {
+        "No lines".to_string()
+    } else {
+        "Reading…".to_string()
+    };
+    let has_next = first + TEXT_PAGE_LINES < line_count;
+    let last_page = line_count.saturating_sub(TEXT_PAGE_LINES);
+
+    let toolbar = node!(
+        Div::new(),
+        lay![
+            size: [440, 36],
+            direction: Direction::Row,
+            cross_alignment: Alignment::Center,
+            axis_alignment: Alignment::Stretch,
+        ]
+    )
+    .push(button("«", (first > 0).then_some(Message::TextGoToLine(0))))
+    .push(button(
+        "‹",
+        (first > 0).then_some(Message::TextGoToLine(first.saturating_sub(TEXT_PAGE_LINES))),
+    ))
+    .push(node!(
+        Text::new(txt!(counter))
+            .style("color", Color::rgb(197.0, 197.0, 197.0))
+            .style("size", 14.0)
+            .style("line_height", 16.)
+            .style("font", "Space Grotesk"),
+        lay![size: [264, Auto]]
+    ))
+    .push(button(
+        "›",
+        has_next.then_some(Message::TextGoToLine(first + TEXT_PAGE_LINES)),
+    ))
+    .push(button(
+        "»",
+        (indexed && first < last_page).then_some(Message::TextGoToLine(last_page)),
+    ));
+
+    // Line numbers are as wide as the largest one.
+    let digits = (first + s.text_lines.len()).max(1).to_string().len() as u32;
+    let number_width = digits * 9 + 10;
+    let line_width = 420 - number_width;
+    let mut lines = node!(
+        Scrollable::new(size!(440, 270)),
+        lay![
+            size: [440, 270],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+        ]
+    )
+    .key(first as u64);
+    for (i, line) in s.text_lines.iter().enumerate() {
+        let number = first + i + 1;
+        let color = if s.file_line == Some(number) {
+            Color::rgb(255., 213., 79.)
+        } else {
+            Color::WHITE
+        };
+        lines = lines.push(
+            node!(
+                Div::new(),
+                lay![
+                    direction: Direction::Row,
+                    cross_alignment: Alignment::Start,
+                    margin: [0., 5., 0., 5.],
+                ]
+            )
+            .push(node!(
+                Text::new(txt!(number.to_string()))
+                    .style("color", Color::rgb(120., 120., 120.))
+                    .style("size", 14.0)
+                    .style("line_height", 20.0)
+                    .style("font", "Space Grotesk"),
+                lay![size: [number_width, Auto]]
+            ))
+            .push(node!(
+                Text::new(txt!(line.clone()))
+                    .style("color", color)
+                    .style("size", 14.0)
+                    .style("line_height", 20.0)
+                    .style("font", "Space Grotesk"),
+                lay![size: [line_width, Auto]]
+            )),
+        );
+    }
+
+    let format = format!(
+        "{} · {}",
+        text.format().encoding.name(),
+        index.line_ending().label()
+    );
+    let mut status = node!(
+        Div::new(),
+        lay![
+            size: [440, 40],
+            direction: Direction::Row,
+            cross_alignment: Alignment::Center,
+            axis_alignment: Alignment::Stretch,
+        ]
+    )
+    .push(node!(
+        Text::new(txt!(format))
+            .style("color", Color::rgb(197.0, 197.0, 197.0))
+            .style("size", 14.0)
+            .style("line_height", 16.)
+            .style("font", "Space Grotesk"),
+        lay![size: [150, Auto]]
+    ))
+    .push(node!(
+        TextBox::new(Some(s.text_jump.clone()))
+            .with_class("text-md border-1 bg-transparent")
+            .placeholder("Line")
+            .on_change(Box::new(|s| msg!(Message::UpdateTextJump(s.to_string())))),
+        lay![size: [100, 32]]
+    ))
+    .push(button("Go", Some(Message::TextJump)));
+    // Following starts once the whole file is indexed.
+    if indexed {
+        let label = if following { "Stop" } else { "Follow" };
+        status = status.push(node!(
+            Button::new(txt!(label))
+                .style("background_color", Color::TRANSPARENT)
+                .style("active_color", Color::MID_GREY)
+                .style("text_color", Color::WHITE)
+                .style("font_size", 14.0)
+                .style("line_height", 16.0)
+                .on_click(Box::new(move || msg!(Message::TextFollow(!following)))),
+            lay![size: [90, 30]]
+        ));
+    }
+
+    node!(
+        Div::new(),
+        lay![
+            size: [440, Auto],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Center,
+        ]
+    )
+    .push(toolbar)
+    .push(lines)
+    .push(status)
+}
+
+// Hex viewer: a bar to page through the file above its rows of offset, bytes
+// and ASCII, then a field to go to an offset and the values the selected
+// byte starts
+fn hex_view(s: &FileManagerState, hex: &HexFile) -> node::Node {
+    let offset = s.hex_offset;
+    let page_bytes = HEX_PAGE_ROWS * ROW_BYTES;
+    let end = offset + s.hex_bytes.len() as u64;
+    let last_row = hexdump::row_start(hex.len().saturating_sub(1));
+    let last_page = last_row.saturating_sub(page_bytes - ROW_BYTES);
+
+    let button = toolbar_button;
+    let counter = if hex.is_empty() {
+        "Empty file".to_string()
+    } else {
+        format!(
+            "{}–{} of {}",
+            hexdump::format_offset(offset),
+            hexdump::format_offset(end.saturating_sub(1)),
+            format_size(hex.len())
+        )
+    };
+    let grey = Color::rgb(197.0, 197.0, 197.0);
+    let highlight = Color::rgb(255., 213., 79.);
+
+    let toolbar = node!(
+        Div::new(),
+        lay![
+            size: [440, 36],
+            direction: Direction::Row,
+            cross_alignment: Alignment::Center,
+            axis_alignment: Alignment::Stretch,
+        ]
+    )
+    .push(button(
+        "«",
+        (offset > 0).then_some(Message::HexGoToOffset(0)),
+    ))
+    .push(button(
+        "‹",
+        (offset > 0).then_some(Message::HexGoToOffset(offset.saturating_sub(page_bytes))),
+    ))
+    .push(node!(
+        Text::new(txt!(counter))
+            .style("color", grey)
+            .style("size", 14.0)
+            .style("line_height", 16.)
+            .style("font", "Space Grotesk"),
+        lay![size: [264, Auto]]
+    ))
+    .push(button(
+        "›",
+        (end < hex.len()).then_some(Message::HexGoToOffset(offset + page_bytes)),
+    ))
+    .push(button(
+        "»",
+        (offset < last_page).then_some(Message::HexGoToOffset(last_page)),
+    ));
+
+    let selected = s.hex_selection.as_ref().map(|(offset, _)| *offset);
+    let mut rows = node!(
+        Scrollable::new(size!(440, 170)),
+        lay![
+            size: [440, 170],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+        ]
+    )
+    .key(offset);
+    for (i, bytes) in s.hex_bytes.chunks(ROW_BYTES as usize).enumerate() {
+        let row_offset = offset + i as u64 * ROW_BYTES;
+        let mut row = node!(
+            Div::new(),
+            lay![
+                size: [440, 22],
+                direction: Direction::Row,
+                cross_alignment: Alignment::Center,
+            ]
+        )
+        .push(node!(
+            Text::new(txt!(hexdump::format_offset(row_offset)))
+                .style("color", Color::rgb(120., 120., 120.))
+                .style("size", 14.0)
+                .style("line_height", 16.)
+                .style("font", "Space Grotesk"),
+            lay![size: [80, Auto]]
+        ));
+        for (j, byte) in bytes.iter().enumerate() {
+            let byte_offset = row_offset + j as u64;
+            let color = if selected == Some(byte_offset) {
+                highlight
+            } else {
+                Color::WHITE
+            };
+            row = row.push(node!(
+                Button::new(txt!(format!("{:02X}", byte)))
+                    .style("background_color", Color::TRANSPARENT)
+                    .style("active_color", Color::MID_GREY)
+                    .style("text_color", color)
+                    .style("font_size", 14.0)
+                    .style("line_height", 16.0)
+                    .on_click(Box::new(move || msg!(Message::HexSelect(byte_offset)))),
+                lay![size: [28, 22]]
+            ));
+        }
+        rows = rows.push(row.push(node!(
+            Text::new(txt!(hexdump::ascii(bytes)))
+                .style("color", grey)
+                .style("size", 14.0)
+                .style("line_height", 16.)
+                .style("font", "Space Grotesk"),
+            lay![size: [80, Auto], margin: [0., 10., 0., 0.]]
+        )));
+    }
+
+    let position = match selected {
+        Some(offset) => format!("Byte {} ({})", hexdump::format_offset(offset), offset),
+        None => String::new(),
+    };
+    let goto = node!(
+        Div::new(),
+        lay![
+            size: [440, 40],
+            direction: Direction::Row,
+            cross_alignment: Alignment::Center,
+            axis_alignment: Alignment::Stretch,
+        ]
+    )
+    .push(node!(
+        TextBox::new(Some(s.hex_goto.clone()))
+            .with_class("text-md border-1 bg-transparent")
+            .placeholder("Offset, e.g. 0x1000")
+            .on_change(Box::new(|s| msg!(Message::UpdateHexGoTo(s.to_string())))),
+        lay![size: [170, 32]]
+    ))
+    .push(button("Go", Some(Message::HexGoTo)))
+    .push(node!(
+        Text::new(txt!(position))
+            .style("color", grey)
+            .style("size", 14.0)
+            .style("line_height", 16.)
+            .style("font", "Space Grotesk"),
+        lay![size: [200, Auto], margin: [0., 10., 0., 0.]]
+    ));
+
+    let mut values = node!(
+        Scrollable::new(size!(440, 100)),
+        lay![
+            size: [440, 100],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+        ]
+    );
+    let value_text = |text: String, color: Color, width: u32| {
+        node!(
+            Text::new(txt!(text))
+                .style("color", color)
+                .style("size", 14.0)
+                .style("line_height", 18.)
+                .style("font", "Space Grotesk"),
+            lay![size: [width, Auto]]
+        )
+    };
+    match &s.hex_selection {
+        Some((_, bytes)) => {
+            let header = [("Type", 60), ("Little endian", 180), ("Big endian", 180)];
+            let mut row = node!(Div::new(), lay![direction: Direction::Row]);
+            for (label, width) in header {
+                row = row.push(value_text(label.to_string(), grey, width));
+            }
+            values = values.push(row);
+            for value in hexdump::interpret(bytes) {
+                values = values.push(
+                    node!(Div::new(), lay![direction: Direction::Row])
+                        .push(value_text(value.kind.to_string(), grey, 60))
+                        .push(value_text(value.little_endian, Color::WHITE, 180))
+                        .push(value_text(value.big_endian, Color::WHITE, 180)),
+                );
+            }
+        }
+        None => {
+            let hint = "Select a byte to see the values read from it.".to_string();
+            values = values.push(value_text(hint, grey, 440));
+        }
+    }
+
+    node!(
+        Div::new(),
+        lay![
+            size: [440, Auto],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Center,
+        ]
+    )
+    .push(toolbar)
+    .push(rows)
+    .push(goto)
+    .push(values)
+}
+
+fn file_viewer_view(s: &FileManagerState) -> node::Node {
+    let file_name = s
+        .view_file
+        .as_ref()
+        .and_then(|p| p.file_name())
+        .map(|n| shorten(&n.to_string_lossy(), MAX_FILE_NAME_CHARS))
+        .unwrap_or_default();
+
+    let mut header = node!(
+        Div::new().bg(Color::BLACK),
+        lay![
+            direction: Direction::Row,
+            cross_alignment: Alignment::Center,
+            axis_alignment: Alignment::Start,
+            padding: [5., 20., 5., 20.],
+        ]
+    )
+    .push(node!(
+        IconButton::new("back_icon")
+            .on_click(Box::new(|| msg!(Message::GoBack)))
+            .icon_type(IconType::Png)
+            .style(
+                "size",
+                Size {
+                    width: Dimension::Px(32.0),
+                    height: Dimension::Px(34.0)
+                }
+            )
+            .style("background_color", Color::TRANSPARENT)
+            .style("border_color", Color::TRANSPARENT)
+            .style("active_color", Color::rgba(85., 85., 85., 0.50)),
+        lay![margin:[5.,5.,5.,5.], size:[32,34]]
+    ))
+    .push(node!(
+        Text::new(txt!(file_name))
+            .style("color", Color::WHITE)
+            .style("size", 24.0)
+            .style("line_height", 24.)
+            .style("font", "Space Grotesk")
+            .style("font_weight", FontWeight::Normal),
+        lay![margin:[5.,20.,5.,5.]]
+    ));
+    // Files with a preview switch between it and their hex dump; others
+    // only have the dump.
+    if s.text.is_some() || s.file_is_image || s.file_is_pdf {
+        let label = match (s.hex_view, s.text.is_some()) {
+            (false, _) => "Hex",
+            (true, true) => "Text",
+            (true, false) => "Preview",
+        };
+        header = header.push(node!(
+            Button::new(txt!(label))
+                .style("background_color", Color::TRANSPARENT)
+                .style("active_color", Color::MID_GREY)
+                .style("text_color", Color::WHITE)
+                .style("font_size", 14.0)
+                .style("line_height", 16.0)
+                .on_click(Box::new(|| msg!(Message::ToggleHexView))),
+            lay![size: [80, 30]]
+        ));
     }
 
-    fn view(&self) -> Option<mctk_core::Node> {
-        let s = self.state_ref();
+    let mut root = node!(
+        Div::new().bg(Color::BLACK),
+        lay![
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+            axis_alignment: Alignment::Start,
+            size_pct:[100,100]
+        ]
+    );
 
-        if s.file_viewer_open {
-            return Some(file_viewer_view(s));
-        }
+    root = root.push(header);
+    root = root.push(node!(HDivider {
+        size: 1.,
+        color: Color::MID_GREY
+    }));
+
+    // Content area scrollable
+    let mut content = node!(
+        Div::new().bg(Color::BLACK),
+        lay![
+            direction: Direction::Column,
+            cross_alignment: Alignment::Center,
+            axis_alignment: Alignment::Start,
+            padding:[20.,20.,20.,20. ],
+            size_pct:[100,100],
+        ]
+    );
 
-        let current_path = s.current_path.clone();
-        let entries = s.entries.clone();
+    if let Some(hex) = s.hex.as_ref().filter(|_| s.hex_view) {
+        content = content.push(hex_view(s, hex));
+    } else if s.file_is_image {
+        content = content.push(image_viewer(s));
+    } else if s.file_is_pdf {
+        content = content.push(pdf_view(s));
+    } else if let Some(text) = &s.text {
+        content = content.push(text_view(s, text));
+    } else if s.file_no_preview {
+        content = content.push(node!(Text::new(txt!(
+            "No preview available for this file."
+        ))
+        .style("color", Color::WHITE)
+        .style("size", 18.0)
+        .style("line_height", 24.0)
+        .style("font", "Space Grotesk")));
+    } else {
+        content = content.push(node!(Text::new(txt!("Loading or no file selected."))
+            .style("color", Color::WHITE)
+            .style("size", 18.0)
+            .style("line_height", 24.0)
+            .style("font", "Space Grotesk")));
+    }
 
-        let mut root = node!(
-            Div::new().bg(Color::BLACK),
-            lay![
-                size_pct: [100],
-                direction: Direction::Column,
-                cross_alignment: Alignment::Stretch,
-                padding: [5., 20., 5., 20.],
-            ]
-        );
+    root = root.push(content);
+    root
+}
 
-        let current_folder_name = current_path
-            .file_name()
-            .unwrap_or_default()
-            .to_string_lossy()
-            .to_string();
+/// Path segments of `path` with the folder each one opens, from the root.
+fn breadcrumb_segments(path: &Path) -> Vec<(String, PathBuf)> {
+    let mut segments = Vec::new();
+    let mut prefix = PathBuf::new();
+    for component in path.components() {
+        prefix.push(component);
+        let label = match component {
+            std::path::Component::RootDir => "/".to_string(),
+            other => other.as_os_str().to_string_lossy().to_string(),
+        };
+        segments.push((label, prefix.clone()));
+    }
+    segments
+}
 
-        let text_node = node!(Text::new(txt!(current_folder_name))
-            .style("color", Color::rgb(197.0, 197.0, 197.0))
-            .style("size", 28.0)
-            .style("line_height", 20.)
-            .style("font", "Space Grotesk")
-            .style("font_weight", FontWeight::Normal));
+/// Indices of the segments that fit in about `max_chars`, always keeping the
+/// first and the last. `None` stands for the collapsed middle.
+fn visible_breadcrumbs(segments: &[(String, PathBuf)], max_chars: usize) -> Vec<Option<usize>> {
+    // Each segment is followed by a separator.
+    let width = |i: usize| segments[i].0.chars().count() + 2;
+    let total: usize = (0..segments.len()).map(width).sum();
+    if total <= max_chars || segments.len() <= 2 {
+        return (0..segments.len()).map(Some).collect();
+    }
+    let last = segments.len() - 1;
+    let mut used = width(0) + width(last) + 3;
+    let mut first_tail = last;
+    while first_tail > 1 && used + width(first_tail - 1) <= max_chars {
+        first_tail -= 1;
+        used += width(first_tail);
+    }
+    let mut visible = vec![Some(0)];
+    if first_tail > 1 {
+        visible.push(None);
+    }
+    visible.extend((first_tail..=last).map(Some));
+    visible
+}
 
-            let header_node = node!(
-                Div::new(),
-                lay![
-                    size_pct: [100, 10],
-                    direction: Direction::Row,
-                    axis_alignment: Alignment::Stretch,
-                    cross_alignment: Alignment::Center,
-                    margin: [5., 0., 0., 0.],
-                ]
-            )
-            .push(
-                node!(
-                    Div::new(),
-                    lay![
-                        size_pct: [70, Auto],
-                        axis_alignment: Alignment::Start,
-                        cross_alignment: Alignment::Center,
-                    ],
+// Header path bar: back and forward buttons, then one button per folder of
+// the current path, the middle collapsed when it does not fit
+fn breadcrumb_bar(path: &Path, can_go_forward: bool) -> node::Node {
+    let nav_button = |icon: &str, message: Message| {
+        node!(
+            IconButton::new(icon)
+                .on_click(Box::new(move || msg!(message.clone())))
+                .icon_type(IconType::Png)
+                .style(
+                    "size",
+                    Size {
+                        width: Dimension::Px(24.0),
+                        height: Dimension::Px(24.0),
+                    }
                 )
-                // .push(node!(
-                //     Div::new(),
-                //     lay![
-                //             size_pct: [20, Auto],
-                //             direction: Direction::Column,
-                //             axis_alignment: Alignment::Start,
-                //         ]
-                // ))
-                // .push(node!(
-                //     Image::new(""),
-                //     lay![
-                //         size:[24,24],
-                //         margin:[20.,0.,0.,10.]
-                //     ]
-                // ))
-                .push(
-                    node!(
-                        Div::new(),
-                        lay![
-                            size_pct: [80, Auto],
-                            direction: Direction::Column,
-                            axis_alignment: Alignment::Start,
-                        ]
+                .style("background_color", Color::TRANSPARENT)
+                .style("border_color", Color::TRANSPARENT)
+                .style("active_color", Color::rgba(85., 85., 85., 0.50))
+                .style("radius", 8.),
+            lay![size: [30, 30]]
+        )
+    };
+    let mut bar = node!(
+        Div::new(),
+        lay![
+            size_pct: [100, Auto],
+            direction: Direction::Row,
+            cross_alignment: Alignment::Center,
+            axis_alignment: Alignment::Start,
+        ]
+    )
+    .push(nav_button("back_icon", Message::GoBack));
+    if can_go_forward {
+        bar = bar.push(nav_button("arrow_icon", Message::GoForward));
+    }
+
+    let segments = breadcrumb_segments(path);
+    let visible = visible_breadcrumbs(&segments, MAX_BREADCRUMB_CHARS);
+    let count = visible.len();
+    for (i, index) in visible.into_iter().enumerate() {
+        let is_current = i + 1 == count;
+        let (label, target) = match index {
+            Some(index) => segments[index].clone(),
+            // The collapsed middle opens the deepest hidden folder.
+            None => {
+                let deepest_hidden = segments.len() + 1 - count;
+                ("…".to_string(), segments[deepest_hidden].1.clone())
+            }
+        };
+        let width = 10. * label.chars().count() as f32 + 8.;
+        bar = bar.push(
+            node!(
+                Button::new(txt!(label))
+                    .style("background_color", Color::TRANSPARENT)
+                    .style("active_color", Color::MID_GREY)
+                    .style(
+                        "text_color",
+                        if is_current {
+                            Color::WHITE
+                        } else {
+                            Color::rgb(150., 150., 150.)
+                        }
                     )
-                    .push(text_node),
-                ),
+                    .style("font_size", if is_current { 18.0 } else { 16.0 })
+                    .style("line_height", 20.0)
+                    .on_click(Box::new(move || msg!(Message::NavigateTo(target.clone())))),
+                lay![size: [width, 32]]
             )
-            .push(
-                node!(
-                    Div::new(),
-                    lay![
-                        size_pct: [30, Auto],
-                        axis_alignment: Alignment::End,
-                        padding: [0, 0, 0, 8.],
-                    ]
-                )
-                .push(node!(
-                    IconButton::new("add_icon")
-                        .on_click(Box::new(|| msg!(Message::CreateFolder)))
-                        .icon_type(IconType::Png)
-                        .style(
-                            "size",
-                            Size {
-                                width: Dimension::Px(30.0),
-                                height: Dimension::Px(30.0),
-                            }
-                        )
-                        .style("background_color", Color::TRANSPARENT)
-                        .style("border_color", Color::TRANSPARENT)
-                        .style("active_color", Color::rgba(85., 85., 85., 0.50))
-                        .style("radius", 10.),
-                    lay![
-                        size: [42, 42],
-                        axis_alignment: Alignment::End,
-                        cross_alignment: Alignment::Center,
-                    ]
-                ))
-                .push(node!(
-                    IconButton::new("dots_icon") // Add the three-dots icon
-                        .on_click(Box::new(|| msg!(Message::OpenFolerModal(true)))) // Open the options modal
-                        .icon_type(IconType::Png)
-                        .style(
-                            "size",
-                            Size {
-                                width: Dimension::Px(30.0),
-                                height: Dimension::Px(30.0),
-                            }
-                        )
-                        .style("background_color", Color::TRANSPARENT)
-                        .style("border_color", Color::TRANSPARENT)
-                        .style("active_color", Color::rgba(85., 85., 85., 0.50))
-                        .style("radius", 10.),
-                    lay![
-                        size: [42, 42],
-                        axis_alignment: Alignment::End,
-                        cross_alignment: Alignment::Center,
-                    ]
-                )),
-            );
-            let mut entries_div = node!(
-                Div::new(),
-                lay![
-                    size: [440, Auto],
-                    direction: Direction::Column,
-                    cross_alignment: Alignment::Stretch,
-                ]
+            .key(2 * i as u64),
+        );
+        if !is_current {
+            bar = bar.push(
+                node!(Text::new(txt!("›"))
+                    .style("color", Color::rgb(110., 110., 110.))
+                    .style("size", 16.0)
+                    .style("line_height", 20.)
+                    .style("font", "Space Grotesk"))
+                .key(2 * i as u64 + 1),
             );
-        
-            // Create the modal for folder options
-            let folder_options_modal = node!(
-                Div::new().bg(Color::rgba(29., 29., 29., 1.)).border(
-                    Color::rgba(127., 127., 135., 1.),
-                    0.,
-                    (10., 10., 10., 10.)
-                ),
-                lay![
-                    size: [200, 250],
-                    direction: Direction::Column,
-                    position_type: Absolute,
-                    position: [10., 210., 0., 0.],
-                    cross_alignment: Alignment::Stretch,
-                    axis_alignment: Alignment::Stretch,
-                    padding: [10., 10., 10., 10.],
-                ]
-            )
-            .push(
-                node!(
-                    Text::new(txt!("Folder Options"))
-                        .style("color", Color::WHITE)
-                        .style("size", 18.)
-                        .style("line_height", 20.)
-                        .style("font", "Space Grotesk")
-                        .style("font_weight", FontWeight::Normal),
-                )
-            )
-            .push(node!(HDivider {
-                size: 1.,
+        }
+    }
+    bar
+}
+
+/// Name shown for the tab of `path`, shortened to fit the tab strip.
+fn tab_label(path: &Path) -> String {
+    shorten(&folder_name(path), TAB_LABEL_CHARS)
+}
+
+fn folder_name(path: &Path) -> String {
+    match path.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => path.display().to_string(),
+    }
+}
+
+/// `name` cut to `max` characters, the last one an ellipsis.
+fn shorten(name: &str, max: usize) -> String {
+    if name.chars().count() <= max {
+        return name.to_string();
+    }
+    let mut short: String = name.chars().take(max - 1).collect();
+    short.push('…');
+    short
+}
+
+/// Icon of a listing row.
+fn entry_icon(entry: &DirEntryInfo) -> &'static str {
+    if entry.is_dir() {
+        return "fold_icon";
+    }
+    let ext = entry
+        .path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    match ext.as_str() {
+        "pdf" => "pdf_icon",
+        _ if picture::is_image(&entry.path) => "img_icon",
+        _ => "file_icon",
+    }
+}
+
+// Bar above a pane naming its folder; tapping the inactive pane's bar
+// focuses that pane
+fn pane_label(path: &Path, is_active: bool) -> node::Node {
+    let mut label = Button::new(txt!(shorten(&folder_name(path), PANE_NAME_CHARS)))
+        .style(
+            "background_color",
+            if is_active {
+                Color::rgba(85., 85., 85., 1.)
+            } else {
+                Color::TRANSPARENT
+            },
+        )
+        .style("active_color", Color::MID_GREY)
+        .style(
+            "text_color",
+            if is_active {
+                Color::WHITE
+            } else {
+                Color::rgb(150., 150., 150.)
+            },
+        )
+        .style("font_size", 14.0)
+        .style("line_height", 16.0)
+        .style("radius", 6.);
+    if !is_active {
+        label = label.on_click(Box::new(|| msg!(Message::FocusOtherPane)));
+    }
+    node!(label, lay![size: [PANE_WIDTH, PANE_LABEL_HEIGHT]])
+}
+
+// The pane that is not focused in dual-pane mode: its folder's entries,
+// dimmed. Opening one focuses the pane first.
+fn other_pane_view(s: &FileManagerState, index: usize, height: u32) -> node::Node {
+    let path = s.model.tab_paths()[index].to_path_buf();
+    let mut entries_div = node!(
+        Div::new(),
+        lay![
+            size: [PANE_WIDTH, Auto],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+        ]
+    );
+    for (i, entry) in s.other_pane_entries.iter().enumerate() {
+        let entry_path = entry.path.clone();
+        let row = Btnrow {
+            title: shorten(&entry.name, PANE_NAME_CHARS),
+            value: String::new(),
+            icon_1: entry_icon(entry).to_string(),
+            icon_2: String::new(),
+            color: Color::rgb(150., 150., 150.),
+            on_click: Some(Box::new(move || {
+                Message::OpenInOtherPane(entry_path.clone())
+            })),
+            icon_2_message: None,
+            is_modal_open: s.is_modal_open,
+            is_folder_options_modal: s.is_folder_options_modal
+                || s.is_sort_modal_open
+                || s.is_filter_modal_open
+                || s.tab_transfer.is_some(),
+            is_action_modal_open: s.is_action_modal_open,
+            is_delete_modal_open: s.is_delete_modal_open,
+            disable_click: s.pending_paste.is_some(),
+            selected: None,
+            scrolls_list: false,
+        };
+        entries_div = entries_div.push(node!(row).key(2 * i as u64));
+        entries_div = entries_div.push(
+            node!(HDivider {
+                size: 0.5,
                 color: Color::MID_GREY
-            }))
-            .push(
-                node!(
-                    Button::new(txt!("Paste"))
-                    .style("background_color", Color::TRANSPARENT)
-                    .style("active_color", Color::MID_GREY)
-                    .style("text_color", Color::WHITE)
-                    .style("font_size", 16.0)
-                    .style("line_height", 18.0)
-                        .on_click(Box::new(|| msg!(Message::Paste))),
-                    lay![margin: [0., 5., 5., 5.], size_pct: [100, 15]]
+            })
+            .key(2 * i as u64 + 1),
+        );
+    }
+    let scrollable_section = node!(
+        Scrollable::new(size!(PANE_WIDTH, height)),
+        lay![
+            size: [PANE_WIDTH, height],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+        ]
+    )
+    .push(entries_div);
+    node!(
+        Div::new(),
+        lay![
+            size: [PANE_WIDTH, height + PANE_LABEL_HEIGHT],
+            direction: Direction::Column,
+        ]
+    )
+    .push(pane_label(&path, false))
+    .push(scrollable_section)
+}
+
+// Tab strip: one button per tab, the active one highlighted and closable,
+// then a button opening the current folder in a new tab
+fn tab_strip(model: &FileManagerModel) -> node::Node {
+    let mut strip = node!(
+        Div::new().bg(Color::BLACK),
+        lay![
+            size: [440, 32],
+            direction: Direction::Row,
+            cross_alignment: Alignment::Center,
+            axis_alignment: Alignment::Start,
+        ]
+    );
+    let active = model.active_tab();
+    for (i, path) in model.tab_paths().into_iter().enumerate() {
+        let is_active = i == active;
+        let mut tab = node!(
+            Div::new()
+                .bg(if is_active {
+                    Color::rgba(85., 85., 85., 1.)
+                } else {
+                    Color::TRANSPARENT
+                })
+                .border(Color::TRANSPARENT, 0., (8., 8., 0., 0.)),
+            lay![
+                size: [Auto, 32],
+                direction: Direction::Row,
+                cross_alignment: Alignment::Center,
+                margin: [0., 0., 0., 2.],
+            ]
+        )
+        .push(node!(
+            Button::new(txt!(tab_label(path)))
+                .style("background_color", Color::TRANSPARENT)
+                .style("active_color", Color::MID_GREY)
+                .style(
+                    "text_color",
+                    if is_active {
+                        Color::WHITE
+                    } else {
+                        Color::rgb(150., 150., 150.)
+                    }
                 )
-            )
-            .push(node!(HDivider {
-                size: 0.3,
-                color: Color::MID_GREY
-            }))
-            .push(
-                node!(
-                    Button::new(txt!("Delete"))
+                .style("font_size", 14.0)
+                .style("line_height", 16.0)
+                .on_click(Box::new(move || msg!(Message::SwitchTab(i)))),
+            lay![size: [Auto, 30], padding: [0., 6., 0., 6.]]
+        ));
+        if is_active {
+            tab = tab.push(node!(
+                Button::new(txt!("×"))
                     .style("background_color", Color::TRANSPARENT)
                     .style("active_color", Color::MID_GREY)
                     .style("text_color", Color::WHITE)
                     .style("font_size", 16.0)
-                    .style("line_height", 18.0)
-                        .on_click(Box::new(|| msg!(Message::DeleteSelected))),
-                    lay![margin: [5., 5., 5., 5.], size_pct: [100, 15]]
-                )
-            )
-            .push(node!(HDivider {
-                size: 0.3,
-                color: Color::MID_GREY
-            }))
-            .push(
-                node!(
-                    Button::new(txt!("Rename"))
+                    .style("line_height", 16.0)
+                    .on_click(Box::new(move || msg!(Message::CloseTab(i)))),
+                lay![size: [24, 30]]
+            ));
+        }
+        strip = strip.push(tab.key(i as u64));
+    }
+    if model.tab_count() < MAX_TABS {
+        strip = strip.push(
+            node!(
+                Button::new(txt!("+"))
                     .style("background_color", Color::TRANSPARENT)
                     .style("active_color", Color::MID_GREY)
                     .style("text_color", Color::WHITE)
-                    .style("font_size", 16.0)
+                    .style("font_size", 18.0)
                     .style("line_height", 18.0)
-                        .on_click(Box::new(|| msg!(Message::RenameSelected))),
-                    lay![margin: [5., 5., 5., 5.], size_pct: [100, 15]]
-                )
+                    .on_click(Box::new(|| msg!(Message::NewTab))),
+                lay![size: [30, 30]]
             )
-            .push(node!(HDivider {
-                size: 0.3,
-                color: Color::MID_GREY
-            }))
-            .push(
-                node!(
-                    Button::new(txt!("Close"))
-                    .style("background_color", Color::TRANSPARENT)
-                    .style("active_color", Color::MID_GREY)
-                    .style("text_color", Color::WHITE)
-                    .style("font_size", 16.0)
-                    .style("line_height", 18.0)
-                        .on_click(Box::new(|| msg!(Message::OpenFolerModal(false)))),
-                    lay![margin: [5., 5., 5., 5.], size_pct: [100, 15]]
-                )
-
-            );
-            // .push(node!(HDivider {
-            //     size: 1.,
-            //     color: Color::MID_GREY
-            // }));
-            if s.is_folder_options_modal {
-                entries_div = entries_div.push(folder_options_modal);
-            }
+            .key(MAX_TABS as u64),
+        );
+    }
+    strip
+}
 
-        let back_row = Btnrow {
-            title: "..".to_string(),
-            value: "".to_string(),
-            icon_1: "fold_icon".to_string(),
-            icon_2: "".to_string(),
-            color: Color::WHITE,
-            on_click: Some(Box::new(move || Message::GoBack)),
-            on_icon_2_click: Some(Box::new(move || {
-                // This will open the action modal without selecting the entry
-                msg!(Message::OpenActionModal(true));
-                // Return a message indicating the action was triggered
-                Message::OpenActionModal(true)
-            })),
-            is_modal_open: s.is_modal_open, // Pass the modal state
-            is_folder_options_modal: s.is_folder_options_modal,
-            is_action_modal_open: s.is_action_modal_open,
-            is_delete_modal_open: s.is_delete_modal_open,
-            disable_click: false, // Initialize the flag to allow clicks
-        };
+/// Value column of a listing row: the size of a file or the item count of a
+/// folder, and how long ago it was modified.
+fn entry_summary(entry: &DirEntryInfo) -> String {
+    let amount = if entry.is_dir() {
+        entry.child_count.map(format_count)
+    } else {
+        Some(format_size(entry.size))
+    };
+    let modified = entry.modified.map(format_relative_time);
+    match (amount, modified) {
+        (Some(amount), Some(modified)) => format!("{} · {}", amount, modified),
+        (amount, modified) => amount.or(modified).unwrap_or_default(),
+    }
+}
 
-        entries_div = entries_div.push(node!(back_row));
-        entries_div = entries_div.push(node!(HDivider {
-            size: 0.5,
-            color: Color::MID_GREY
-        }));
+// Progress panel shown over the folder view while a paste is running
+fn transfer_progress_view(progress: &TransferProgress) -> node::Node {
+    let file_name = progress
+        .current_file
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let bar_width = 300. * progress.fraction();
 
-        let action_modal = node!(
-            Div::new().bg(Color::rgba(29., 29., 29., 1.)).border(
-                Color::rgba(127., 127., 135., 1.),
-                0.,
-                (10., 10., 10., 10.)
-            ),
+    node!(
+        Div::new().bg(Color::rgba(29., 29., 29., 1.)).border(
+            Color::rgba(127., 127., 135., 1.),
+            0.,
+            (10., 10., 10., 10.)
+        ),
+        lay![
+            size: [440, 80],
+            direction: Direction::Row,
+            position_type: Absolute,
+            position: [Auto, 20., 10., 20.],
+            cross_alignment: Alignment::Center,
+            axis_alignment: Alignment::Stretch,
+            padding: [10., 10., 10., 10.],
+        ]
+    )
+    .push(
+        node!(
+            Div::new(),
             lay![
-                size: [320, 160],
+                size: [310, Auto],
                 direction: Direction::Column,
-                position_type: Absolute,
-                position: [140., 60., 0., 0.],
-                cross_alignment: Alignment::Stretch,
-                axis_alignment: Alignment::Stretch,
-                padding: [15., 15.,  15., 10.]
+                axis_alignment: Alignment::Center,
             ]
         )
+        .push(node!(
+            Text::new(txt!(format!("Copying {}", file_name)))
+                .style("color", Color::WHITE)
+                .style("size", 16.0)
+                .style("line_height", 18.0)
+                .style("font", "Space Grotesk"),
+            lay![margin: [0., 0., 5., 0.]]
+        ))
+        .push(node!(
+            Text::new(txt!(format!(
+                "{} / {}",
+                format_size(progress.copied_bytes),
+                format_size(progress.total_bytes)
+            )))
+            .style("color", Color::rgb(197.0, 197.0, 197.0))
+            .style("size", 14.0)
+            .style("line_height", 16.0)
+            .style("font", "Space Grotesk"),
+            lay![margin: [0., 0., 5., 0.]]
+        ))
         .push(
             node!(
-                Div::new(),
-                lay![
-                    size_pct: [100, 42],
-                    cross_alignment: Alignment::Center,
-                    axis_alignment: Alignment::Center,
-                    direction: Direction::Column,
-                ]
-            )
-            .push(node!(
-                Text::new(txt!(s.action_modal_title.clone())) // Use the action modal title from state
-                    .style("color", Color::WHITE)
-                    .style("size", 18.)
-                    .style("line_height", 20.)
-                    .style("font", "Space Grotesk")
-                    .style("font_weight", FontWeight::Normal),
-                lay![
-                    size: [Auto],
-                ]
-            )),
-        )
-        .push(
-            // BUTTONS
-            node!(
-                Div::new(),
-                lay![
-                    size_pct: [100, 68],
-                    direction: Direction::Row,
-                    axis_alignment: Alignment::Stretch,
-                    cross_alignment: Alignment::Stretch,
-                ]
+                Div::new().bg(Color::rgba(68., 68., 68., 1.)),
+                lay![size: [300, 6]]
             )
-            .push(node!(
-                Div::new(),
-                lay![
-                    // size_pct: [28, 100],
-                    axis_alignment: Alignment::Start,
-                ]
-            ))
-            .push(
-                node!(
-                    Div::new(),
-                    lay![
-                        size_pct: [100, 100],
-                        axis_alignment: Alignment::Center,
-                        cross_alignment:Alignment::Center,
-                        direction: Direction::Column,
-                    ]
-                )
-                .push(node!(
-                    TextBox::new(Some("".to_string()))
-                        .with_class("text-md border-1 bg-transparent")
-                        .placeholder("Enter Name")
-                        .on_change(Box::new(|s| msg!(Message::UpdateFolderName(s.to_string())))),
-                    lay![
-                        size_pct: [80, 60],
-                        margin: [0., 0., 10., 0.]
-                    ]
-                ))
-                .push(
-                    node!(
-                        Div::new(),
-                        lay![
-                            size_pct: [100, 50],
-                            axis_alignment: Alignment::Stretch,
-                            direction: Direction::Row,
-                            margin: [0., 0., 10., 0.]
-                        ]
-                    )
-                    .push(node!(
-                        Button::new(txt!("Cancel"))
-                            .style("text_color", Color::WHITE)
-                            .style("background_color", Color::rgba(68., 68., 68., 1.))
-                            .style("active_color", Color::rgba(82., 81., 81., 1.))
-                            .style("font_size", 16.)
-                            .style("line_height", 18.)
-                            .style("radius", 8.)
-                            .on_click(Box::new(move || {
-                                msg!(Message::OpenActionModal(false)) // Close the action modal
-                            })),
+            .push(node!(Div::new().bg(Color::WHITE), lay![size: [bar_width, 6]])),
+        ),
+    )
+    .push(node!(
+        Button::new(txt!("Cancel"))
+            .style("text_color", Color::WHITE)
+            .style("background_color", Color::rgba(68., 68., 68., 1.))
+            .style("active_color", Color::rgba(82., 81., 81., 1.))
+            .style("font_size", 16.)
+            .style("line_height", 18.)
+            .style("radius", 8.)
+            .on_click(Box::new(|| msg!(Message::CancelTransfer))),
+        lay![size: [100, 40]]
+    ))
+}
 
-                            // .on_click(Box::new(move || {
-                            //     msg!(Message::OpenModal(false)) // Close modal
-                            // })),
-                        lay![
-                            size_pct: [48, 100],
-                            padding: [0., 0., 0., 12.],
-                            cross_alignment: Alignment::Start,
-                            axis_alignment: Alignment::Start,
-                        ]
-                    ))
-                    .push(node!(
-                        Button::new(txt!("Confirm"))
-                            .style("text_color", Color::BLACK)
-                            .style("background_color", Color::WHITE)
-                            .style("active_color", Color::rgba(194., 184., 184., 1.))
-                            .style("font_size", 16.)
-                            .style("line_height", 18.)
-                            .style("radius", 8.)
-                            .on_click(Box::new(move || {
-                                msg!(Message::ConfirmAction) // Trigger confirm action
-                            })),
-                        lay![
-                            size_pct: [48, 100],
-                            padding: [0., 12., 0., 0.],
-                            axis_alignment: Alignment::End,
-                        ]
-                    )),
-                ),
-            ),
-        );
+// Notice shown at the bottom after an operation, with its Undo or Redo action
+fn toast_view(toast: &Toast<Message>, queued: usize) -> node::Node {
+    let text = if queued > 0 {
+        format!("{} (+{})", toast.text, queued)
+    } else {
+        toast.text.clone()
+    };
+
+    let mut view = node!(
+        Div::new().bg(Color::rgba(29., 29., 29., 1.)).border(
+            Color::rgba(127., 127., 135., 1.),
+            0.,
+            (10., 10., 10., 10.)
+        ),
+        lay![
+            size: [440, 60],
+            direction: Direction::Row,
+            position_type: Absolute,
+            position: [Auto, 20., 10., 20.],
+            cross_alignment: Alignment::Center,
+            axis_alignment: Alignment::Stretch,
+            padding: [10., 10., 10., 10.],
+        ]
+    )
+    .push(node!(
+        Div::new().bg(severity_color(toast.severity)),
+        lay![size: [4, 40], margin: [0., 0., 0., 6.]]
+    ))
+    // Tapping the text opens the history of past notices.
+    .push(node!(
+        Button::new(txt!(text))
+            .style("text_color", Color::WHITE)
+            .style("background_color", Color::TRANSPARENT)
+            .style("active_color", Color::rgba(85., 85., 85., 0.50))
+            .style("font_size", 16.)
+            .style("line_height", 18.)
+            .on_click(Box::new(|| msg!(Message::OpenToastHistory(true)))),
+        lay![size: [240, 40]]
+    ));
+
+    if let Some(action) = toast.action.clone() {
+        let label = if matches!(action, Message::Redo) {
+            "Redo"
+        } else {
+            "Undo"
+        };
+        view = view.push(node!(
+            Button::new(txt!(label))
+                .style("text_color", Color::BLACK)
+                .style("background_color", Color::WHITE)
+                .style("active_color", Color::rgba(194., 184., 184., 1.))
+                .style("font_size", 16.)
+                .style("line_height", 18.)
+                .style("radius", 8.)
+                .on_click(Box::new(move || msg!(action.clone()))),
+            lay![size: [70, 40], margin: [0., 5., 0., 5.]]
+        ));
+    }
 
-        if s.is_action_modal_open {
-            entries_div = entries_div.push(action_modal);
+    let id = toast.id;
+    view.push(node!(
+        Button::new(txt!("Close"))
+            .style("text_color", Color::WHITE)
+            .style("background_color", Color::rgba(68., 68., 68., 1.))
+            .style("active_color", Color::rgba(82., 81., 81., 1.))
+            .style("font_size", 16.)
+            .style("line_height", 18.)
+            .style("radius", 8.)
+            .on_click(Box::new(move || msg!(Message::DismissToast(id)))),
+        lay![size: [70, 40]]
+    ))
+}
+
+fn severity_color(severity: Severity) -> Color {
+    match severity {
+        Severity::Info => Color::rgb(197.0, 197.0, 197.0),
+        Severity::Success => Color::rgb(76.0, 175.0, 80.0),
+        Severity::Warning => Color::rgb(255.0, 179.0, 0.0),
+        Severity::Error => Color::rgb(229.0, 57.0, 53.0),
+    }
+}
+
+/// Notice for a failed operation. Filesystem errors describe themselves,
+/// other errors are prefixed with `context`; a suggested remedy is appended.
+/// Filesystem errors are also logged with their operation and path.
+fn error_notice(context: &str, e: &ModelError) -> (Severity, String) {
+    let severity = match e {
+        ModelError::AlreadyExists(_)
+        | ModelError::ContainsCurrentFolder
+        | ModelError::FileOp(FileOpError::NotFound { .. } | FileOpError::AlreadyExists { .. }) => {
+            Severity::Warning
+        }
+        _ => Severity::Error,
+    };
+    let text = match e {
+        ModelError::FileOp(e) => {
+            if severity == Severity::Warning {
+                tracing::warn!(op = %e.op(), path = %e.path().display(), error = %e.io_error(), "{}", context);
+            } else {
+                tracing::error!(op = %e.op(), path = %e.path().display(), error = %e.io_error(), "{}", context);
+            }
+            e.to_string()
         }
+        ModelError::AlreadyExists(_) => e.to_string(),
+        _ => format!("{}: {}", context, e),
+    };
+    match e.remedy() {
+        Some(remedy) => (severity, format!("{} {}", text, remedy)),
+        None => (severity, text),
+    }
+}
 
-        let delete_modal = node!(
-            Div::new().bg(Color::rgba(29., 29., 29., 1.)).border(
-                Color::rgba(127., 127., 135., 1.),
-                0.,
-                (10., 10., 10., 10.)
-            ),
+// Adds the current notice, if any, on top of a view
+fn with_toast(view: node::Node, s: &FileManagerState) -> node::Node {
+    match s.toasts.current() {
+        Some(toast) => view.push(toast_view(toast, s.toasts.queued())),
+        None => view,
+    }
+}
+
+// History layout: past notices, newest first
+fn toast_history_view(s: &FileManagerState) -> node::Node {
+    let header = node!(
+        Div::new().bg(Color::BLACK),
+        lay![
+            size_pct: [100, Auto],
+            direction: Direction::Row,
+            cross_alignment: Alignment::Center,
+            axis_alignment: Alignment::Stretch,
+            padding: [5., 20., 5., 20.],
+        ]
+    )
+    .push(
+        node!(
+            Div::new(),
             lay![
-                size: [320, 160],
-                direction: Direction::Column,
-                position_type: Absolute,
-                position: [140., 60., 0., 0.],
-                cross_alignment: Alignment::Stretch,
-                axis_alignment: Alignment::Stretch,
-                padding: [15., 15.,  15., 10.]
+                direction: Direction::Row,
+                cross_alignment: Alignment::Center,
+                axis_alignment: Alignment::Start,
             ]
         )
-        .push(
-            node!(
-                Div::new(),
-                lay![
-                    size_pct: [100, 72],
-                    cross_alignment: Alignment::Center,
-                    axis_alignment: Alignment::Center,
-                ]
-            )
-            .push(node!(
-                Text::new(txt!(format!("Delete {}?", s.delete_item_name)))
-                    .style("color", Color::WHITE)
-                    .style("size", 18.)
-                    .style("line_height", 20.)
-                    .style("font", "Space Grotesk")
-                    .style("font_weight", FontWeight::Normal),
-                lay![
-                    size: [Auto],
-                ]
-            )),
-        )
-        .push(
-            node!(
-                Div::new(),
-                lay![
-                    size_pct: [100, 28],
-                    direction: Direction::Row,
-                    axis_alignment: Alignment::Stretch,
-                    cross_alignment: Alignment::Stretch,
-                ]
-            )
-            .push(node!(
-                Div::new(),
-                lay![
-                    size_pct: [28, 100],
-                    axis_alignment: Alignment::Start,
-                ]
-            ))
-            .push(
-                node!(
-                    Div::new(),
-                    lay![
-                        size_pct: [72, 100],
-                        axis_alignment: Alignment::Stretch,
-                    ]
+        .push(node!(
+            IconButton::new("back_icon")
+                .on_click(Box::new(|| msg!(Message::OpenToastHistory(false))))
+                .icon_type(IconType::Png)
+                .style(
+                    "size",
+                    Size {
+                        width: Dimension::Px(32.0),
+                        height: Dimension::Px(34.0)
+                    }
                 )
-                .push(node!(
-                    Button::new(txt!("Cancel"))
-                        .style("text_color", Color::WHITE)
-                        .style("background_color", Color::rgba(68., 68., 68., 1.))
-                        .style("active_color", Color::rgba(82., 81., 81., 1.))
-                        .style("font_size", 16.)
-                        .style("line_height", 18.)
-                        .style("radius", 8.)
-                        .on_click(Box::new(move || {
-                            msg!(Message::OpenDeleteModal(false)) // Close modal
-                        })),
-                    lay![
-                        size_pct: [48, 100],
-                        padding: [0., 0., 0., 12.],
-                        axis_alignment: Alignment::Start,
-                    ]
-                ))
-                .push(node!(
-                    Button::new(txt!("Confirm"))
-                        .style("text_color", Color::BLACK)
-                        .style("background_color", Color::WHITE)
-                        .style("active_color", Color::rgba(194., 184., 184., 1.))
-                        .style("font_size", 16.)
-                        .style("line_height", 18.)
-                        .style("radius", 8.)
-                        .on_click(Box::new(move || {
-                            msg!(Message::ConfirmDelete) // Trigger confirm action
-                        })),
-                    lay![
-                        size_pct: [48, 100],
-                        padding: [0., 12., 0., 0.],
-                        axis_alignment: Alignment::End,
-                    ]
-                )),
-            ),
-        );
+                .style("background_color", Color::TRANSPARENT)
+                .style("border_color", Color::TRANSPARENT)
+                .style("active_color", Color::rgba(85., 85., 85., 0.50)),
+            lay![margin:[5.,5.,5.,5.], size:[32,34]]
+        ))
+        .push(node!(
+            Text::new(txt!("Messages"))
+                .style("color", Color::WHITE)
+                .style("size", 24.0)
+                .style("line_height", 24.)
+                .style("font", "Space Grotesk")
+                .style("font_weight", FontWeight::Normal),
+            lay![margin:[5.,20.,5.,5.]]
+        )),
+    )
+    .push(node!(
+        Button::new(txt!("Clear"))
+            .style("background_color", Color::TRANSPARENT)
+            .style("active_color", Color::MID_GREY)
+            .style("text_color", Color::WHITE)
+            .style("font_size", 16.0)
+            .style("line_height", 18.0)
+            .on_click(Box::new(|| msg!(Message::ClearToastHistory))),
+        lay![size: [80, 40]]
+    ));
 
-        if s.is_delete_modal_open {
-            entries_div = entries_div.push(delete_modal);
-        }
+    let mut entries_div = node!(
+        Div::new(),
+        lay![
+            size: [440, Auto],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+        ]
+    );
 
-        if s.is_modal_open {
-            let modal_content = node!(
-                Div::new().bg(Color::BLACK), // border
-                lay![
-                    size: [200, 200],
-                    direction: Direction::Column,
-                    position_type: Absolute,
-                    position:[120., 190.,0., 0],
-                    cross_alignment: Alignment::Stretch,
-                    axis_alignment:Alignment::Stretch,
-                    // margin:[0., 10., 0., 0.],
-                    padding: [20., 20., 20., 20.],
-                ]
-            )
-            .push(node!(Text::new(txt!("File Options"))
-                .style("color", Color::WHITE)
-                .style("size", 20.0)
-                .style("line_height", 24.0)
-                .style("font", "Space Grotesk")))
-            .push(node!(HDivider {
-                size: 1.,
-                color: Color::MID_GREY
-            }))
-            .push(node!(
-                Button::new(txt!("Rename"))
-                    .style("background_color", Color::TRANSPARENT)
-                    .style("active_color", Color::MID_GREY)
-                    .style("text_color", Color::WHITE)
-                    .style("font_size", 16.0)
-                    .style("line_height", 18.0)
-                    .on_click(Box::new(|| msg!(Message::RenameSelected))),
-                lay![margin: [5., 5., 5., 5.], size_pct: [100, 25]]
-            ))
-            .push(node!(HDivider {
-                size: 0.3,
-                color: Color::MID_GREY
-            }))
-            .push(node!(
-                Button::new(txt!("Copy"))
-                    .style("background_color", Color::TRANSPARENT)
-                    .style("font_size", 15.0)
-                    .style("line_height", 24.0)
-                    .style("text_color", Color::WHITE)
-                    .on_click(Box::new(|| msg!(Message::CopySelected))),
-                lay![margin: [5., 5., 5., 5.], size_pct: [100, 25]]
-            ))
-            .push(node!(HDivider {
-                size: 0.3,
-                color: Color::MID_GREY
-            }))
-            .push(node!(
-                Button::new(txt!("Delete"))
-                    .style("background_color", Color::TRANSPARENT)
-                    .style("font_size", 15.0)
-                    .style("line_height", 24.0)
-                    .style("text_color", Color::WHITE)
-                    .on_click(Box::new(|| msg!(Message::DeleteSelected))),
-                lay![margin: [5., 5., 5., 5.], size_pct: [100, 25]]
-            ))
-            .push(node!(HDivider {
-                size: 0.3,
-                color: Color::MID_GREY
-            }))
-            .push(node!(
-                Button::new(txt!("Paste"))
-                    .style("background_color", Color::TRANSPARENT)
-                    .style("font_size", 15.0)
-                    .style("line_height", 24.0)
-                    .style("text_color", Color::WHITE)
-                    .on_click(Box::new(|| msg!(Message::Paste))),
-                lay![margin: [5., 5., 5., 5.], size_pct: [100, 25]]
-            ))
-            .push(node!(HDivider {
-                size: 0.3,
-                color: Color::MID_GREY
-            }))
-            .push(node!(
-                Button::new(txt!("Close"))
-                    .style("background_color", Color::TRANSPARENT)
-                    .style("font_size", 15.0)
-                    .style("line_height", 24.0)
-                    .style("text_color", Color::WHITE)
-                    .on_click(Box::new(|| msg!(Message::OpenModal(false)))),
-                lay![margin: [5., 5., 5., 5.], size_pct: [100, 25]]
-            ))
-            .push(node!(HDivider {
-                size: 1.,
+    for (i, toast) in s.toasts.history().enumerate() {
+        let row = Btnrow {
+            title: toast.text.clone(),
+            value: toast.time.format("%H:%M:%S").to_string(),
+            icon_1: "".to_string(),
+            icon_2: "".to_string(),
+            color: severity_color(toast.severity),
+            on_click: None,
+            icon_2_message: None,
+            is_modal_open: false,
+            is_folder_options_modal: false,
+            is_action_modal_open: false,
+            is_delete_modal_open: false,
+            disable_click: true,
+            selected: None,
+            scrolls_list: false,
+        };
+        entries_div = entries_div.push(node!(row).key(2 * i as u64));
+        entries_div = entries_div.push(
+            node!(HDivider {
+                size: 0.5,
                 color: Color::MID_GREY
-            }));
+            })
+            .key(2 * i as u64 + 1),
+        );
+    }
 
-            entries_div = entries_div.push(modal_content);
-        }
+    let scrollable_section = node!(
+        Scrollable::new(size!(440, 380)),
+        lay![
+            size: [440, 380],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+        ]
+    )
+    .push(entries_div);
 
-        for (i, entry) in entries.iter().enumerate() {
-            let name = entry
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .to_string();
-            let entry_clone = Arc::new(entry.clone());
-            let (main_icon, righticon) = if entry.is_dir() {
-                ("fold_icon".to_string(), "".to_string()) // Replace with actual folder icon path
-            } else {
-                let ext = entry
-                    .extension()
-                    .and_then(|e| e.to_str())
-                    .unwrap_or("")
-                    .to_lowercase();
-                let file_icon = match ext.as_str() {
-                    "txt" => "file_icon".to_string(),
-                    "pdf" => "pdf_icon".to_string(),
-                    "png" | "jpg" | "jpeg" | "gif" => "img_icon".to_string(),
-                    _ => "file_icon".to_string(),
-                };
-                (file_icon, "dots_icon".to_string())
-            };
+    node!(
+        Div::new().bg(Color::BLACK),
+        lay![
+            size_pct: [100],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+            padding: [5., 20., 5., 20.],
+        ]
+    )
+    .push(header)
+    .push(node!(HDivider {
+        size: 1.,
+        color: Color::MID_GREY
+    }))
+    .push(scrollable_section)
+}
 
-            let btn_row = Btnrow {
-                title: name.to_string(),
-                value: "".to_string(),
-                icon_1: main_icon,
-                icon_2: righticon,
-                color: Color::WHITE,
-                on_click: Some(Box::new(move || {
-                    Message::SelectEntry((*entry_clone).clone())
-                })),
-                on_icon_2_click: Some(Box::new(move || {
-                    // This will open the action modal without selecting the entry
-                    msg!(Message::OpenActionModal(true));
-                    // Return a message indicating the action was triggered
-                    Message::OpenActionModal(true)
-                })),
-                is_modal_open: s.is_modal_open, // Pass the modal state
-                is_folder_options_modal: s.is_folder_options_modal,
-                is_action_modal_open: s.is_action_modal_open,
-                is_delete_modal_open: s.is_delete_modal_open,
-                disable_click: false, // Initialize the flag to allow clicks
-            };
+// Search layout: query field and mode in the header, excludes and status
+// below, then the matches with their path relative to the searched folder
+fn search_view(s: &FileManagerState) -> node::Node {
+    let header = node!(
+        Div::new().bg(Color::BLACK),
+        lay![
+            size_pct: [100, Auto],
+            direction: Direction::Row,
+            cross_alignment: Alignment::Center,
+            axis_alignment: Alignment::Stretch,
+            padding: [5., 20., 5., 20.],
+        ]
+    )
+    .push(node!(
+        IconButton::new("back_icon")
+            .on_click(Box::new(|| msg!(Message::OpenSearch(false))))
+            .icon_type(IconType::Png)
+            .style(
+                "size",
+                Size {
+                    width: Dimension::Px(32.0),
+                    height: Dimension::Px(34.0)
+                }
+            )
+            .style("background_color", Color::TRANSPARENT)
+            .style("border_color", Color::TRANSPARENT)
+            .style("active_color", Color::rgba(85., 85., 85., 0.50)),
+        lay![margin:[5.,5.,5.,5.], size:[32,34]]
+    ))
+    .push(node!(
+        TextBox::new(Some(s.search_query.text.clone()))
+            .with_class("text-md border-1 bg-transparent")
+            .placeholder("Search")
+            .on_change(Box::new(|s| msg!(Message::UpdateSearchText(s.to_string())))),
+        lay![size: [280, 36]]
+    ))
+    .push(node!(
+        Button::new(txt!(s.search_query.mode.label()))
+            .style("background_color", Color::TRANSPARENT)
+            .style("active_color", Color::MID_GREY)
+            .style("text_color", Color::WHITE)
+            .style("font_size", 16.0)
+            .style("line_height", 18.0)
+            .on_click(Box::new(|| msg!(Message::CycleSearchMode))),
+        lay![size: [70, 40]]
+    ));
+
+    let running = s.search_cancel.is_some();
+    let status = match s.search_summary {
+        _ if s.search_query.text.trim().is_empty() => String::new(),
+        None => format!("Searching… {} found", s.search_results.len()),
+        Some(summary) if summary.cancelled => format!("Stopped, {} found", summary.matches),
+        Some(summary) if summary.truncated => format!("{} shown, more match", summary.matches),
+        Some(summary) if summary.indexed => format!("{} found in index", summary.matches),
+        Some(summary) => format!("{} found", summary.matches),
+    };
+    let mut options_row = node!(
+        Div::new(),
+        lay![
+            size_pct: [100, Auto],
+            direction: Direction::Row,
+            cross_alignment: Alignment::Center,
+            axis_alignment: Alignment::Stretch,
+            padding: [0., 20., 5., 20.],
+        ]
+    )
+    .push(node!(
+        TextBox::new(Some(s.search_query.excludes.join(", ")))
+            .with_class("text-md border-1 bg-transparent")
+            .placeholder("Exclude: .git, target")
+            .on_change(Box::new(|s| msg!(Message::UpdateSearchExcludes(
+                s.to_string()
+            )))),
+        lay![size: [200, 32]]
+    ))
+    .push(node!(
+        Text::new(txt!(status))
+            .style("color", Color::rgb(197.0, 197.0, 197.0))
+            .style("size", 14.0)
+            .style("line_height", 16.)
+            .style("font", "Space Grotesk")
+            .style("font_weight", FontWeight::Normal),
+        lay![margin: [0., 10., 0., 10.]]
+    ));
+    if running {
+        options_row = options_row.push(node!(
+            Button::new(txt!("Stop"))
+                .style("background_color", Color::TRANSPARENT)
+                .style("active_color", Color::MID_GREY)
+                .style("text_color", Color::WHITE)
+                .style("font_size", 16.0)
+                .style("line_height", 18.0)
+                .on_click(Box::new(|| msg!(Message::CancelSearch))),
+            lay![size: [60, 32]]
+        ));
+    }
+
+    let mut entries_div = node!(
+        Div::new(),
+        lay![
+            size: [440, Auto],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+        ]
+    );
 
-            entries_div = entries_div.push(node!(btn_row).key(i as u64));
+    for (i, result) in s.search_results.iter().enumerate() {
+        if let Some(line) = &result.line {
+            entries_div = entries_div.push(content_match_row(result, line).key(2 * i as u64));
             entries_div = entries_div.push(
                 node!(HDivider {
                     size: 0.5,
                     color: Color::MID_GREY
                 })
-                .key((i + 1) as u64),
+                .key(2 * i as u64 + 1),
             );
+            continue;
         }
-
-        // let create_folder_btn = node!(
-        //     Button::new(txt!("Create Folder"))
-        //         .style("font_size", 15.0)
-        //         .style("line_height", 24.0)
-        //         .style("color", Color::WHITE)
-        //         .on_click(Box::new(|| msg!(Message::CreateFolder))),
-        //     lay![padding: [5., 5., 5., 5.], size_pct: [22, Auto]]
-        // );
-
-        // let rename_btn = node!(
-        //     Button::new(txt!("Rename"))
-        //         .style("font_size", 15.0)
-        //         .style("line_height", 24.0)
-        //         .style("color", Color::WHITE)
-        //         .on_click(Box::new(|| msg!(Message::RenameSelected))),
-        //     lay![padding: [5., 5., 5., 5.], size_pct: [15, Auto]]
-        // );
-
-        // let delete_btn = node!(
-        //     Button::new(txt!("Delete"))
-        //         .style("font_size", 15.0)
-        //         .style("line_height", 24.0)
-        //         .style("color", Color::WHITE)
-        //         .on_click(Box::new(|| msg!(Message::DeleteSelected))),
-        //     lay![margin: [5., 5., 5., 5.], size_pct: [15, Auto]]
-        // );
-
-        // let copy_btn = node!(
-        //     Button::new(txt!("Copy"))
-        //         .style("font_size", 15.0)
-        //         .style("line_height", 24.0)
-        //         .style("color", Color::WHITE)
-        //         .on_click(Box::new(|| msg!(Message::CopySelected))),
-        //     lay![margin: [5., 5., 5., 5.], size_pct: [10, Auto]]
-        // );
-
-        // let paste_btn = node!(
-        //     Button::new(txt!("Paste"))
-        //         .style("font_size", 15.0)
-        //         .style("line_height", 24.0)
-        //         .style("color", Color::WHITE)
-        //         .on_click(Box::new(|| msg!(Message::Paste))),
-        //     lay![margin: [5., 5.,  5., 5.], size_pct: [10, Auto]]
-        // );
-
-        // let back_btn = node!(
-        //     Button::new(txt!("Go Back"))
-        //         .style("font_size", 15.0)
-        //         .style("line_height", 24.0)
-        //         .style("color", Color::DARK_GREY)
-        //         .on_click(Box::new(|| msg!(Message::GoBack))),
-        //     lay![margin: [5., 0., 5., 5.], size_pct: [15, Auto]]
-        // );
-
-        // let mut actions_row = node!(
-        //     Div::new().bg(Color::DARK_GREY),
-        //     lay![
-        //         direction: Direction::Row,
-        //         position_type: Absolute,
-        //         position: [Auto, 0., 0., 0.],
-        //         size_pct: [100, 10],
-        //         cross_alignment: Alignment::End,
-        //         axis_alignment: Alignment::Center,
-        //         padding: [0., 20., 0., 0.]
-        //     ]
-        // );
-
-        // actions_row = actions_row
-        //     .push(create_folder_btn)
-        //     .push(rename_btn)
-        //     .push(delete_btn)
-        //     .push(copy_btn)
-        //     .push(paste_btn)
-        //     .push(back_btn);
-
-        let mut scrollable_section = node!(
-            Scrollable::new(size!(440, 380)),
-            lay![
-                size: [440, 380],
-                direction: Direction::Column,
-                cross_alignment: Alignment::Stretch,
-            ]
+        let path = result.entry.path.clone();
+        let icon = if result.entry.is_dir() {
+            "fold_icon"
+        } else {
+            "file_icon"
+        };
+        let row = Btnrow {
+            title: result.entry.name.clone(),
+            value: result.relative_path.to_string_lossy().to_string(),
+            icon_1: icon.to_string(),
+            icon_2: "".to_string(),
+            color: Color::WHITE,
+            on_click: Some(Box::new(move || Message::OpenSearchResult(path.clone()))),
+            icon_2_message: None,
+            is_modal_open: false,
+            is_folder_options_modal: false,
+            is_action_modal_open: false,
+            is_delete_modal_open: false,
+            disable_click: false,
+            selected: None,
+            scrolls_list: false,
+        };
+        entries_div = entries_div.push(node!(row).key(2 * i as u64));
+        entries_div = entries_div.push(
+            node!(HDivider {
+                size: 0.5,
+                color: Color::MID_GREY
+            })
+            .key(2 * i as u64 + 1),
         );
-        scrollable_section = scrollable_section.push(entries_div);
-
-        root = root.push(header_node);
-        root = root.push(node!(HDivider {
-            size: 1.,
-            color: Color::MID_GREY
-        }));
-        root = root.push(scrollable_section);
-        // root = root.push(actions_row);
-        Some(root)
     }
-}
 
-impl RootComponent<FileManagerParams> for FileManager {}
+    let scrollable_section = node!(
+        Scrollable::new(size!(440, 340)),
+        lay![
+            size: [440, 340],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+        ]
+    )
+    .push(entries_div);
 
-pub struct Btnrow {
-    pub title: String,
-    pub value: String,
-    pub icon_1: String,
-    pub icon_2: String,
-    pub color: Color,
-    pub on_click: Option<Box<dyn Fn() -> Message + Send + Sync>>,
-    pub on_icon_2_click: Option<Box<dyn Fn() -> Message + Send + Sync>>, // New field for icon 2 click
-    pub is_modal_open: bool,
-    pub is_action_modal_open: bool,
-    pub is_delete_modal_open: bool,
-    is_folder_options_modal:bool,
-    pub disable_click: bool,
+    node!(
+        Div::new().bg(Color::BLACK),
+        lay![
+            size_pct: [100],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+            padding: [5., 20., 5., 20.],
+        ]
+    )
+    .push(header)
+    .push(options_row)
+    .push(node!(HDivider {
+        size: 1.,
+        color: Color::MID_GREY
+    }))
+    .push(scrollable_section)
 }
 
-impl std::fmt::Debug for Btnrow {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("Btnrow ")
-            .field("title", &self.title)
-            .field("icon_1", &self.icon_1)
-            .field("icon_2", &self.icon_2)
-            .finish()
-    }
+// Content search result: the file and line number, then the matching line
+// with the match highlighted
+fn content_match_row(result: &SearchMatch, line: &LineMatch) -> node::Node {
+    let path = result.entry.path.clone();
+    let number = line.number;
+    let row = Btnrow {
+        title: result.entry.name.clone(),
+        value: format!("{}:{}", result.relative_path.to_string_lossy(), number),
+        icon_1: "file_icon".to_string(),
+        icon_2: "".to_string(),
+        color: Color::WHITE,
+        on_click: Some(Box::new(move || {
+            Message::OpenSearchLine(path.clone(), number)
+        })),
+        icon_2_message: None,
+        is_modal_open: false,
+        is_folder_options_modal: false,
+        is_action_modal_open: false,
+        is_delete_modal_open: false,
+        disable_click: false,
+        selected: None,
+        scrolls_list: false,
+    };
+    let snippet_text = |text: &str, color: Color| {
+        node!(Text::new(txt!(text.to_string()))
+            .style("color", color)
+            .style("size", 13.0)
+            .style("line_height", 16.)
+            .style("font", "Space Grotesk")
+            .style("font_weight", FontWeight::Normal))
+    };
+    let snippet = &line.snippet;
+    let grey = Color::rgb(170., 170., 170.);
+    let snippet_row = node!(
+        Div::new(),
+        lay![
+            size_pct: [100, Auto],
+            direction: Direction::Row,
+            padding: [0., 40., 6., 10.],
+        ]
+    )
+    .push(snippet_text(&snippet[..line.highlight.start], grey))
+    .push(snippet_text(
+        &snippet[line.highlight.clone()],
+        Color::rgb(255., 213., 79.),
+    ))
+    .push(snippet_text(&snippet[line.highlight.end..], grey));
+
+    node!(
+        Div::new(),
+        lay![
+            size_pct: [100, Auto],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+        ]
+    )
+    .push(node!(row))
+    .push(snippet_row)
 }
-// self.disable_click 
 
-impl Component for Btnrow {
-    fn on_click(&mut self, event: &mut event::Event<event::Click>) {
-        // Check if the modal is open
-        if self.is_modal_open || self.is_action_modal_open || self.is_delete_modal_open || self.is_folder_options_modal || self.disable_click
-        {
-            return; // Ignore the click if the modal is open
-        }
-        
+// Asks how to handle the first paste item whose destination already exists
+fn conflict_modal(job: &PasteJob, apply_to_all: bool) -> node::Node {
+    let Some(item) = job.next_conflict() else {
+        return node!(Div::new());
+    };
+    let name = item
+        .dest
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let resolutions = item.allowed_resolutions();
 
-        if let Some(f) = &self.on_click {
-            event.emit(Box::new(f()));
-        }
+    let mut buttons = node!(
+        Div::new(),
+        lay![
+            size_pct: [100, Auto],
+            direction: Direction::Row,
+            axis_alignment: Alignment::Stretch,
+            margin: [10., 0., 0., 0.],
+        ]
+    );
+    for (i, resolution) in resolutions.iter().copied().enumerate() {
+        let label = match resolution {
+            ConflictResolution::Overwrite => "Overwrite",
+            ConflictResolution::Skip => "Skip",
+            ConflictResolution::KeepBoth => "Keep Both",
+            ConflictResolution::Merge => "Merge",
+        };
+        buttons = buttons.push(
+            node!(
+                Button::new(txt!(label))
+                    .style("text_color", Color::WHITE)
+                    .style("background_color", Color::rgba(68., 68., 68., 1.))
+                    .style("active_color", Color::rgba(82., 81., 81., 1.))
+                    .style("font_size", 14.)
+                    .style("line_height", 16.)
+                    .style("radius", 8.)
+                    .on_click(Box::new(move || msg!(Message::ResolveConflict(resolution)))),
+                lay![size: [Auto, 36], padding: [0., 8., 0., 8.], margin: [0., 4., 0., 4.]]
+            )
+            .key(i as u64),
+        );
     }
 
-    fn view(&self) -> Option<node::Node> {
-        let text_node = node!(Text::new(txt!(self.title.clone()))
-            .style("color", self.color)
-            .style("size", 20.0)
+    let mut modal = node!(
+        Div::new().bg(Color::rgba(29., 29., 29., 1.)).border(
+            Color::rgba(127., 127., 135., 1.),
+            0.,
+            (10., 10., 10., 10.)
+        ),
+        lay![
+            size: [400, 200],
+            direction: Direction::Column,
+            position_type: Absolute,
+            position: [100., 20., 0., 0.],
+            cross_alignment: Alignment::Stretch,
+            axis_alignment: Alignment::Start,
+            padding: [15., 15., 15., 10.]
+        ]
+    )
+    .push(node!(
+        Text::new(txt!(format!("'{}' already exists", name)))
+            .style("color", Color::WHITE)
+            .style("size", 18.)
             .style("line_height", 20.)
             .style("font", "Space Grotesk")
-            .style("font_weight", FontWeight::Normal));
-
-        let value_node = node!(Text::new(txt!(self.value.clone()))
+            .style("font_weight", FontWeight::Normal),
+        lay![margin: [0., 0., 5., 0.]]
+    ))
+    .push(node!(
+        Text::new(txt!(format!("{} conflict(s) remaining", job.conflicts_remaining())))
             .style("color", Color::rgb(197.0, 197.0, 197.0))
-            .style("size", 20.0)
-            .style("line_height", 20.)
-            .style("font", "Space Grotesk")
-            .style("font_weight", FontWeight::Normal));
+            .style("size", 14.)
+            .style("line_height", 16.)
+            .style("font", "Space Grotesk"),
+        lay![margin: [0., 0., 5., 0.]]
+    ))
+    .push(buttons);
 
-        Some(
-            node!(
-                Div::new(),
-                lay![
-                    padding: [10, 10, 10, 10],
-                    size_pct: [100, Auto],
-                    direction: Direction::Row,
-                    axis_alignment: Alignment::Stretch,
-                    cross_alignment: Alignment::Center,
-                ]
-            )
-            .push(
-                node!(
-                    Div::new(),
-                    lay![
-                        direction: Direction::Row,
-                        cross_alignment: Alignment::Center,
-                        axis_alignment: Alignment::Start,
-                    ]
-                )
-                .push(node!(
-                    Image::new(self.icon_1.clone()),
-                    lay![
-                        size:[24,24],
-                        margin:[20.,0.,0.,10.]
-                    ]
-                ))
-                .push(text_node),
-            )
-            .push(
-                node!(
-                    Div::new(),
-                    lay![
-                        direction: Direction::Row,
-                        axis_alignment: Alignment::End,
-                        cross_alignment:Alignment::Center,
-                    ]
-                )
-                .push(value_node)
-                .push(node!(
-                    IconButton::new(self.icon_2.clone())
-                        .on_click(Box::new(move || Box::new(Message::OpenModal(true)))) // Open modal when icon 2 is clicked
-                        .icon_type(IconType::Png)
-                        .style(
-                            "size",
-                            Size {
-                                width: Dimension::Px(34.0),
-                                height: Dimension::Px(34.0)
-                            }
-                        ),
-                    lay![size:[34,34], margin:[10.,0.,0.,0.]]
-                )),
-            ),
+    let mut footer = node!(
+        Div::new(),
+        lay![
+            size_pct: [100, Auto],
+            direction: Direction::Row,
+            axis_alignment: Alignment::Stretch,
+            margin: [10., 0., 0., 0.],
+        ]
+    );
+    // Merging can surface further conflicts, so "apply to all" is offered
+    // then as well as for multi-item pastes.
+    if job.conflicts_remaining() > 1 || resolutions.contains(&ConflictResolution::Merge) {
+        footer = footer.push(node!(
+            Button::new(txt!(if apply_to_all {
+                "Apply to all: On"
+            } else {
+                "Apply to all: Off"
+            }))
+            .style("text_color", Color::WHITE)
+            .style("background_color", Color::TRANSPARENT)
+            .style("active_color", Color::MID_GREY)
+            .style("font_size", 14.)
+            .style("line_height", 16.)
+            .on_click(Box::new(|| msg!(Message::ToggleConflictApplyAll))),
+            lay![size: [Auto, 36], padding: [0., 8., 0., 8.]]
+        ));
+    }
+    footer = footer.push(node!(
+        Button::new(txt!("Cancel"))
+            .style("text_color", Color::BLACK)
+            .style("background_color", Color::WHITE)
+            .style("active_color", Color::rgba(194., 184., 184., 1.))
+            .style("font_size", 14.)
+            .style("line_height", 16.)
+            .style("radius", 8.)
+            .on_click(Box::new(|| msg!(Message::CancelPaste))),
+        lay![size: [Auto, 36], padding: [0., 8., 0., 8.], margin: [0., 4., 0., 4.]]
+    ));
+
+    modal = modal.push(footer);
+    modal
+}
+
+// Options popup listing one button per action
+fn options_modal<L: Into<String>>(title: &str, options: Vec<(L, Message)>) -> node::Node {
+    let height = 50. + 40. * options.len() as f32;
+    let mut modal = node!(
+        Div::new().bg(Color::rgba(29., 29., 29., 1.)).border(
+            Color::rgba(127., 127., 135., 1.),
+            0.,
+            (10., 10., 10., 10.)
+        ),
+        lay![
+            size: [200, height],
+            direction: Direction::Column,
+            position_type: Absolute,
+            position: [10., 210., 0., 0.],
+            cross_alignment: Alignment::Stretch,
+            axis_alignment: Alignment::Start,
+            padding: [10., 10., 10., 10.],
+        ]
+    )
+    .push(
+        node!(
+            Text::new(txt!(title.to_string()))
+                .style("color", Color::WHITE)
+                .style("size", 18.)
+                .style("line_height", 20.)
+                .style("font", "Space Grotesk")
+                .style("font_weight", FontWeight::Normal),
         )
+    )
+    .push(node!(HDivider {
+        size: 1.,
+        color: Color::MID_GREY
+    }));
+
+    let count = options.len();
+    for (i, (label, message)) in options.into_iter().enumerate() {
+        let label: String = label.into();
+        modal = modal.push(
+            node!(
+                Button::new(txt!(label))
+                    .style("background_color", Color::TRANSPARENT)
+                    .style("active_color", Color::MID_GREY)
+                    .style("text_color", Color::WHITE)
+                    .style("font_size", 16.0)
+                    .style("line_height", 18.0)
+                    .on_click(Box::new(move || msg!(message.clone()))),
+                lay![margin: [5., 5., 5., 5.], size: [Auto, 30]]
+            )
+            .key(2 * i as u64),
+        );
+        if i + 1 < count {
+            modal = modal.push(
+                node!(HDivider {
+                    size: 0.3,
+                    color: Color::MID_GREY
+                })
+                .key(2 * i as u64 + 1),
+            );
+        }
     }
+    modal
 }
 
-// File viewer layout
-fn file_viewer_view(s: &FileManagerState) -> node::Node {
-    let file_name = s
-        .view_file
-        .as_ref()
-        .and_then(|p| p.file_name())
-        .map(|n| n.to_string_lossy().to_string())
-        .unwrap_or_default();
+// Sort options: one entry per key, the current one checked, then the
+// direction and folders-first toggles
+fn sort_modal(order: SortOrder) -> node::Node {
+    let mut options: Vec<(String, Message)> = SortKey::ALL
+        .into_iter()
+        .map(|key| {
+            let mark = if key == order.key { "✓ " } else { "" };
+            let message = Message::SetSortOrder(SortOrder { key, ..order });
+            (format!("{}{}", mark, key.label()), message)
+        })
+        .collect();
+    options.push((
+        if order.descending { "Descending" } else { "Ascending" }.to_string(),
+        Message::SetSortOrder(SortOrder {
+            descending: !order.descending,
+            ..order
+        }),
+    ));
+    options.push((
+        format!(
+            "Folders first: {}",
+            if order.folders_first { "On" } else { "Off" }
+        ),
+        Message::SetSortOrder(SortOrder {
+            folders_first: !order.folders_first,
+            ..order
+        }),
+    ));
+    options.push(("Close".to_string(), Message::OpenSortModal(false)));
+    options_modal("Sort By", options)
+}
+
+// Filter options: the categories, the current one checked, and a custom
+// pattern or extension list
+fn filter_modal(filter: &Filter) -> node::Node {
+    let mut options: Vec<(String, Message)> = Category::ALL
+        .into_iter()
+        .map(|category| {
+            let rule = FilterRule::Category(category);
+            let mark = if filter.rule.as_ref() == Some(&rule) { "✓ " } else { "" };
+            (
+                format!("{}{}", mark, category.label()),
+                Message::SetFilterRule(Some(rule)),
+            )
+        })
+        .collect();
+    options.push(("Custom…".to_string(), Message::EditCustomFilter));
+    if filter.rule.is_some() {
+        options.push(("Clear Filter".to_string(), Message::SetFilterRule(None)));
+    }
+    options.push(("Close".to_string(), Message::OpenFilterModal(false)));
+    options_modal("Filter", options)
+}
+
+// Header chip naming the active filter; tapping it removes the filter
+fn filter_chip(rule: &FilterRule) -> node::Node {
+    node!(
+        Button::new(txt!(format!("{}  ✕", rule.label())))
+            .style("background_color", Color::rgba(45., 45., 45., 1.))
+            .style("active_color", Color::MID_GREY)
+            .style("text_color", Color::WHITE)
+            .style("font_size", 12.0)
+            .style("line_height", 14.0)
+            .style("radius", 10.)
+            .on_click(Box::new(|| msg!(Message::SetFilterRule(None)))),
+        lay![
+            size: [Auto, 22],
+            margin: [4., 0., 0., 0.],
+            padding: [0., 8., 0., 8.],
+        ]
+    )
+}
 
+// Places layout: home and the user directories, the trash, then bookmarks
+fn places_view(s: &FileManagerState) -> node::Node {
     let header = node!(
         Div::new().bg(Color::BLACK),
         lay![
+            size_pct: [100, Auto],
             direction: Direction::Row,
             cross_alignment: Alignment::Center,
             axis_alignment: Alignment::Start,
@@ -1260,7 +5052,7 @@ fn file_viewer_view(s: &FileManagerState) -> node::Node {
         lay![margin:[5.,5.,5.,5.], size:[32,34]]
     ))
     .push(node!(
-        Text::new(txt!(file_name))
+        Text::new(txt!("Places"))
             .style("color", Color::WHITE)
             .style("size", 24.0)
             .style("line_height", 24.)
@@ -1269,91 +5061,505 @@ fn file_viewer_view(s: &FileManagerState) -> node::Node {
         lay![margin:[5.,20.,5.,5.]]
     ));
 
-    let mut root = node!(
-        Div::new().bg(Color::BLACK),
+    let mut entries_div = node!(
+        Div::new(),
         lay![
+            size: [440, Auto],
             direction: Direction::Column,
             cross_alignment: Alignment::Stretch,
-            axis_alignment: Alignment::Start,
-            size_pct:[100,100]
         ]
     );
 
-    root = root.push(header);
-    root = root.push(node!(HDivider {
+    let disable_click = s.selected_bookmark.is_some();
+    let place_row = |title: String, color: Color, on_click: Message| Btnrow {
+        title,
+        value: "".to_string(),
+        icon_1: "fold_icon".to_string(),
+        icon_2: "".to_string(),
+        color,
+        on_click: Some(Box::new(move || on_click.clone())),
+        icon_2_message: None,
+        is_modal_open: false,
+        is_folder_options_modal: false,
+        is_action_modal_open: false,
+        is_delete_modal_open: false,
+        disable_click,
+        selected: None,
+        scrolls_list: false,
+    };
+    let divider = || {
+        node!(HDivider {
+            size: 0.5,
+            color: Color::MID_GREY
+        })
+    };
+
+    let recent_row = place_row(
+        "Recent".to_string(),
+        Color::WHITE,
+        Message::OpenRecent(true),
+    );
+    entries_div = entries_div.push(node!(recent_row).key(0));
+    entries_div = entries_div.push(divider().key(1));
+
+    let mut key = 2;
+    for place in &s.user_dirs {
+        let row = place_row(
+            place.name.clone(),
+            Color::WHITE,
+            Message::OpenPlace(place.path.clone()),
+        );
+        entries_div = entries_div.push(node!(row).key(key));
+        entries_div = entries_div.push(divider().key(key + 1));
+        key += 2;
+    }
+    let trash_row = place_row("Trash".to_string(), Color::WHITE, Message::OpenTrash);
+    entries_div = entries_div.push(node!(trash_row).key(key));
+    entries_div = entries_div.push(divider().key(key + 1));
+    key += 2;
+
+    entries_div = entries_div.push(
+        node!(
+            Text::new(txt!("Bookmarks"))
+                .style("color", Color::rgb(197.0, 197.0, 197.0))
+                .style("size", 16.0)
+                .style("line_height", 20.0)
+                .style("font", "Space Grotesk"),
+            lay![margin: [16., 10., 6., 10.]]
+        )
+        .key(key),
+    );
+    key += 1;
+
+    let mut has_bookmarks = false;
+    for (i, bookmark) in s.bookmarks.entries().iter().enumerate() {
+        // Remote locations are kept in the file but cannot be browsed.
+        let Some(path) = bookmark.path() else {
+            continue;
+        };
+        has_bookmarks = true;
+        let color = if path.is_dir() {
+            Color::WHITE
+        } else {
+            Color::MID_GREY
+        };
+        let mut row = place_row(bookmark.name(), color, Message::OpenPlace(path));
+        row.icon_2 = "dots_icon".to_string();
+        row.icon_2_message = Some(Message::SelectBookmark(Some(i)));
+        entries_div = entries_div.push(node!(row).key(key));
+        entries_div = entries_div.push(divider().key(key + 1));
+        key += 2;
+    }
+
+    if !has_bookmarks {
+        entries_div = entries_div.push(
+            node!(
+                Text::new(txt!("Add bookmarks from the folder options."))
+                    .style("color", Color::rgb(197.0, 197.0, 197.0))
+                    .style("size", 16.0)
+                    .style("line_height", 20.0)
+                    .style("font", "Space Grotesk"),
+                lay![margin: [6., 10., 0., 10.]]
+            )
+            .key(key),
+        );
+    }
+
+    let selected = s
+        .selected_bookmark
+        .and_then(|i| s.bookmarks.entries().get(i));
+    if let Some(path) = selected.and_then(|bookmark| bookmark.path()) {
+        entries_div = entries_div.push(options_modal(
+            &path.display().to_string(),
+            vec![
+                ("Open", Message::OpenPlace(path)),
+                ("Remove Bookmark", Message::RemoveBookmark),
+                ("Close", Message::SelectBookmark(None)),
+            ],
+        ));
+    }
+
+    let scrollable_section = node!(
+        Scrollable::new(size!(440, 380)),
+        lay![
+            size: [440, 380],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+        ]
+    )
+    .push(entries_div);
+
+    node!(
+        Div::new().bg(Color::BLACK),
+        lay![
+            size_pct: [100],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+            padding: [5., 20., 5., 20.],
+        ]
+    )
+    .push(header)
+    .push(node!(HDivider {
         size: 1.,
         color: Color::MID_GREY
-    }));
+    }))
+    .push(scrollable_section)
+}
 
-    // Content area scrollable
-    let mut content = node!(
+// Recent layout: recently used files newest first, with remove and clear actions
+fn recent_view(s: &FileManagerState) -> node::Node {
+    let header = node!(
         Div::new().bg(Color::BLACK),
         lay![
-            direction: Direction::Column,
+            size_pct: [100, Auto],
+            direction: Direction::Row,
             cross_alignment: Alignment::Center,
-            axis_alignment: Alignment::Start,
-            padding:[20.,20.,20.,20. ],
-            size_pct:[100,100],
+            axis_alignment: Alignment::Stretch,
+            padding: [5., 20., 5., 20.],
+        ]
+    )
+    .push(
+        node!(
+            Div::new(),
+            lay![
+                direction: Direction::Row,
+                cross_alignment: Alignment::Center,
+                axis_alignment: Alignment::Start,
+            ]
+        )
+        .push(node!(
+            IconButton::new("back_icon")
+                .on_click(Box::new(|| msg!(Message::GoBack)))
+                .icon_type(IconType::Png)
+                .style(
+                    "size",
+                    Size {
+                        width: Dimension::Px(32.0),
+                        height: Dimension::Px(34.0)
+                    }
+                )
+                .style("background_color", Color::TRANSPARENT)
+                .style("border_color", Color::TRANSPARENT)
+                .style("active_color", Color::rgba(85., 85., 85., 0.50)),
+            lay![margin:[5.,5.,5.,5.], size:[32,34]]
+        ))
+        .push(node!(
+            Text::new(txt!("Recent"))
+                .style("color", Color::WHITE)
+                .style("size", 24.0)
+                .style("line_height", 24.)
+                .style("font", "Space Grotesk")
+                .style("font_weight", FontWeight::Normal),
+            lay![margin:[5.,20.,5.,5.]]
+        )),
+    )
+    .push(node!(
+        Button::new(txt!("Clear"))
+            .style("background_color", Color::TRANSPARENT)
+            .style("active_color", Color::MID_GREY)
+            .style("text_color", Color::WHITE)
+            .style("font_size", 16.0)
+            .style("line_height", 18.0)
+            .on_click(Box::new(|| msg!(Message::OpenClearRecentModal(true)))),
+        lay![size: [80, 40]]
+    ));
+
+    let mut entries_div = node!(
+        Div::new(),
+        lay![
+            size: [440, Auto],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
         ]
     );
 
-    if s.file_is_image {
-        if let Some(file) = &s.view_file {
-            let file_str = file.to_string_lossy().to_string();
-            content = content.push(node!(
-                Image::new(file_str),
-                lay![size:[400,400], margin:[10.,10.,10.,10.]]
-            ));
-        } else {
-            content = content.push(node!(Text::new(txt!("No file selected"))
-                .style("color", Color::WHITE)
+    if s.recent_files.is_empty() {
+        entries_div = entries_div.push(node!(
+            Text::new(txt!("No recent files."))
+                .style("color", Color::rgb(197.0, 197.0, 197.0))
                 .style("size", 18.0)
                 .style("line_height", 24.0)
-                .style("font", "Space Grotesk")));
+                .style("font", "Space Grotesk"),
+            lay![margin: [20., 10., 0., 10.]]
+        ));
+    }
+
+    let disable_click = s.selected_recent.is_some() || s.is_clear_recent_modal_open;
+    for (i, file) in s.recent_files.iter().enumerate() {
+        let name = file
+            .path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let ext = file
+            .path
+            .extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or("")
+            .to_lowercase();
+        let icon = match ext.as_str() {
+            "pdf" => "pdf_icon",
+            "png" | "jpg" | "jpeg" | "gif" => "img_icon",
+            _ => "file_icon",
+        };
+        // Files moved or deleted since are dimmed.
+        let color = if file.path.exists() {
+            Color::WHITE
+        } else {
+            Color::MID_GREY
+        };
+        let path = file.path.clone();
+
+        let row = Btnrow {
+            title: name,
+            value: format_relative_time(file.last_used().into()),
+            icon_1: icon.to_string(),
+            icon_2: "dots_icon".to_string(),
+            color,
+            on_click: Some(Box::new(move || Message::SelectEntry(path.clone()))),
+            icon_2_message: Some(Message::SelectRecent(Some(i))),
+            is_modal_open: false,
+            is_folder_options_modal: false,
+            is_action_modal_open: false,
+            is_delete_modal_open: false,
+            disable_click,
+            selected: None,
+            scrolls_list: false,
+        };
+        entries_div = entries_div.push(node!(row).key(2 * i as u64));
+        entries_div = entries_div.push(
+            node!(HDivider {
+                size: 0.5,
+                color: Color::MID_GREY
+            })
+            .key(2 * i as u64 + 1),
+        );
+    }
+
+    if let Some(file) = s.selected_recent.and_then(|i| s.recent_files.get(i)) {
+        let title = file
+            .path
+            .parent()
+            .map(|p| format!("In {}", p.display()))
+            .unwrap_or_default();
+        let mut options = vec![("Open", Message::SelectEntry(file.path.clone()))];
+        if let Some(dir) = file.path.parent() {
+            options.push(("Open Folder", Message::OpenPlace(dir.to_path_buf())));
         }
-    } else if s.file_is_pdf {
-        content = content.push(node!(Text::new(txt!("PDF viewing is not implemented"))
-            .style("color", Color::WHITE)
-            .style("size", 18.0)
-            .style("line_height", 24.0)
-            .style("font", "Space Grotesk")));
-    } else if let Some(content_str) = &s.file_content {
-        // Scrollable text area
-        let mut scroll = node!(
-            Scrollable::new(size!(440, 320)),
+        options.push(("Remove from Recent", Message::RemoveRecent));
+        options.push(("Close", Message::SelectRecent(None)));
+        entries_div = entries_div.push(options_modal(&title, options));
+    }
+
+    if s.is_clear_recent_modal_open {
+        entries_div = entries_div.push(options_modal(
+            "Clear the recent files history?",
+            vec![
+                ("Clear History", Message::ConfirmClearRecent),
+                ("Cancel", Message::OpenClearRecentModal(false)),
+            ],
+        ));
+    }
+
+    let scrollable_section = node!(
+        Scrollable::new(size!(440, 380)),
+        lay![
+            size: [440, 380],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+        ]
+    )
+    .push(entries_div);
+
+    node!(
+        Div::new().bg(Color::BLACK),
+        lay![
+            size_pct: [100],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+            padding: [5., 20., 5., 20.],
+        ]
+    )
+    .push(header)
+    .push(node!(HDivider {
+        size: 1.,
+        color: Color::MID_GREY
+    }))
+    .push(scrollable_section)
+}
+
+// Trash layout: trashed entries newest first, with restore and delete actions
+fn trash_view(s: &FileManagerState) -> node::Node {
+    let header = node!(
+        Div::new().bg(Color::BLACK),
+        lay![
+            size_pct: [100, Auto],
+            direction: Direction::Row,
+            cross_alignment: Alignment::Center,
+            axis_alignment: Alignment::Stretch,
+            padding: [5., 20., 5., 20.],
+        ]
+    )
+    .push(
+        node!(
+            Div::new(),
             lay![
-                size: [440, 320],
-                direction: Direction::Column,
-                cross_alignment: Alignment::Stretch,
+                direction: Direction::Row,
+                cross_alignment: Alignment::Center,
+                axis_alignment: Alignment::Start,
             ]
-        );
-
-        scroll = scroll.push(node!(
-            Text::new(txt!(content_str.clone()))
+        )
+        .push(node!(
+            IconButton::new("back_icon")
+                .on_click(Box::new(|| msg!(Message::GoBack)))
+                .icon_type(IconType::Png)
+                .style(
+                    "size",
+                    Size {
+                        width: Dimension::Px(32.0),
+                        height: Dimension::Px(34.0)
+                    }
+                )
+                .style("background_color", Color::TRANSPARENT)
+                .style("border_color", Color::TRANSPARENT)
+                .style("active_color", Color::rgba(85., 85., 85., 0.50)),
+            lay![margin:[5.,5.,5.,5.], size:[32,34]]
+        ))
+        .push(node!(
+            Text::new(txt!("Trash"))
                 .style("color", Color::WHITE)
-                .style("size", 14.0)
-                .style("line_height", 20.0)
+                .style("size", 24.0)
+                .style("line_height", 24.)
+                .style("font", "Space Grotesk")
+                .style("font_weight", FontWeight::Normal),
+            lay![margin:[5.,20.,5.,5.]]
+        )),
+    )
+    .push(node!(
+        Button::new(txt!("Empty"))
+            .style("background_color", Color::TRANSPARENT)
+            .style("active_color", Color::MID_GREY)
+            .style("text_color", Color::WHITE)
+            .style("font_size", 16.0)
+            .style("line_height", 18.0)
+            .on_click(Box::new(|| msg!(Message::OpenEmptyTrashModal(true)))),
+        lay![size: [80, 40]]
+    ));
+
+    let mut entries_div = node!(
+        Div::new(),
+        lay![
+            size: [440, Auto],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+        ]
+    );
+
+    if s.trash_items.is_empty() {
+        entries_div = entries_div.push(node!(
+            Text::new(txt!("Trash is empty."))
+                .style("color", Color::rgb(197.0, 197.0, 197.0))
+                .style("size", 18.0)
+                .style("line_height", 24.0)
                 .style("font", "Space Grotesk"),
-            lay![margin:[5.,5.,5.,5.]]
+            lay![margin: [20., 10., 0., 10.]]
         ));
+    }
 
-        content = content.push(scroll);
-    } else if s.file_no_preview {
-        content = content.push(node!(Text::new(txt!(
-            "No preview available for this file."
-        ))
-        .style("color", Color::WHITE)
-        .style("size", 18.0)
-        .style("line_height", 24.0)
-        .style("font", "Space Grotesk")));
-    } else {
-        content = content.push(node!(Text::new(txt!("Loading or no file selected."))
-            .style("color", Color::WHITE)
-            .style("size", 18.0)
-            .style("line_height", 24.0)
-            .style("font", "Space Grotesk")));
+    let disable_click = s.selected_trash_item.is_some() || s.is_empty_trash_modal_open;
+    for (i, item) in s.trash_items.iter().enumerate() {
+        let name = item
+            .original_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let deleted = item
+            .deletion_date
+            .map(|date| date.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_default();
+        let icon = if item.files_path().is_dir() {
+            "fold_icon"
+        } else {
+            "file_icon"
+        };
+
+        let row = Btnrow {
+            title: name,
+            value: deleted,
+            icon_1: icon.to_string(),
+            icon_2: "".to_string(),
+            color: Color::WHITE,
+            on_click: Some(Box::new(move || Message::SelectTrashItem(Some(i)))),
+            icon_2_message: None,
+            is_modal_open: false,
+            is_folder_options_modal: false,
+            is_action_modal_open: false,
+            is_delete_modal_open: false,
+            disable_click,
+            selected: None,
+            scrolls_list: false,
+        };
+        entries_div = entries_div.push(node!(row).key(2 * i as u64));
+        entries_div = entries_div.push(
+            node!(HDivider {
+                size: 0.5,
+                color: Color::MID_GREY
+            })
+            .key(2 * i as u64 + 1),
+        );
     }
 
-    root = root.push(content);
-    root
+    if let Some(item) = s.selected_trash_item.and_then(|i| s.trash_items.get(i)) {
+        let title = item
+            .original_path
+            .parent()
+            .map(|p| format!("From {}", p.display()))
+            .unwrap_or_default();
+        entries_div = entries_div.push(options_modal(
+            &title,
+            vec![
+                ("Restore", Message::RestoreTrashItem),
+                ("Delete Permanently", Message::DeleteTrashItem),
+                ("Close", Message::SelectTrashItem(None)),
+            ],
+        ));
+    }
+
+    if s.is_empty_trash_modal_open {
+        entries_div = entries_div.push(options_modal(
+            "Empty Trash permanently?",
+            vec![
+                ("Empty Trash", Message::ConfirmEmptyTrash),
+                ("Cancel", Message::OpenEmptyTrashModal(false)),
+            ],
+        ));
+    }
+
+    let scrollable_section = node!(
+        Scrollable::new(size!(440, 380)),
+        lay![
+            size: [440, 380],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+        ]
+    )
+    .push(entries_div);
+
+    node!(
+        Div::new().bg(Color::BLACK),
+        lay![
+            size_pct: [100],
+            direction: Direction::Column,
+            cross_alignment: Alignment::Stretch,
+            padding: [5., 20., 5., 20.],
+        ]
+    )
+    .push(header)
+    .push(node!(HDivider {
+        size: 1.,
+        color: Color::MID_GREY
+    }))
+    .push(scrollable_section)
 }
@@ -0,0 +1,279 @@
+//! Hex dumps of files of any size, read a few rows at a time, and the values
+//! the bytes at an offset stand for.
+
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::os::unix::fs::FileExt;
+use std::path::{Path, PathBuf};
+
+/// Bytes shown on one row of a dump.
+pub const ROW_BYTES: u64 = 8;
+
+/// A file opened for a hex dump.
+#[derive(Debug)]
+pub struct HexFile {
+    path: PathBuf,
+    file: File,
+    len: u64,
+}
+
+impl HexFile {
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            len,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Size of the file when it was opened.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Reads up to `count` bytes at `offset`; fewer at the end of the file.
+    pub fn read(&self, offset: u64, count: usize) -> io::Result<Vec<u8>> {
+        let mut buf = vec![0; count];
+        let mut filled = 0;
+        while filled < count {
+            match self
+                .file
+                .read_at(&mut buf[filled..], offset + filled as u64)
+            {
+                Ok(0) => break,
+                Ok(n) => filled += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+        buf.truncate(filled);
+        Ok(buf)
+    }
+}
+
+/// Start of the row holding `offset`.
+pub fn row_start(offset: u64) -> u64 {
+    offset - offset % ROW_BYTES
+}
+
+/// Formats the offset of a row, e.g. `0000A0F8`.
+pub fn format_offset(offset: u64) -> String {
+    format!("{:08X}", offset)
+}
+
+/// The bytes as text, with a `.` for each byte that is not printable ASCII.
+pub fn ascii(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&byte| {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            }
+        })
+        .collect()
+}
+
+/// Parses an offset typed by the user: hexadecimal with a `0x` prefix,
+/// decimal otherwise.
+pub fn parse_offset(text: &str) -> Option<u64> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// A value the bytes at an offset stand for, read in both byte orders.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interpretation {
+    /// Type read, e.g. `u16` or `f32`.
+    pub kind: &'static str,
+    pub little_endian: String,
+    pub big_endian: String,
+}
+
+impl Interpretation {
+    fn new(
+        kind: &'static str,
+        little_endian: impl fmt::Display,
+        big_endian: impl fmt::Display,
+    ) -> Self {
+        Self {
+            kind,
+            little_endian: little_endian.to_string(),
+            big_endian: big_endian.to_string(),
+        }
+    }
+
+    fn float<T>(kind: &'static str, little_endian: T, big_endian: T) -> Self
+    where
+        T: fmt::Display + fmt::LowerExp + Into<f64> + Copy,
+    {
+        Self::new(kind, format_float(little_endian), format_float(big_endian))
+    }
+}
+
+/// Values of the integer and floating point types that start with the
+/// first of `bytes`, as far as there are enough bytes for them.
+pub fn interpret(bytes: &[u8]) -> Vec<Interpretation> {
+    let mut values = Vec::new();
+    if let Some(b) = array::<1>(bytes) {
+        values.push(Interpretation::new("u8", b[0], b[0]));
+        values.push(Interpretation::new("i8", b[0] as i8, b[0] as i8));
+        values.push(Interpretation::new(
+            "bits",
+            format!("{:08b}", b[0]),
+            format!("{:08b}", b[0]),
+        ));
+    }
+    if let Some(b) = array::<2>(bytes) {
+        values.push(Interpretation::new(
+            "u16",
+            u16::from_le_bytes(b),
+            u16::from_be_bytes(b),
+        ));
+        values.push(Interpretation::new(
+            "i16",
+            i16::from_le_bytes(b),
+            i16::from_be_bytes(b),
+        ));
+    }
+    if let Some(b) = array::<4>(bytes) {
+        values.push(Interpretation::new(
+            "u32",
+            u32::from_le_bytes(b),
+            u32::from_be_bytes(b),
+        ));
+        values.push(Interpretation::new(
+            "i32",
+            i32::from_le_bytes(b),
+            i32::from_be_bytes(b),
+        ));
+        values.push(Interpretation::float(
+            "f32",
+            f32::from_le_bytes(b),
+            f32::from_be_bytes(b),
+        ));
+    }
+    if let Some(b) = array::<8>(bytes) {
+        values.push(Interpretation::new(
+            "u64",
+            u64::from_le_bytes(b),
+            u64::from_be_bytes(b),
+        ));
+        values.push(Interpretation::new(
+            "i64",
+            i64::from_le_bytes(b),
+            i64::from_be_bytes(b),
+        ));
+        values.push(Interpretation::float(
+            "f64",
+            f64::from_le_bytes(b),
+            f64::from_be_bytes(b),
+        ));
+    }
+    values
+}
+
+fn array<const N: usize>(bytes: &[u8]) -> Option<[u8; N]> {
+    bytes.get(..N)?.try_into().ok()
+}
+
+/// Very small and very large numbers in scientific notation, which plain
+/// formatting would spell out in hundreds of digits.
+fn format_float<T>(value: T) -> String
+where
+    T: fmt::Display + fmt::LowerExp + Into<f64> + Copy,
+{
+    let magnitude = value.into().abs();
+    if magnitude != 0. && magnitude.is_finite() && !(1e-4..1e9).contains(&magnitude) {
+        format!("{:e}", value)
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn formats_rows() {
+        assert_eq!(row_start(0), 0);
+        assert_eq!(row_start(15), 8);
+        assert_eq!(row_start(16), 16);
+        assert_eq!(format_offset(0xA0F8), "0000A0F8");
+        assert_eq!(format_offset(0x1_0000_0000), "100000000");
+        assert_eq!(ascii(b"Hi there\0\x7F\xFF~"), "Hi there...~");
+    }
+
+    #[test]
+    fn parses_offsets() {
+        assert_eq!(parse_offset(" 4096 "), Some(4096));
+        assert_eq!(parse_offset("0x1f"), Some(31));
+        assert_eq!(parse_offset("0XFF"), Some(255));
+        assert_eq!(parse_offset("1f"), None);
+        assert_eq!(parse_offset("0x"), None);
+        assert_eq!(parse_offset("-1"), None);
+    }
+
+    #[test]
+    fn interprets_bytes_in_both_orders() {
+        let values = interpret(&[0x00, 0x00, 0x80, 0x3F, 0, 0, 0, 0]);
+        let value = |kind| {
+            let value = values.iter().find(|value| value.kind == kind).unwrap();
+            (value.little_endian.as_str(), value.big_endian.as_str())
+        };
+        assert_eq!(value("u8"), ("0", "0"));
+        assert_eq!(value("bits"), ("00000000", "00000000"));
+        assert_eq!(value("u16"), ("0", "0"));
+        assert_eq!(value("u32"), ("1065353216", "32831"));
+        assert_eq!(value("f32"), ("1", "4.6006e-41"));
+        assert_eq!(value("i64"), ("1065353216", "141008071294976"));
+
+        let values = interpret(&[0xFF, 0xFE, 0x01]);
+        let kinds: Vec<_> = values.iter().map(|value| value.kind).collect();
+        assert_eq!(kinds, ["u8", "i8", "bits", "u16", "i16"]);
+        assert_eq!(values[1].little_endian, "-1");
+        assert_eq!(values[4].big_endian, "-2");
+        assert!(interpret(&[]).is_empty());
+    }
+
+    #[test]
+    fn formats_floats() {
+        assert_eq!(format_float(0.), "0");
+        assert_eq!(format_float(1.5), "1.5");
+        assert_eq!(format_float(1e-5), "1e-5");
+        assert_eq!(format_float(-2e10), "-2e10");
+        assert_eq!(format_float(f64::INFINITY), "inf");
+        assert_eq!(format_float(f32::NAN), "NaN");
+    }
+
+    #[test]
+    fn reads_at_any_offset() {
+        let dir = TestDir::new();
+        let file = HexFile::open(&dir.file("data", b"0123456789")).unwrap();
+        assert_eq!(file.len(), 10);
+        assert_eq!(file.read(8, 8).unwrap(), b"89");
+        assert_eq!(file.read(2, 3).unwrap(), b"234");
+        assert!(file.read(20, 8).unwrap().is_empty());
+
+        let empty = HexFile::open(&dir.file("empty", "")).unwrap();
+        assert!(empty.is_empty());
+        assert!(HexFile::open(&dir.path().join("missing")).is_err());
+    }
+}
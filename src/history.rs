@@ -0,0 +1,109 @@
+//! Browser-style history of visited folders.
+
+use std::path::{Path, PathBuf};
+
+/// Most folders remembered in each direction.
+const MAX_ENTRIES: usize = 100;
+
+#[derive(Debug, Clone, Default)]
+pub struct History {
+    back: Vec<PathBuf>,
+    forward: Vec<PathBuf>,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records that `from` was left for another folder. Clears the forward
+    /// history.
+    pub fn visit(&mut self, from: PathBuf) {
+        if self.back.last() != Some(&from) {
+            self.back.push(from);
+        }
+        if self.back.len() > MAX_ENTRIES {
+            self.back.remove(0);
+        }
+        self.forward.clear();
+    }
+
+    /// Steps back from `current`, skipping folders that no longer exist.
+    pub fn back(&mut self, current: &Path) -> Option<PathBuf> {
+        let target = pop_existing(&mut self.back)?;
+        self.forward.push(current.to_path_buf());
+        Some(target)
+    }
+
+    /// Steps forward from `current`, skipping folders that no longer exist.
+    pub fn forward(&mut self, current: &Path) -> Option<PathBuf> {
+        let target = pop_existing(&mut self.forward)?;
+        self.back.push(current.to_path_buf());
+        Some(target)
+    }
+
+    pub fn can_go_back(&self) -> bool {
+        !self.back.is_empty()
+    }
+
+    pub fn can_go_forward(&self) -> bool {
+        !self.forward.is_empty()
+    }
+}
+
+fn pop_existing(stack: &mut Vec<PathBuf>) -> Option<PathBuf> {
+    while let Some(path) = stack.pop() {
+        if path.is_dir() {
+            return Some(path);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn goes_back_and_forward() {
+        let dir = TestDir::new();
+        let (a, b, c) = (dir.dir("a"), dir.dir("b"), dir.dir("c"));
+        let mut history = History::new();
+        assert!(!history.can_go_back() && !history.can_go_forward());
+        history.visit(a.clone());
+        history.visit(a.clone());
+        history.visit(b.clone());
+
+        assert_eq!(history.back(&c), Some(b.clone()));
+        assert_eq!(history.back(&b), Some(a.clone()));
+        assert_eq!(history.back(&a), None);
+        assert_eq!(history.forward(&a), Some(b.clone()));
+        assert!(history.can_go_back() && history.can_go_forward());
+
+        history.visit(b);
+        assert!(!history.can_go_forward());
+    }
+
+    #[test]
+    fn skips_removed_folders() {
+        let dir = TestDir::new();
+        let (a, b) = (dir.dir("a"), dir.dir("b"));
+        let mut history = History::new();
+        history.visit(a.clone());
+        history.visit(b.clone());
+        std::fs::remove_dir(&b).unwrap();
+        assert_eq!(history.back(dir.path()), Some(a));
+        assert!(!history.can_go_back());
+    }
+
+    #[test]
+    fn keeps_a_bounded_history() {
+        let mut history = History::new();
+        for i in 0..MAX_ENTRIES + 10 {
+            history.visit(PathBuf::from(i.to_string()));
+        }
+        assert_eq!(history.back.len(), MAX_ENTRIES);
+        assert_eq!(history.back[0], PathBuf::from("10"));
+    }
+}
@@ -0,0 +1,739 @@
+//! Persistent index of the files under configured roots, so that searches
+//! are answered from memory instead of walking the disk.
+//!
+//! The index is saved under `$XDG_CACHE_HOME`, rebuilt by a periodic
+//! rescan and kept fresh in between by inotify watches on the indexed
+//! folders. Roots, exclusions and text extraction are read from
+//! `$XDG_CONFIG_HOME/file-manager/index.conf`.
+
+use crate::entry::DirEntryInfo;
+use crate::filter::glob_match;
+use crate::search::{is_binary, scan_text, Search, SearchMatch};
+use crate::trash::{decode_path, encode_path};
+use crate::watch::next_event;
+use crate::xdg;
+use inotify::{EventMask, Inotify, WatchDescriptor, WatchMask, Watches};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::{self, Write};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+use tokio::time::{self, Instant};
+
+/// Age after which the index is rebuilt from scratch.
+const RESCAN_INTERVAL: Duration = Duration::from_secs(30 * 60);
+/// Delay between a change to the index and saving it.
+const SAVE_DELAY: Duration = Duration::from_secs(10);
+/// Text files up to this size have their contents indexed.
+const MAX_INDEXED_TEXT: u64 = 64 * 1024;
+
+/// What is indexed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexConfig {
+    pub roots: Vec<PathBuf>,
+    /// Glob patterns of names left out of the index with their contents.
+    pub excludes: Vec<String>,
+    /// Also index the contents of small text files for content searches.
+    pub index_text: bool,
+}
+
+impl Default for IndexConfig {
+    fn default() -> Self {
+        Self {
+            roots: vec![xdg::home_dir()],
+            excludes: [".cache", ".git", "node_modules", "target"]
+                .map(String::from)
+                .to_vec(),
+            index_text: false,
+        }
+    }
+}
+
+impl IndexConfig {
+    /// `$XDG_CONFIG_HOME/file-manager/index.conf`.
+    pub fn default_path() -> PathBuf {
+        xdg::app_config_dir().join("index.conf")
+    }
+
+    /// Reads `key = value` lines: any number of `root` and `exclude`, and
+    /// `index_text = true|false`. Roots or excludes given in the file
+    /// replace the defaults. A missing file gives the defaults.
+    pub fn load(path: &Path) -> Self {
+        let mut config = Self::default();
+        let Ok(contents) = fs::read_to_string(path) else {
+            return config;
+        };
+        let (mut roots, mut excludes) = (Vec::new(), Vec::new());
+        for line in contents.lines() {
+            let line = line.trim();
+            if line.starts_with('#') {
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim() {
+                "root" => roots.push(PathBuf::from(value)),
+                "exclude" => excludes.push(value.to_string()),
+                "index_text" => config.index_text = value == "true",
+                _ => {}
+            }
+        }
+        if !roots.is_empty() {
+            config.roots = roots;
+        }
+        if !excludes.is_empty() {
+            config.excludes = excludes;
+        }
+        config
+    }
+
+    fn excluded(&self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        self.excludes
+            .iter()
+            .any(|pattern| glob_match(&pattern.to_lowercase(), &name))
+    }
+
+    /// Whether `path` is inside a root and not excluded.
+    pub fn covers(&self, path: &Path) -> bool {
+        self.roots.iter().any(|root| {
+            path.strip_prefix(root).is_ok_and(|relative| {
+                !relative
+                    .components()
+                    .any(|c| self.excluded(&c.as_os_str().to_string_lossy()))
+            })
+        })
+    }
+}
+
+/// An indexed file or folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexedFile {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub is_dir: bool,
+    /// Contents of a small text file, if text is indexed.
+    pub text: Option<Arc<str>>,
+}
+
+/// Files under the configured roots, by path.
+#[derive(Debug)]
+pub struct SearchIndex {
+    /// File the index is saved to; `None` keeps it in memory only.
+    path: Option<PathBuf>,
+    config: IndexConfig,
+    files: BTreeMap<PathBuf, IndexedFile>,
+    /// When the last full scan finished, `None` before the first one.
+    built: Option<SystemTime>,
+}
+
+impl SearchIndex {
+    pub fn in_memory(config: IndexConfig) -> Self {
+        Self {
+            path: None,
+            config,
+            files: BTreeMap::new(),
+            built: None,
+        }
+    }
+
+    /// Reads the index saved at `path`. A missing or unreadable index is
+    /// empty and built by the next scan.
+    pub fn load(path: PathBuf, config: IndexConfig) -> Self {
+        let mut index = Self::in_memory(config);
+        let contents = fs::read_to_string(&path).unwrap_or_default();
+        let mut lines = contents.lines();
+        index.built = lines
+            .next()
+            .and_then(|line| line.strip_prefix("built\t"))
+            .and_then(decode_time);
+        if index.built.is_some() {
+            index.files = lines
+                .filter_map(decode_file)
+                .map(|file| (file.path.clone(), file))
+                .collect();
+        }
+        index.path = Some(path);
+        index
+    }
+
+    /// `$XDG_CACHE_HOME/file-manager/index`.
+    pub fn default_path() -> PathBuf {
+        xdg::app_cache_dir().join("index")
+    }
+
+    pub fn config(&self) -> &IndexConfig {
+        &self.config
+    }
+
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Time until the index is due for a full rescan.
+    fn rescan_in(&self) -> Duration {
+        self.built
+            .and_then(|built| built.elapsed().ok())
+            .map_or(Duration::ZERO, |age| RESCAN_INTERVAL.saturating_sub(age))
+    }
+
+    /// Reads everything under the roots of `config`. The text of files
+    /// unchanged since `previous` is taken from there.
+    pub fn scan(
+        config: &IndexConfig,
+        previous: &BTreeMap<PathBuf, IndexedFile>,
+    ) -> BTreeMap<PathBuf, IndexedFile> {
+        let mut files = BTreeMap::new();
+        for root in &config.roots {
+            scan_tree(root, config, previous, &mut files);
+        }
+        files
+    }
+
+    /// Takes the result of a full scan.
+    fn replace(&mut self, files: BTreeMap<PathBuf, IndexedFile>) {
+        self.files = files;
+        self.built = Some(SystemTime::now());
+    }
+
+    /// Re-reads `path` and everything under it after a change. Returns the
+    /// folders that are now indexed there.
+    pub fn update(&mut self, path: &Path) -> Vec<PathBuf> {
+        self.remove(path);
+        if !self.config.covers(path) {
+            return Vec::new();
+        }
+        let mut files = BTreeMap::new();
+        scan_tree(path, &self.config, &BTreeMap::new(), &mut files);
+        let dirs = files
+            .values()
+            .filter(|file| file.is_dir)
+            .map(|file| file.path.clone())
+            .collect();
+        self.files.append(&mut files);
+        dirs
+    }
+
+    /// Forgets `path` and everything under it.
+    pub fn remove(&mut self, path: &Path) {
+        let below: Vec<PathBuf> = self
+            .files
+            .range::<Path, _>((Bound::Included(path), Bound::Unbounded))
+            .map(|(key, _)| key)
+            .take_while(|key| key.starts_with(path))
+            .cloned()
+            .collect();
+        for key in below {
+            self.files.remove(&key);
+        }
+    }
+
+    fn dirs(&self) -> Vec<PathBuf> {
+        self.files
+            .values()
+            .filter(|file| file.is_dir)
+            .map(|file| file.path.clone())
+            .collect()
+    }
+
+    /// Answers `search` from the index, with at most about `limit` matches.
+    /// Returns `None` if the index cannot answer it: the folder is not
+    /// indexed, the index is not built yet, or text is not indexed for a
+    /// content search.
+    pub fn lookup(&self, search: &Search, limit: usize) -> Option<Vec<SearchMatch>> {
+        let root = search.root();
+        let content = search.content_regex();
+        if self.built.is_none()
+            || !self.config.covers(root)
+            || (content.is_some() && !self.config.index_text)
+        {
+            return None;
+        }
+
+        let mut matches = Vec::new();
+        let below = self
+            .files
+            .range::<Path, _>((Bound::Excluded(root), Bound::Unbounded))
+            .map(|(_, file)| file)
+            .take_while(|file| file.path.starts_with(root));
+        for file in below {
+            if matches.len() >= limit {
+                break;
+            }
+            let relative = file.path.strip_prefix(root).unwrap_or(&file.path);
+            let skipped = relative
+                .components()
+                .any(|c| search.skipped(&c.as_os_str().to_string_lossy()));
+            if skipped {
+                continue;
+            }
+            let lines = match content {
+                Some(regex) => match &file.text {
+                    Some(text) => Some(scan_text(text.as_bytes(), regex)),
+                    None => continue,
+                },
+                None => None,
+            };
+            let name = file.path.file_name().unwrap_or_default().to_string_lossy();
+            let found = match &lines {
+                Some(lines) => !lines.is_empty(),
+                None => search.name_matches(&name),
+            };
+            if !found {
+                continue;
+            }
+            // Entries removed since they were indexed are left out.
+            let Ok(info) = DirEntryInfo::read(file.path.clone()) else {
+                continue;
+            };
+            match lines {
+                Some(lines) => matches.extend(
+                    lines
+                        .into_iter()
+                        .map(|line| search.found(info.clone(), Some(line))),
+                ),
+                None => matches.push(search.found(info, None)),
+            }
+        }
+        Some(matches)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let Some(built) = self.built else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("tmp");
+        let mut file = io::BufWriter::new(fs::File::create(&tmp)?);
+        writeln!(file, "built\t{}", encode_time(built))?;
+        for indexed in self.files.values() {
+            writeln!(file, "{}", encode_file(indexed))?;
+        }
+        file.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&tmp, path)
+    }
+}
+
+/// Adds `path` and, for a folder, everything under it to `files`.
+/// Symlinked folders are indexed as entries but not descended into.
+fn scan_tree(
+    path: &Path,
+    config: &IndexConfig,
+    previous: &BTreeMap<PathBuf, IndexedFile>,
+    files: &mut BTreeMap<PathBuf, IndexedFile>,
+) {
+    let mut pending = vec![path.to_path_buf()];
+    while let Some(path) = pending.pop() {
+        let Ok(meta) = fs::symlink_metadata(&path) else {
+            continue;
+        };
+        let modified = meta.modified().ok();
+        let is_dir = meta.is_dir();
+        let unchanged = previous
+            .get(&path)
+            .filter(|old| old.size == meta.len() && old.modified == modified);
+        let text = match unchanged {
+            Some(old) => old.text.clone(),
+            None if config.index_text && meta.is_file() && meta.len() <= MAX_INDEXED_TEXT => {
+                fs::read(&path)
+                    .ok()
+                    .filter(|contents| !is_binary(contents))
+                    .map(|contents| String::from_utf8_lossy(&contents).into())
+            }
+            None => None,
+        };
+        if is_dir {
+            if let Ok(read_dir) = fs::read_dir(&path) {
+                for entry in read_dir.flatten() {
+                    if !config.excluded(&entry.file_name().to_string_lossy()) {
+                        pending.push(entry.path());
+                    }
+                }
+            }
+        }
+        files.insert(
+            path.clone(),
+            IndexedFile {
+                path,
+                size: if is_dir { 0 } else { meta.len() },
+                modified,
+                is_dir,
+                text,
+            },
+        );
+    }
+}
+
+fn encode_time(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    format!(
+        "{}.{:09}",
+        since_epoch.as_secs(),
+        since_epoch.subsec_nanos()
+    )
+}
+
+fn decode_time(value: &str) -> Option<SystemTime> {
+    let (secs, nanos) = value.split_once('.')?;
+    let since_epoch = Duration::new(secs.parse().ok()?, nanos.parse().ok()?);
+    Some(UNIX_EPOCH + since_epoch)
+}
+
+/// One tab-separated line: path, size, modification time, `d` or `f`, and
+/// the text. Path and text are percent-encoded, missing values are `-`.
+fn encode_file(file: &IndexedFile) -> String {
+    format!(
+        "{}\t{}\t{}\t{}\t{}",
+        encode_path(&file.path),
+        file.size,
+        file.modified.map_or("-".to_string(), encode_time),
+        if file.is_dir { "d" } else { "f" },
+        file.text
+            .as_deref()
+            .map_or("-".to_string(), |text| encode_path(Path::new(text)))
+    )
+}
+
+fn decode_file(line: &str) -> Option<IndexedFile> {
+    let mut fields = line.split('\t');
+    let path = decode_path(fields.next()?);
+    let size = fields.next()?.parse().ok()?;
+    let modified = decode_time(fields.next()?);
+    let is_dir = fields.next()? == "d";
+    let text = match fields.next()? {
+        "-" => None,
+        text => Some(decode_path(text).to_string_lossy().into()),
+    };
+    Some(IndexedFile {
+        path,
+        size,
+        modified,
+        is_dir,
+        text,
+    })
+}
+
+/// Keeps a [`SearchIndex`] up to date on the tokio runtime. Dropping it
+/// stops the updates and saves the index.
+#[derive(Debug)]
+pub struct IndexService {
+    index: Arc<Mutex<SearchIndex>>,
+    task: JoinHandle<()>,
+}
+
+impl IndexService {
+    /// Starts maintaining `index` on the current tokio runtime. A rescan
+    /// starts right away if the index is missing or old.
+    pub fn start(index: SearchIndex) -> Self {
+        let index = Arc::new(Mutex::new(index));
+        let shared = index.clone();
+        let task = tokio::spawn(async move {
+            if let Err(e) = maintain(shared).await {
+                tracing::warn!("Search index is only refreshed by rescans: {}", e);
+            }
+        });
+        Self { index, task }
+    }
+
+    /// A handle for looking up the index from another thread.
+    pub fn reader(&self) -> IndexReader {
+        IndexReader(self.index.clone())
+    }
+}
+
+/// Shared read access to the index kept by an [`IndexService`].
+#[derive(Debug, Clone)]
+pub struct IndexReader(Arc<Mutex<SearchIndex>>);
+
+impl IndexReader {
+    /// See [`SearchIndex::lookup`].
+    pub fn lookup(&self, search: &Search, limit: usize) -> Option<Vec<SearchMatch>> {
+        self.0.lock().ok()?.lookup(search, limit)
+    }
+}
+
+impl Drop for IndexService {
+    fn drop(&mut self) {
+        self.task.abort();
+        if let Ok(index) = self.index.lock() {
+            if let Err(e) = index.save() {
+                tracing::warn!("Failed to save search index: {}", e);
+            }
+        }
+    }
+}
+
+/// Rescans the index when due and applies inotify events in between.
+async fn maintain(index: Arc<Mutex<SearchIndex>>) -> io::Result<()> {
+    let inotify = Inotify::init()?;
+    let mut stream = inotify.into_event_stream(vec![0; 4096])?;
+    let mut watcher = TreeWatcher {
+        watches: stream.watches(),
+        dirs: HashMap::new(),
+        full: false,
+    };
+
+    let (dirs, rescan_in) = {
+        let index = lock(&index);
+        (index.dirs(), index.rescan_in())
+    };
+    watcher.add_all(dirs);
+    let mut rescan_at = Instant::now() + rescan_in;
+    let mut save_at = None;
+
+    loop {
+        if Instant::now() >= rescan_at {
+            let (config, previous) = {
+                let index = lock(&index);
+                (index.config.clone(), index.files.clone())
+            };
+            let files = tokio::task::spawn_blocking(move || SearchIndex::scan(&config, &previous))
+                .await
+                .map_err(io::Error::other)?;
+            let dirs = {
+                let mut index = lock(&index);
+                index.replace(files);
+                index.dirs()
+            };
+            watcher.add_all(dirs);
+            rescan_at = Instant::now() + RESCAN_INTERVAL;
+            save_at = Some(Instant::now());
+        }
+
+        let wake_at = save_at.map_or(rescan_at, |save_at: Instant| save_at.min(rescan_at));
+        tokio::select! {
+            event = next_event(&mut stream) => {
+                let Some(event) = event else {
+                    return Ok(());
+                };
+                let event = event?;
+                if event.mask.contains(EventMask::Q_OVERFLOW) {
+                    rescan_at = Instant::now();
+                    continue;
+                }
+                if event.mask.contains(EventMask::IGNORED) {
+                    watcher.dirs.remove(&event.wd);
+                    continue;
+                }
+                let (Some(dir), Some(name)) = (watcher.dirs.get(&event.wd), event.name) else {
+                    continue;
+                };
+                let path = dir.join(name);
+                let new_dirs = lock(&index).update(&path);
+                watcher.add_all(new_dirs);
+                save_at.get_or_insert(Instant::now() + SAVE_DELAY);
+            }
+            _ = time::sleep_until(wake_at) => {}
+        }
+
+        if save_at.is_some_and(|save_at| Instant::now() >= save_at) {
+            save_at = None;
+            if let Err(e) = lock(&index).save() {
+                tracing::warn!("Failed to save search index: {}", e);
+            }
+        }
+    }
+}
+
+fn lock(index: &Mutex<SearchIndex>) -> std::sync::MutexGuard<'_, SearchIndex> {
+    index
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// inotify watches on the indexed folders.
+struct TreeWatcher {
+    watches: Watches,
+    dirs: HashMap<WatchDescriptor, PathBuf>,
+    /// The watch limit was reached; changes are then only picked up by
+    /// rescans.
+    full: bool,
+}
+
+impl TreeWatcher {
+    fn add_all(&mut self, dirs: Vec<PathBuf>) {
+        for dir in dirs {
+            if self.full {
+                return;
+            }
+            let mask = WatchMask::CREATE
+                | WatchMask::DELETE
+                | WatchMask::MOVED_FROM
+                | WatchMask::MOVED_TO
+                | WatchMask::CLOSE_WRITE
+                | WatchMask::ONLYDIR
+                | WatchMask::DONT_FOLLOW;
+            match self.watches.add(&dir, mask) {
+                Ok(wd) => {
+                    self.dirs.insert(wd, dir);
+                }
+                Err(e) if e.raw_os_error() == Some(libc::ENOSPC) => {
+                    tracing::warn!("inotify watch limit reached, indexing {:?} by rescans", dir);
+                    self.full = true;
+                }
+                Err(_) => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{SearchMode, SearchQuery};
+    use crate::test_dir::TestDir;
+
+    fn config(dir: &TestDir, index_text: bool) -> IndexConfig {
+        IndexConfig {
+            roots: vec![dir.path().to_path_buf()],
+            excludes: vec!["target".into()],
+            index_text,
+        }
+    }
+
+    fn built(config: IndexConfig) -> SearchIndex {
+        let mut index = SearchIndex::in_memory(config);
+        let files = SearchIndex::scan(&index.config, &BTreeMap::new());
+        index.replace(files);
+        index
+    }
+
+    /// Relative paths of the matches `index` gives for `text` under `root`.
+    fn lookup(
+        index: &SearchIndex,
+        root: &Path,
+        text: &str,
+        mode: SearchMode,
+    ) -> Option<Vec<String>> {
+        let query = SearchQuery {
+            text: text.into(),
+            mode,
+            ..SearchQuery::default()
+        };
+        let search = Search::new(root.to_path_buf(), &query).unwrap();
+        let matches = index.lookup(&search, 10)?;
+        Some(
+            matches
+                .into_iter()
+                .map(|m| m.relative_path.to_string_lossy().to_string())
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn reads_the_config() {
+        let dir = TestDir::new();
+        let path = dir.file(
+            "index.conf",
+            "# comment\nroot = /a\nroot=/b\nexclude = *.o\nindex_text = true\nbogus\n",
+        );
+        let config = IndexConfig::load(&path);
+        assert_eq!(config.roots, [PathBuf::from("/a"), PathBuf::from("/b")]);
+        assert_eq!(config.excludes, ["*.o"]);
+        assert!(config.index_text);
+        assert!(config.covers(Path::new("/a/src/main.rs")));
+        assert!(!config.covers(Path::new("/a/src/main.O")));
+        assert!(!config.covers(Path::new("/c")));
+        assert_eq!(
+            IndexConfig::load(&dir.path().join("missing")),
+            IndexConfig::default()
+        );
+    }
+
+    #[test]
+    fn answers_searches_under_its_roots() {
+        let dir = TestDir::new();
+        dir.file("docs/notes.txt", "");
+        dir.file("docs/.hidden/notes.txt", "");
+        dir.file("target/notes.txt", "");
+        dir.file("other.txt", "");
+        let index = SearchIndex::in_memory(config(&dir, false));
+        assert_eq!(
+            lookup(&index, dir.path(), "notes", SearchMode::Substring),
+            None
+        );
+
+        let index = built(config(&dir, false));
+        assert_eq!(
+            lookup(&index, dir.path(), "notes", SearchMode::Substring).unwrap(),
+            ["docs/notes.txt"]
+        );
+        let docs = dir.path().join("docs");
+        assert_eq!(
+            lookup(&index, &docs, "*.txt", SearchMode::Glob).unwrap(),
+            ["notes.txt"]
+        );
+        assert_eq!(
+            lookup(&index, Path::new("/"), "notes", SearchMode::Substring),
+            None
+        );
+        assert_eq!(lookup(&index, dir.path(), "x", SearchMode::Content), None);
+    }
+
+    #[test]
+    fn looks_up_indexed_text() {
+        let dir = TestDir::new();
+        dir.file("a.conf", "port = 80\nhost = x\n");
+        dir.file("b.bin", b"host\0");
+        let index = built(config(&dir, true));
+        assert_eq!(
+            lookup(&index, dir.path(), "HOST", SearchMode::Content).unwrap(),
+            ["a.conf"]
+        );
+    }
+
+    #[test]
+    fn follows_changes() {
+        let dir = TestDir::new();
+        let old = dir.file("docs/old.txt", "");
+        let mut index = built(config(&dir, false));
+
+        let docs = dir.path().join("docs");
+        fs::remove_dir_all(&docs).unwrap();
+        dir.file("docs/sub/new.txt", "");
+        assert_eq!(index.update(&docs), [docs.clone(), docs.join("sub")]);
+        assert!(!index.files.contains_key(&old));
+        assert_eq!(
+            lookup(&index, dir.path(), "new", SearchMode::Substring).unwrap(),
+            ["docs/sub/new.txt"]
+        );
+
+        assert!(index.update(&dir.path().join("target")).is_empty());
+        index.remove(&docs);
+        assert_eq!(index.len(), 1);
+    }
+
+    #[test]
+    fn survives_a_restart() {
+        let dir = TestDir::new();
+        dir.file("a b.txt", "tab\there");
+        dir.dir("folder");
+        let path = dir.path().join("cache/index");
+        let mut index = SearchIndex::load(path.clone(), config(&dir, true));
+        index.save().unwrap();
+        assert!(!path.exists());
+
+        let files = SearchIndex::scan(&index.config, &BTreeMap::new());
+        index.replace(files);
+        index.save().unwrap();
+        let loaded = SearchIndex::load(path, config(&dir, true));
+        assert_eq!(loaded.files, index.files);
+        assert_eq!(loaded.built, index.built);
+        assert!(loaded.rescan_in() > Duration::ZERO);
+    }
+}
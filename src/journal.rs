@@ -0,0 +1,549 @@
+//! Undo/redo journal of the file operations performed through the model.
+//!
+//! Every entry is an [`Operation`] that knows how to revert and re-apply
+//! itself. The journal is written to disk after each change so that the
+//! history survives a restart or a crash.
+
+use crate::error::{FileOp, FileOpError};
+use crate::model::{ModelError, ModelResult};
+use crate::transfer::{self, CancelToken, TransferProgress};
+use crate::trash::{self, decode_path, encode_path, TrashedItem};
+use crate::xdg;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Number of operations kept on the undo stack.
+const MAX_ENTRIES: usize = 100;
+
+/// A reversible change to the filesystem.
+///
+/// Undo and redo are idempotent per path: a step whose effect is already
+/// visible is skipped, so an operation that failed halfway can be retried
+/// once the cause of the failure is gone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Operation {
+    Rename { from: PathBuf, to: PathBuf },
+    CreateFolder { path: PathBuf },
+    /// Entries moved from the first path to the second.
+    Move { moves: Vec<(PathBuf, PathBuf)> },
+    Trash { items: Vec<TrashedItem> },
+    /// Copies that were written, with their trash entry while undone.
+    Copy {
+        items: Vec<(PathBuf, Option<TrashedItem>)>,
+    },
+}
+
+impl Operation {
+    /// Short description such as `Rename 'a' to 'b'`.
+    pub fn describe(&self) -> String {
+        match self {
+            Operation::Rename { from, to } => {
+                format!("Rename '{}' to '{}'", file_name(from), file_name(to))
+            }
+            Operation::CreateFolder { path } => format!("Create folder '{}'", file_name(path)),
+            Operation::Move { moves } => {
+                format!("Move {}", count_or_name(moves.len(), moves.first().map(|m| &m.0)))
+            }
+            Operation::Trash { items } => format!(
+                "Trash {}",
+                count_or_name(items.len(), items.first().map(|i| &i.original_path))
+            ),
+            Operation::Copy { items } => {
+                format!("Copy {}", count_or_name(items.len(), items.first().map(|i| &i.0)))
+            }
+        }
+    }
+
+    /// Reverts the operation: renames and moves back, restores trashed
+    /// entries, removes the created folder (only while it is empty) and
+    /// moves copies to the trash.
+    pub fn undo(&mut self) -> ModelResult<()> {
+        match self {
+            Operation::Rename { from, to } => relocate(to, from),
+            Operation::CreateFolder { path } => match fs::remove_dir(&*path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    Err(FileOpError::new(FileOp::Delete, &*path, e).into())
+                }
+                _ => Ok(()),
+            },
+            Operation::Move { moves } => {
+                for (from, to) in moves.iter().rev() {
+                    relocate(to, from)?;
+                }
+                Ok(())
+            }
+            Operation::Trash { items } => {
+                for item in items.iter() {
+                    let restored = fs::symlink_metadata(item.files_path()).is_err()
+                        && fs::symlink_metadata(&item.original_path).is_ok();
+                    if !restored {
+                        trash::restore(item).map_err(|e| {
+                            FileOpError::new(FileOp::Restore, &item.original_path, e)
+                        })?;
+                    }
+                }
+                Ok(())
+            }
+            Operation::Copy { items } => {
+                for (path, trashed) in items.iter_mut() {
+                    if trashed.is_none() {
+                        let item = trash::trash(path)
+                            .map_err(|e| FileOpError::new(FileOp::Trash, &*path, e))?;
+                        *trashed = Some(item);
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Applies the operation again after [`Self::undo`].
+    pub fn redo(&mut self) -> ModelResult<()> {
+        match self {
+            Operation::Rename { from, to } => relocate(from, to),
+            Operation::CreateFolder { path } => match fs::create_dir(&*path) {
+                Err(e) if !(e.kind() == io::ErrorKind::AlreadyExists && path.is_dir()) => {
+                    Err(FileOpError::new(FileOp::CreateFolder, &*path, e).into())
+                }
+                _ => Ok(()),
+            },
+            Operation::Move { moves } => {
+                for (from, to) in moves.iter() {
+                    relocate(from, to)?;
+                }
+                Ok(())
+            }
+            Operation::Trash { items } => {
+                for item in items.iter_mut() {
+                    let trashed = fs::symlink_metadata(item.files_path()).is_ok()
+                        && fs::symlink_metadata(&item.original_path).is_err();
+                    if !trashed {
+                        *item = trash::trash(&item.original_path).map_err(|e| {
+                            FileOpError::new(FileOp::Trash, &item.original_path, e)
+                        })?;
+                    }
+                }
+                Ok(())
+            }
+            Operation::Copy { items } => {
+                for (_, trashed) in items.iter_mut() {
+                    if let Some(item) = trashed {
+                        trash::restore(item).map_err(|e| {
+                            FileOpError::new(FileOp::Restore, &item.original_path, e)
+                        })?;
+                        *trashed = None;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// One journal line: the kind followed by tab-separated, percent-encoded
+    /// paths.
+    fn encode(&self) -> String {
+        let mut fields: Vec<String> = Vec::new();
+        let kind = match self {
+            Operation::Rename { from, to } => {
+                fields.extend([encode_path(from), encode_path(to)]);
+                "rename"
+            }
+            Operation::CreateFolder { path } => {
+                fields.push(encode_path(path));
+                "create"
+            }
+            Operation::Move { moves } => {
+                for (from, to) in moves {
+                    fields.extend([encode_path(from), encode_path(to)]);
+                }
+                "move"
+            }
+            Operation::Trash { items } => {
+                for item in items {
+                    fields.extend(encode_trashed(Some(item), &item.original_path));
+                }
+                "trash"
+            }
+            Operation::Copy { items } => {
+                for (path, trashed) in items {
+                    fields.extend(encode_trashed(trashed.as_ref(), path));
+                }
+                "copy"
+            }
+        };
+        fields.insert(0, kind.to_string());
+        fields.join("\t")
+    }
+
+    fn decode(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let kind = fields.next()?;
+        let paths: Vec<PathBuf> = fields.map(decode_path).collect();
+        let op = match kind {
+            "rename" => match paths.as_slice() {
+                [from, to] => Operation::Rename {
+                    from: from.clone(),
+                    to: to.clone(),
+                },
+                _ => return None,
+            },
+            "create" => match paths.as_slice() {
+                [path] => Operation::CreateFolder { path: path.clone() },
+                _ => return None,
+            },
+            "move" if paths.len().is_multiple_of(2) => Operation::Move {
+                moves: paths
+                    .chunks(2)
+                    .map(|pair| (pair[0].clone(), pair[1].clone()))
+                    .collect(),
+            },
+            "trash" if paths.len().is_multiple_of(3) => Operation::Trash {
+                items: paths
+                    .chunks(3)
+                    .map(decode_trashed)
+                    .collect::<Option<_>>()?,
+            },
+            "copy" if paths.len().is_multiple_of(3) => Operation::Copy {
+                items: paths
+                    .chunks(3)
+                    .map(|fields| (fields[0].clone(), decode_trashed(fields)))
+                    .collect(),
+            },
+            _ => return None,
+        };
+        Some(op)
+    }
+}
+
+/// Undo and redo stacks, persisted to a file.
+#[derive(Debug, Clone, Default)]
+pub struct Journal {
+    /// File the journal is saved to; `None` keeps it in memory only.
+    path: Option<PathBuf>,
+    undo: Vec<Operation>,
+    redo: Vec<Operation>,
+}
+
+impl Journal {
+    /// A journal that is not saved anywhere.
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Reads the journal stored at `path`, starting empty if there is none.
+    /// Lines that cannot be parsed are dropped.
+    pub fn load(path: PathBuf) -> Self {
+        let mut journal = Self {
+            path: Some(path),
+            ..Self::default()
+        };
+        let contents = journal
+            .path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .unwrap_or_default();
+        for line in contents.lines() {
+            let Some((stack, op)) = line.split_once('\t') else {
+                continue;
+            };
+            match (stack, Operation::decode(op)) {
+                ("undo", Some(op)) => journal.undo.push(op),
+                ("redo", Some(op)) => journal.redo.push(op),
+                _ => {}
+            }
+        }
+        journal
+    }
+
+    /// `$XDG_STATE_HOME/file-manager/journal`.
+    pub fn default_path() -> PathBuf {
+        xdg::app_state_dir().join("journal")
+    }
+
+    /// The operation [`Self::undo`] would revert.
+    pub fn last_undo(&self) -> Option<&Operation> {
+        self.undo.last()
+    }
+
+    /// The operation [`Self::redo`] would re-apply.
+    pub fn last_redo(&self) -> Option<&Operation> {
+        self.redo.last()
+    }
+
+    /// Records a completed operation. This clears the redo stack.
+    pub fn record(&mut self, op: Operation) {
+        self.undo.push(op);
+        if self.undo.len() > MAX_ENTRIES {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        self.persist();
+    }
+
+    /// Reverts the most recent operation. If that fails, the operation stays
+    /// on the undo stack so it can be retried.
+    pub fn undo(&mut self) -> ModelResult<Operation> {
+        let mut op = self.undo.pop().ok_or(ModelError::NothingToUndo)?;
+        let result = op.undo();
+        match result {
+            Ok(()) => self.redo.push(op.clone()),
+            Err(_) => self.undo.push(op.clone()),
+        }
+        self.persist();
+        result.map(|_| op)
+    }
+
+    /// Re-applies the most recently undone operation. If that fails, the
+    /// operation stays on the redo stack so it can be retried.
+    pub fn redo(&mut self) -> ModelResult<Operation> {
+        let mut op = self.redo.pop().ok_or(ModelError::NothingToRedo)?;
+        let result = op.redo();
+        match result {
+            Ok(()) => self.undo.push(op.clone()),
+            Err(_) => self.redo.push(op.clone()),
+        }
+        self.persist();
+        result.map(|_| op)
+    }
+
+    /// Writes the journal to its file.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Write a sibling first so a crash never leaves a truncated journal.
+        let tmp = path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp)?;
+        for op in &self.undo {
+            writeln!(file, "undo\t{}", op.encode())?;
+        }
+        for op in &self.redo {
+            writeln!(file, "redo\t{}", op.encode())?;
+        }
+        file.sync_all()?;
+        fs::rename(&tmp, path)
+    }
+
+    fn persist(&self) {
+        if let Err(e) = self.save() {
+            tracing::warn!("Failed to save journal: {}", e);
+        }
+    }
+}
+
+/// Moves `from` to `to`, across filesystems if needed. Does nothing if the
+/// move already happened.
+fn relocate(from: &Path, to: &Path) -> ModelResult<()> {
+    let from_exists = fs::symlink_metadata(from).is_ok();
+    let to_exists = fs::symlink_metadata(to).is_ok();
+    if !from_exists && to_exists {
+        return Ok(());
+    }
+    if to_exists {
+        return Err(ModelError::AlreadyExists(file_name(to)));
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).map_err(|e| FileOpError::new(FileOp::Move, parent, e))?;
+    }
+    let mut progress = TransferProgress::default();
+    transfer::move_path(
+        from,
+        to,
+        None,
+        &CancelToken::new(),
+        &mut progress,
+        &mut |_| {},
+    )?;
+    Ok(())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
+
+fn count_or_name(count: usize, first: Option<&PathBuf>) -> String {
+    match (count, first) {
+        (1, Some(path)) => format!("'{}'", file_name(path)),
+        _ => format!("{} items", count),
+    }
+}
+
+/// `path`, then the trash directory and entry name, which are empty while
+/// the entry is not in the trash.
+fn encode_trashed(item: Option<&TrashedItem>, path: &Path) -> [String; 3] {
+    match item {
+        Some(item) => [
+            encode_path(path),
+            encode_path(&item.trash_dir),
+            encode_path(Path::new(&item.name)),
+        ],
+        None => [encode_path(path), String::new(), String::new()],
+    }
+}
+
+fn decode_trashed(fields: &[PathBuf]) -> Option<TrashedItem> {
+    match fields {
+        [original_path, trash_dir, name] if !name.as_os_str().is_empty() => Some(TrashedItem {
+            trash_dir: trash_dir.clone(),
+            name: name.clone().into_os_string(),
+            original_path: original_path.clone(),
+            deletion_date: None,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    fn trashed(name: &str) -> TrashedItem {
+        TrashedItem {
+            trash_dir: PathBuf::from("/home/me/.local/share/Trash"),
+            name: name.into(),
+            original_path: PathBuf::from("/home/me").join(name),
+            deletion_date: None,
+        }
+    }
+
+    #[test]
+    fn operations_survive_encoding() {
+        let ops = [
+            Operation::Rename {
+                from: "/a/old name".into(),
+                to: "/a/new\tname".into(),
+            },
+            Operation::CreateFolder {
+                path: "/a/100%".into(),
+            },
+            Operation::Move {
+                moves: vec![
+                    ("/a/x".into(), "/b/x".into()),
+                    ("/a/y".into(), "/b/y".into()),
+                ],
+            },
+            Operation::Trash {
+                items: vec![trashed("a.txt"), trashed("b.txt")],
+            },
+            Operation::Copy {
+                items: vec![
+                    ("/b/x".into(), None),
+                    // The trash entry of a copy was made from the copy itself.
+                    ("/home/me/y".into(), Some(trashed("y"))),
+                ],
+            },
+        ];
+        for op in ops {
+            assert_eq!(Operation::decode(&op.encode()), Some(op));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        for line in [
+            "",
+            "rename\t/a",
+            "create\t/a\t/b",
+            "move\t/a",
+            "trash\t/a\t/t\t",
+            "delete\t/a",
+        ] {
+            assert_eq!(Operation::decode(line), None, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn describes_operations() {
+        let rename = Operation::Rename {
+            from: "/a/old".into(),
+            to: "/a/new".into(),
+        };
+        assert_eq!(rename.describe(), "Rename 'old' to 'new'");
+        let trash = Operation::Trash {
+            items: vec![trashed("a"), trashed("b")],
+        };
+        assert_eq!(trash.describe(), "Trash 2 items");
+    }
+
+    #[test]
+    fn undoes_and_redoes_across_restarts() {
+        let dir = TestDir::new();
+        let path = dir.path().join("state/journal");
+        let from = dir.file("a.txt", "a");
+        let to = dir.path().join("sub/a.txt");
+        let folder = dir.dir("new");
+
+        let mut journal = Journal::load(path.clone());
+        journal.record(Operation::CreateFolder {
+            path: folder.clone(),
+        });
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::rename(&from, &to).unwrap();
+        journal.record(Operation::Move {
+            moves: vec![(from.clone(), to.clone())],
+        });
+
+        let mut journal = Journal::load(path.clone());
+        assert!(matches!(journal.undo().unwrap(), Operation::Move { .. }));
+        assert!(from.exists() && !to.exists());
+
+        let mut journal = Journal::load(path.clone());
+        assert_eq!(
+            journal.last_undo(),
+            Some(&Operation::CreateFolder {
+                path: folder.clone()
+            })
+        );
+        journal.undo().unwrap();
+        assert!(!folder.exists());
+        assert!(matches!(journal.undo(), Err(ModelError::NothingToUndo)));
+
+        let mut journal = Journal::load(path);
+        journal.redo().unwrap();
+        journal.redo().unwrap();
+        assert!(folder.is_dir() && to.exists() && !from.exists());
+    }
+
+    #[test]
+    fn a_failed_undo_can_be_retried() {
+        let dir = TestDir::new();
+        let from = dir.path().join("a");
+        let to = dir.file("b", "");
+        let mut journal = Journal::in_memory();
+        journal.record(Operation::Rename {
+            from: from.clone(),
+            to: to.clone(),
+        });
+
+        dir.file("a", "in the way");
+        assert!(matches!(journal.undo(), Err(ModelError::AlreadyExists(_))));
+        fs::remove_file(&from).unwrap();
+        journal.undo().unwrap();
+        assert!(from.exists() && !to.exists());
+    }
+
+    #[test]
+    fn recording_clears_redo() {
+        let mut journal = Journal::in_memory();
+        for i in 0..MAX_ENTRIES + 1 {
+            journal.record(Operation::CreateFolder {
+                path: PathBuf::from(format!("/nonexistent/{}", i)),
+            });
+        }
+        assert_eq!(journal.undo.len(), MAX_ENTRIES);
+        journal.undo().unwrap();
+        assert!(journal.last_redo().is_some());
+        journal.record(Operation::CreateFolder {
+            path: "/nonexistent/x".into(),
+        });
+        assert!(journal.last_redo().is_none());
+    }
+}
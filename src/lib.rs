@@ -0,0 +1,25 @@
+pub mod entry;
+pub mod error;
+pub mod filter;
+pub mod format;
+pub mod hexdump;
+pub mod history;
+pub mod index;
+pub mod journal;
+pub mod model;
+pub mod paste;
+pub mod pdf;
+pub mod picture;
+pub mod places;
+pub mod recent;
+pub mod search;
+pub mod sort;
+pub mod textfile;
+pub mod toast;
+pub mod transfer;
+pub mod trash;
+pub mod watch;
+pub mod xdg;
+
+#[cfg(test)]
+mod test_dir;
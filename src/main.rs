@@ -3,13 +3,13 @@ use std::time::Duration;
 use tracing_subscriber::EnvFilter;
 
 use mctk_core::reexports::cosmic_text;
-use mctk_core::AssetParams;
+use mctk_core::{msg, AssetParams};
 use mctk_smithay::layer_shell::layer_surface::LayerOptions;
 use mctk_smithay::layer_shell::layer_window;
-use mctk_smithay::{WindowInfo, WindowOptions};
+use mctk_smithay::{WindowInfo, WindowMessage, WindowOptions};
 use smithay_client_toolkit::shell::wlr_layer;
 mod gui;
-use gui::{FileManager, FileManagerParams};
+use gui::{FileManager, FileManagerParams, Message};
 
 
 #[tokio::main]
@@ -20,6 +20,12 @@ async fn main() -> anyhow::Result<()> {
         .with_env_filter(env_filter)
         .init();
 
+    // Name sorting collates by the user's locale rather than the "C" one.
+    // SAFETY: nothing has been spawned yet that could read the locale.
+    unsafe {
+        libc::setlocale(libc::LC_COLLATE, c"".as_ptr());
+    }
+
     let mut fonts = cosmic_text::fontdb::Database::new();
     fonts.load_system_fonts();
 
@@ -88,7 +94,9 @@ async fn main() -> anyhow::Result<()> {
         AssetParams::new("src/assets/icons/image.png".to_string()),
     );
 
-    let (mut app, mut event_loop, ..) =
+    let (app_channel, mut app_rx) = tokio::sync::mpsc::unbounded_channel::<Message>();
+
+    let (mut app, mut event_loop, window_tx) =
         layer_window::LayerWindow::open_blocking::<FileManager, FileManagerParams>(
             layer_window::LayerWindowParams {
                 window_info,
@@ -99,9 +107,20 @@ async fn main() -> anyhow::Result<()> {
                 layer_shell_opts,
                 ..Default::default()
             },
-            FileManagerParams {},
+            FileManagerParams {
+                app_channel: Some(app_channel),
+            },
         );
 
+    // Forward messages from background tasks into the component tree
+    tokio::spawn(async move {
+        while let Some(message) = app_rx.recv().await {
+            let _ = window_tx.send(WindowMessage::Send {
+                message: msg!(message),
+            });
+        }
+    });
+
     loop {
         event_loop
             .dispatch(Duration::from_millis(16), &mut app)
@@ -0,0 +1,1154 @@
+use crate::entry::DirEntryInfo;
+use crate::error::{FileOp, FileOpError};
+use crate::filter::Filter;
+use crate::history::History;
+use crate::journal::{Journal, Operation};
+use crate::paste::{ConflictResolution, PasteJob};
+use crate::sort::{SortOrder, SortPreferences};
+use crate::transfer::{CancelToken, TransferError};
+use crate::trash::{self, TrashedItem};
+use crate::watch::DirChange;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+
+/// Most tabs open at once.
+pub const MAX_TABS: usize = 6;
+
+/// Errors returned by [`FileManagerModel`] operations.
+#[derive(Debug)]
+pub enum ModelError {
+    NothingSelected,
+    MultipleSelected,
+    ClipboardEmpty,
+    EmptyName,
+    AlreadyExists(String),
+    NoParent,
+    Cancelled,
+    NothingToUndo,
+    NothingToRedo,
+    NoPreviousFolder,
+    NoNextFolder,
+    TooManyTabs,
+    LastTab,
+    /// The selection holds the current directory or one of its ancestors.
+    ContainsCurrentFolder,
+    FileOp(FileOpError),
+    Io(io::Error),
+}
+
+impl fmt::Display for ModelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelError::NothingSelected => write!(f, "No file/folder selected."),
+            ModelError::MultipleSelected => write!(f, "Select a single file/folder."),
+            ModelError::ClipboardEmpty => write!(f, "No file/folder copied."),
+            ModelError::EmptyName => write!(f, "Name cannot be empty."),
+            ModelError::AlreadyExists(name) => {
+                write!(f, "A file or folder named '{}' already exists.", name)
+            }
+            ModelError::NoParent => write!(f, "No parent directory."),
+            ModelError::Cancelled => write!(f, "Transfer cancelled."),
+            ModelError::NothingToUndo => write!(f, "Nothing to undo."),
+            ModelError::NothingToRedo => write!(f, "Nothing to redo."),
+            ModelError::NoPreviousFolder => write!(f, "No previous folder."),
+            ModelError::NoNextFolder => write!(f, "No next folder."),
+            ModelError::TooManyTabs => write!(f, "Too many tabs open."),
+            ModelError::LastTab => write!(f, "The last tab cannot be closed."),
+            ModelError::ContainsCurrentFolder => {
+                write!(f, "Cannot remove the folder shown or a folder holding it.")
+            }
+            ModelError::FileOp(e) => write!(f, "{}", e),
+            ModelError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl ModelError {
+    /// What the user can do about the error, if anything.
+    pub fn remedy(&self) -> Option<&'static str> {
+        match self {
+            ModelError::AlreadyExists(_) => Some("Choose another name."),
+            ModelError::TooManyTabs => Some("Close a tab first."),
+            ModelError::ContainsCurrentFolder => Some("Go to another folder first."),
+            ModelError::FileOp(e) => e.remedy(),
+            _ => None,
+        }
+    }
+}
+
+impl std::error::Error for ModelError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ModelError::FileOp(e) => Some(e),
+            ModelError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ModelError {
+    fn from(e: io::Error) -> Self {
+        ModelError::Io(e)
+    }
+}
+
+impl From<FileOpError> for ModelError {
+    fn from(e: FileOpError) -> Self {
+        ModelError::FileOp(e)
+    }
+}
+
+impl From<TransferError> for ModelError {
+    fn from(e: TransferError) -> Self {
+        match e {
+            TransferError::Cancelled => ModelError::Cancelled,
+            TransferError::Failed(e) => ModelError::FileOp(e),
+        }
+    }
+}
+
+pub type ModelResult<T> = Result<T, ModelError>;
+
+/// Headless state of a file browser: the current directory, its listing,
+/// the selected entries, the clipboard, the undo journal, the sort order
+/// chosen for each directory, the filter on what is listed and the history
+/// of visited directories.
+///
+/// Several tabs can be open. The directory, selection, sort order, filter,
+/// history and scroll position belong to the active tab; the clipboard,
+/// journal and sort preferences are shared by all tabs.
+///
+/// Every operation works directly on the filesystem and returns a typed
+/// result, so the model can be driven from tests or any mctk front-end.
+#[derive(Debug, Clone)]
+pub struct FileManagerModel {
+    current_path: PathBuf,
+    entries: Vec<DirEntryInfo>,
+    selection: BTreeSet<PathBuf>,
+    clipboard: Option<Clipboard>,
+    journal: Journal,
+    sort: SortOrder,
+    sort_preferences: SortPreferences,
+    filter: Filter,
+    history: History,
+    /// Every open tab. The one at `active_tab` is empty, its state is in the
+    /// fields above while it is shown.
+    tabs: Vec<Tab>,
+    active_tab: usize,
+    next_tab_id: u64,
+}
+
+/// State of a tab that is not shown.
+#[derive(Debug, Clone, Default)]
+struct Tab {
+    id: u64,
+    /// Row of the listing shown at the top, kept while the tab is active.
+    scroll: usize,
+    current_path: PathBuf,
+    selection: BTreeSet<PathBuf>,
+    sort: SortOrder,
+    filter: Filter,
+    history: History,
+}
+
+/// Whether a paste should leave the clipboard entries in place or move them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClipboardMode {
+    Copy,
+    Cut,
+}
+
+#[derive(Debug, Clone)]
+pub struct Clipboard {
+    pub paths: Vec<PathBuf>,
+    pub mode: ClipboardMode,
+}
+
+impl FileManagerModel {
+    pub fn new(current_path: PathBuf) -> Self {
+        let mut model = Self {
+            current_path,
+            entries: Vec::new(),
+            selection: BTreeSet::new(),
+            clipboard: None,
+            journal: Journal::in_memory(),
+            sort: SortOrder::default(),
+            sort_preferences: SortPreferences::in_memory(),
+            filter: Filter::default(),
+            history: History::new(),
+            tabs: vec![Tab::default()],
+            active_tab: 0,
+            next_tab_id: 1,
+        };
+        model.refresh();
+        model
+    }
+
+    /// Records operations into `journal` instead of an in-memory one.
+    pub fn with_journal(mut self, journal: Journal) -> Self {
+        self.journal = journal;
+        self
+    }
+
+    /// Remembers sort orders in `preferences` instead of in memory, and
+    /// applies the one saved for the current directory.
+    pub fn with_sort_preferences(mut self, preferences: SortPreferences) -> Self {
+        self.sort_preferences = preferences;
+        self.sort = self.sort_preferences.get(&self.current_path);
+        self.refresh();
+        self
+    }
+
+    pub fn current_path(&self) -> &Path {
+        &self.current_path
+    }
+
+    pub fn entries(&self) -> &[DirEntryInfo] {
+        &self.entries
+    }
+
+    /// All selected entries.
+    pub fn selection(&self) -> &BTreeSet<PathBuf> {
+        &self.selection
+    }
+
+    /// The selected entry, if exactly one is selected.
+    pub fn selected(&self) -> Option<&PathBuf> {
+        match self.selection.len() {
+            1 => self.selection.first(),
+            _ => None,
+        }
+    }
+
+    pub fn clipboard(&self) -> Option<&Clipboard> {
+        self.clipboard.as_ref()
+    }
+
+    pub fn journal(&self) -> &Journal {
+        &self.journal
+    }
+
+    pub fn sort_order(&self) -> SortOrder {
+        self.sort
+    }
+
+    /// Re-sorts the listing by `order` and remembers it for the current
+    /// directory.
+    pub fn set_sort_order(&mut self, order: SortOrder) {
+        self.sort = order;
+        self.entries.sort_by(|a, b| order.compare(a, b));
+        self.sort_preferences.set(&self.current_path, order);
+    }
+
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+
+    /// Lists only the entries matching `filter`. Entries it hides are
+    /// deselected.
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+        self.refresh();
+        let entries = &self.entries;
+        self.selection
+            .retain(|path| entries.iter().any(|entry| &entry.path == path));
+    }
+
+    /// Re-reads the listing of the current directory.
+    pub fn refresh(&mut self) {
+        self.entries = read_entries(self.current_path.clone(), &self.sort);
+        let filter = &self.filter;
+        self.entries.retain(|entry| filter.matches(entry));
+    }
+
+    /// Applies changes reported by a watch on the current directory. Entries
+    /// keep their order and selection; a renamed entry stays selected under
+    /// its new name. If the directory is gone, moves to its nearest existing
+    /// ancestor.
+    pub fn apply_changes(&mut self, changes: &[DirChange]) {
+        for change in changes {
+            match change {
+                DirChange::Added(path) => self.update_entry(path.clone()),
+                DirChange::Removed(path) => {
+                    self.entries.retain(|entry| &entry.path != path);
+                    self.selection.remove(path);
+                }
+                DirChange::Renamed { from, to } => {
+                    self.entries.retain(|entry| &entry.path != from);
+                    if self.selection.remove(from) {
+                        self.selection.insert(to.clone());
+                    }
+                    self.update_entry(to.clone());
+                }
+                DirChange::Rescan => {
+                    self.refresh();
+                    let entries = &self.entries;
+                    self.selection
+                        .retain(|path| entries.iter().any(|entry| &entry.path == path));
+                }
+                DirChange::Gone => {
+                    let existing = self
+                        .current_path
+                        .ancestors()
+                        .find(|dir| dir.is_dir())
+                        .map(Path::to_path_buf)
+                        .unwrap_or_else(|| PathBuf::from("/"));
+                    self.navigate(existing);
+                }
+            }
+        }
+    }
+
+    /// Re-reads the metadata of `path`, adding it at its sorted position if
+    /// it is not listed yet and passes the filter.
+    fn update_entry(&mut self, path: PathBuf) {
+        if path.parent() != Some(self.current_path.as_path()) {
+            return;
+        }
+        self.entries.retain(|entry| entry.path != path);
+        let Ok(info) = DirEntryInfo::read(path) else {
+            return;
+        };
+        if !self.filter.matches(&info) {
+            self.selection.remove(&info.path);
+            return;
+        }
+        let index = self
+            .entries
+            .binary_search_by(|entry| self.sort.compare(entry, &info))
+            .unwrap_or_else(|index| index);
+        self.entries.insert(index, info);
+    }
+
+    /// Makes `path` the only selected entry.
+    pub fn select(&mut self, path: PathBuf) {
+        self.selection.clear();
+        self.selection.insert(path);
+    }
+
+    /// Adds `path` to the selection or removes it if already selected.
+    pub fn toggle_selection(&mut self, path: PathBuf) {
+        if !self.selection.remove(&path) {
+            self.selection.insert(path);
+        }
+    }
+
+    /// Selects every entry of the current directory.
+    pub fn select_all(&mut self) {
+        self.selection = self.entries.iter().map(|entry| entry.path.clone()).collect();
+    }
+
+    /// Selects exactly the entries of the current directory that are not
+    /// selected.
+    pub fn invert_selection(&mut self) {
+        self.selection = self
+            .entries
+            .iter()
+            .filter(|entry| !self.selection.contains(&entry.path))
+            .map(|entry| entry.path.clone())
+            .collect();
+    }
+
+    pub fn clear_selection(&mut self) {
+        self.selection.clear();
+    }
+
+    fn selected_one(&self) -> ModelResult<PathBuf> {
+        match self.selection.len() {
+            0 => Err(ModelError::NothingSelected),
+            1 => Ok(self.selection.first().cloned().unwrap_or_default()),
+            _ => Err(ModelError::MultipleSelected),
+        }
+    }
+
+    fn selected_all(&self) -> ModelResult<Vec<PathBuf>> {
+        if self.selection.is_empty() {
+            return Err(ModelError::NothingSelected);
+        }
+        Ok(self.selection.iter().cloned().collect())
+    }
+
+    /// The selected entries, unless removing them would remove the current
+    /// directory.
+    fn selected_removable(&self) -> ModelResult<Vec<PathBuf>> {
+        let selected = self.selected_all()?;
+        if selected
+            .iter()
+            .any(|path| self.current_path.starts_with(path))
+        {
+            return Err(ModelError::ContainsCurrentFolder);
+        }
+        Ok(selected)
+    }
+
+    /// Enters `path`, recording the current directory in the history.
+    pub fn navigate(&mut self, path: PathBuf) {
+        if path != self.current_path {
+            self.history.visit(self.current_path.clone());
+        }
+        self.enter(path);
+    }
+
+    /// Enters `path` with nothing selected and reloads the listing in the
+    /// order remembered for it.
+    fn enter(&mut self, path: PathBuf) {
+        self.tabs[self.active_tab].scroll = 0;
+        self.selection.clear();
+        self.sort = self.sort_preferences.get(&path);
+        self.current_path = path;
+        self.refresh();
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Returns to the directory visited before the current one.
+    pub fn go_back(&mut self) -> ModelResult<&Path> {
+        let path = self
+            .history
+            .back(&self.current_path)
+            .ok_or(ModelError::NoPreviousFolder)?;
+        self.enter(path);
+        Ok(&self.current_path)
+    }
+
+    /// Undoes the last [`go_back`](Self::go_back).
+    pub fn go_forward(&mut self) -> ModelResult<&Path> {
+        let path = self
+            .history
+            .forward(&self.current_path)
+            .ok_or(ModelError::NoNextFolder)?;
+        self.enter(path);
+        Ok(&self.current_path)
+    }
+
+    /// Moves to the parent of the current directory.
+    pub fn go_up(&mut self) -> ModelResult<&Path> {
+        let parent = self
+            .current_path
+            .parent()
+            .ok_or(ModelError::NoParent)?
+            .to_path_buf();
+        self.navigate(parent);
+        Ok(&self.current_path)
+    }
+
+    pub fn tab_count(&self) -> usize {
+        self.tabs.len()
+    }
+
+    pub fn active_tab(&self) -> usize {
+        self.active_tab
+    }
+
+    /// Identifier of the active tab, unique for the life of the model.
+    pub fn active_tab_id(&self) -> u64 {
+        self.tabs[self.active_tab].id
+    }
+
+    /// Row of the active tab's listing shown at the top. Entering a
+    /// directory starts at the top again.
+    pub fn scroll(&self) -> usize {
+        self.tabs[self.active_tab].scroll
+    }
+
+    pub fn set_scroll(&mut self, row: usize) {
+        self.tabs[self.active_tab].scroll = row;
+    }
+
+    /// The directory of each tab, in order.
+    pub fn tab_paths(&self) -> Vec<&Path> {
+        (0..self.tabs.len())
+            .map(|index| self.tab_path(index))
+            .collect()
+    }
+
+    fn tab_path(&self, index: usize) -> &Path {
+        if index == self.active_tab {
+            &self.current_path
+        } else {
+            &self.tabs[index].current_path
+        }
+    }
+
+    /// The listing of the tab at `index`, read again for tabs that are not
+    /// shown. Panics if there is no such tab.
+    pub fn tab_entries(&self, index: usize) -> Vec<DirEntryInfo> {
+        if index == self.active_tab {
+            return self.entries.clone();
+        }
+        let tab = &self.tabs[index];
+        let mut entries = read_entries(tab.current_path.clone(), &tab.sort);
+        entries.retain(|entry| tab.filter.matches(entry));
+        entries
+    }
+
+    /// Enters `path` in the tab at `index`, recording its directory in the
+    /// tab's history. Panics if there is no such tab.
+    pub fn navigate_tab(&mut self, index: usize, path: PathBuf) {
+        if index == self.active_tab {
+            return self.navigate(path);
+        }
+        let tab = &mut self.tabs[index];
+        if path != tab.current_path {
+            tab.history.visit(mem::take(&mut tab.current_path));
+        }
+        tab.sort = self.sort_preferences.get(&path);
+        tab.selection.clear();
+        tab.scroll = 0;
+        tab.current_path = path;
+    }
+
+    /// Opens `path` in a new tab after the active one and shows it. The new
+    /// tab keeps whether hidden files are shown, but not the filter rule.
+    pub fn open_tab(&mut self, path: PathBuf) -> ModelResult<()> {
+        if self.tabs.len() >= MAX_TABS {
+            return Err(ModelError::TooManyTabs);
+        }
+        let tab = Tab {
+            id: self.next_tab_id,
+            sort: self.sort_preferences.get(&path),
+            filter: Filter {
+                show_hidden: self.filter.show_hidden,
+                rule: None,
+            },
+            current_path: path,
+            ..Tab::default()
+        };
+        self.next_tab_id += 1;
+        self.stash_active();
+        self.active_tab += 1;
+        self.tabs.insert(self.active_tab, tab);
+        self.restore_active();
+        Ok(())
+    }
+
+    /// Shows the tab at `index`.
+    pub fn switch_tab(&mut self, index: usize) {
+        if index == self.active_tab || index >= self.tabs.len() {
+            return;
+        }
+        self.stash_active();
+        self.active_tab = index;
+        self.restore_active();
+    }
+
+    /// Closes the tab at `index`. Closing the active tab shows the next
+    /// one, or the previous one if it was the last.
+    pub fn close_tab(&mut self, index: usize) -> ModelResult<()> {
+        if self.tabs.len() == 1 {
+            return Err(ModelError::LastTab);
+        }
+        if index >= self.tabs.len() {
+            return Ok(());
+        }
+        self.stash_active();
+        self.tabs.remove(index);
+        if self.active_tab > index || self.active_tab == self.tabs.len() {
+            self.active_tab -= 1;
+        }
+        self.restore_active();
+        Ok(())
+    }
+
+    /// Moves the state of the active tab out of the model fields.
+    fn stash_active(&mut self) {
+        let tab = &mut self.tabs[self.active_tab];
+        tab.current_path = mem::take(&mut self.current_path);
+        tab.selection = mem::take(&mut self.selection);
+        tab.sort = self.sort;
+        tab.filter = mem::take(&mut self.filter);
+        tab.history = mem::take(&mut self.history);
+        self.entries.clear();
+    }
+
+    /// Moves the state of the active tab into the model fields and lists
+    /// its directory again.
+    fn restore_active(&mut self) {
+        let tab = &mut self.tabs[self.active_tab];
+        self.current_path = mem::take(&mut tab.current_path);
+        self.selection = mem::take(&mut tab.selection);
+        self.sort = tab.sort;
+        self.filter = mem::take(&mut tab.filter);
+        self.history = mem::take(&mut tab.history);
+        self.refresh();
+        let entries = &self.entries;
+        self.selection
+            .retain(|path| entries.iter().any(|entry| &entry.path == path));
+    }
+
+    /// Describes copying or moving the selected entries into the directory
+    /// of the tab at `index`, to be run like [`Self::paste_job`]. Panics if
+    /// there is no such tab.
+    pub fn transfer_job(&self, index: usize, mode: ClipboardMode) -> ModelResult<PasteJob> {
+        let paths = self.selected_all()?;
+        Ok(PasteJob::new(&paths, self.tab_path(index), mode))
+    }
+
+    /// Creates `name` inside the current directory and returns its path.
+    pub fn create_folder(&mut self, name: &str) -> ModelResult<PathBuf> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(ModelError::EmptyName);
+        }
+        let new_folder_path = self.current_path.join(name);
+        fs::create_dir(&new_folder_path)
+            .map_err(|e| FileOpError::new(FileOp::CreateFolder, &new_folder_path, e))?;
+        self.journal.record(Operation::CreateFolder {
+            path: new_folder_path.clone(),
+        });
+        self.refresh();
+        Ok(new_folder_path)
+    }
+
+    /// Renames the selected entry in place and returns its new path.
+    pub fn rename_selected(&mut self, new_name: &str) -> ModelResult<PathBuf> {
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            return Err(ModelError::EmptyName);
+        }
+        let selected = self.selected_one()?;
+        let new_path = selected.with_file_name(new_name);
+        if new_path.exists() {
+            return Err(ModelError::AlreadyExists(new_name.to_string()));
+        }
+        fs::rename(&selected, &new_path)
+            .map_err(|e| FileOpError::new(FileOp::Rename, &selected, e))?;
+        self.journal.record(Operation::Rename {
+            from: selected,
+            to: new_path.clone(),
+        });
+        self.select(new_path.clone());
+        self.refresh();
+        Ok(new_path)
+    }
+
+    /// Puts the selected entries on the clipboard for copying.
+    pub fn copy_selected(&mut self) -> ModelResult<&[PathBuf]> {
+        self.set_clipboard(ClipboardMode::Copy)
+    }
+
+    /// Puts the selected entries on the clipboard for moving.
+    pub fn cut_selected(&mut self) -> ModelResult<&[PathBuf]> {
+        self.set_clipboard(ClipboardMode::Cut)
+    }
+
+    fn set_clipboard(&mut self, mode: ClipboardMode) -> ModelResult<&[PathBuf]> {
+        let paths = self.selected_all()?;
+        Ok(&self.clipboard.insert(Clipboard { paths, mode }).paths)
+    }
+
+    /// Describes the copy or move a paste into the current directory would
+    /// perform. Callers resolve its conflicts, run it off the UI thread with
+    /// [`PasteJob::run`] and report back with [`Self::finish_paste`].
+    pub fn paste_job(&self) -> ModelResult<PasteJob> {
+        let clipboard = self.clipboard.as_ref().ok_or(ModelError::ClipboardEmpty)?;
+        Ok(PasteJob::new(
+            &clipboard.paths,
+            &self.current_path,
+            clipboard.mode,
+        ))
+    }
+
+    /// Updates the model after `job` has run: a successful paste is
+    /// journaled, and a successful move empties the clipboard, since its
+    /// entries no longer exist. A failed paste was already undone by
+    /// [`PasteJob::run`] and is not journaled. Returns whether the journal
+    /// got an entry, which is not the case when every item was skipped.
+    pub fn finish_paste(&mut self, job: &PasteJob, result: &ModelResult<Vec<PathBuf>>) -> bool {
+        let mut journaled = false;
+        if result.is_ok() {
+            // Entries replaced by Overwrite are gone for good; undoing only
+            // reverts the pasted entries.
+            let items: Vec<_> = job
+                .items
+                .iter()
+                .filter(|item| item.resolution != Some(ConflictResolution::Skip))
+                .filter(|item| item.source != item.dest)
+                .collect();
+            if !items.is_empty() {
+                self.journal.record(match job.mode {
+                    ClipboardMode::Copy => Operation::Copy {
+                        items: items.iter().map(|item| (item.dest.clone(), None)).collect(),
+                    },
+                    ClipboardMode::Cut => Operation::Move {
+                        moves: items
+                            .iter()
+                            .map(|item| (item.source.clone(), item.dest.clone()))
+                            .collect(),
+                    },
+                });
+                journaled = true;
+            }
+        }
+        let moved = result.is_ok() && job.mode == ClipboardMode::Cut;
+        if moved && self.clipboard.as_ref().map(|c| &c.paths) == Some(&job.sources) {
+            self.clipboard = None;
+        }
+        self.refresh();
+        journaled
+    }
+
+    /// Copies or moves the clipboard entries into the current directory and
+    /// returns the written paths. Fails with [`ModelError::AlreadyExists`]
+    /// instead of touching an existing entry; use [`Self::paste_job`] to
+    /// resolve conflicts first.
+    pub fn paste(&mut self) -> ModelResult<Vec<PathBuf>> {
+        let job = self.paste_job()?;
+        let res = job.run(&CancelToken::new(), |_| {});
+        self.finish_paste(&job, &res);
+        res
+    }
+
+    /// Moves the selected entries to the trash and returns their trash
+    /// entries. Stops at the first entry that cannot be trashed.
+    pub fn trash_selected(&mut self) -> ModelResult<Vec<TrashedItem>> {
+        let selected = self.selected_removable()?;
+        let mut trashed = Vec::new();
+        let mut result = Ok(());
+        for path in selected {
+            match trash::trash(&path) {
+                Ok(item) => {
+                    self.selection.remove(&path);
+                    trashed.push(item);
+                }
+                Err(e) => {
+                    result = Err(FileOpError::new(FileOp::Trash, &path, e));
+                    break;
+                }
+            }
+        }
+        if !trashed.is_empty() {
+            self.journal.record(Operation::Trash {
+                items: trashed.clone(),
+            });
+        }
+        self.refresh();
+        result?;
+        Ok(trashed)
+    }
+
+    /// Reverts the most recent journaled operation and returns it.
+    pub fn undo(&mut self) -> ModelResult<Operation> {
+        let result = self.journal.undo();
+        self.refresh();
+        result
+    }
+
+    /// Re-applies the most recently undone operation and returns it.
+    pub fn redo(&mut self) -> ModelResult<Operation> {
+        let result = self.journal.redo();
+        self.refresh();
+        result
+    }
+
+    /// Permanently removes the selected entries and returns their former
+    /// paths. Stops at the first entry that cannot be removed. This cannot be
+    /// undone.
+    pub fn delete_selected(&mut self) -> ModelResult<Vec<PathBuf>> {
+        let selected = self.selected_removable()?;
+        let mut deleted = Vec::new();
+        let mut result = Ok(());
+        for path in selected {
+            result = if path.is_dir() {
+                fs::remove_dir_all(&path)
+            } else {
+                fs::remove_file(&path)
+            }
+            .map_err(|e| FileOpError::new(FileOp::Delete, &path, e));
+            if result.is_err() {
+                break;
+            }
+            self.selection.remove(&path);
+            deleted.push(path);
+        }
+        self.refresh();
+        result?;
+        Ok(deleted)
+    }
+}
+
+/// Lists `path`, sorted by `order`.
+pub fn read_entries(path: PathBuf, order: &SortOrder) -> Vec<DirEntryInfo> {
+    let mut entries = Vec::new();
+
+    match fs::read_dir(&path) {
+        Ok(dir) => {
+            for entry in dir.flatten() {
+                // Entries removed while listing are skipped.
+                if let Ok(info) = DirEntryInfo::read(entry.path()) {
+                    entries.push(info);
+                }
+            }
+        }
+        Err(e) => tracing::warn!("Failed to read directory {:?}: {}", path, e),
+    }
+
+    entries.sort_by(|a, b| order.compare(a, b));
+
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    fn names(model: &FileManagerModel) -> Vec<&str> {
+        model
+            .entries()
+            .iter()
+            .map(|entry| entry.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn lists_folders_first_in_natural_order() {
+        let dir = TestDir::new();
+        dir.file("b10.txt", "");
+        dir.file("b2.txt", "");
+        dir.dir("zeta");
+        let model = FileManagerModel::new(dir.path().to_path_buf());
+        assert_eq!(names(&model), ["zeta", "b2.txt", "b10.txt"]);
+    }
+
+    #[test]
+    fn creates_and_renames_with_undo() {
+        let dir = TestDir::new();
+        let mut model = FileManagerModel::new(dir.path().to_path_buf());
+        assert!(matches!(
+            model.create_folder("  "),
+            Err(ModelError::EmptyName)
+        ));
+        let folder = model.create_folder(" docs ").unwrap();
+        assert_eq!(folder, dir.path().join("docs"));
+
+        assert!(matches!(
+            model.rename_selected("papers"),
+            Err(ModelError::NothingSelected)
+        ));
+        model.select(folder.clone());
+        let renamed = model.rename_selected("papers").unwrap();
+        assert!(renamed.is_dir() && !folder.exists());
+        assert_eq!(model.selected(), Some(&renamed));
+        assert_eq!(names(&model), ["papers"]);
+
+        model.undo().unwrap();
+        assert!(folder.is_dir() && !renamed.exists());
+        model.redo().unwrap();
+        assert!(renamed.is_dir() && !folder.exists());
+        assert!(matches!(model.redo(), Err(ModelError::NothingToRedo)));
+    }
+
+    #[test]
+    fn rename_keeps_an_existing_entry() {
+        let dir = TestDir::new();
+        let a = dir.file("a", "a");
+        dir.file("b", "b");
+        let mut model = FileManagerModel::new(dir.path().to_path_buf());
+        model.select(a.clone());
+        let result = model.rename_selected("b");
+        assert!(matches!(result, Err(ModelError::AlreadyExists(name)) if name == "b"));
+        assert_eq!(fs::read_to_string(dir.path().join("b")).unwrap(), "b");
+
+        model.select_all();
+        assert!(matches!(
+            model.rename_selected("c"),
+            Err(ModelError::MultipleSelected)
+        ));
+    }
+
+    #[test]
+    fn copies_through_the_clipboard() {
+        let dir = TestDir::new();
+        let source = dir.file("src/note.txt", "hello");
+        dir.file("src/folder/inner.txt", "inner");
+        let dest = dir.dir("dest");
+        let mut model = FileManagerModel::new(dir.path().join("src"));
+        assert!(matches!(model.paste(), Err(ModelError::ClipboardEmpty)));
+
+        model.select(source.clone());
+        model.copy_selected().unwrap();
+        model.navigate(dest.clone());
+        let pasted = model.paste().unwrap();
+        assert_eq!(pasted, [dest.join("note.txt")]);
+        assert_eq!(fs::read_to_string(&pasted[0]).unwrap(), "hello");
+        assert!(source.exists());
+
+        model.select(dir.path().join("src/folder"));
+        model.copy_selected().unwrap();
+        model.paste().unwrap();
+        assert!(dest.join("folder/inner.txt").exists());
+        assert_eq!(names(&model), ["folder", "note.txt"]);
+    }
+
+    #[test]
+    fn a_cut_empties_the_clipboard_once_moved() {
+        let dir = TestDir::new();
+        let source = dir.file("src/note.txt", "hello");
+        let dest = dir.dir("dest");
+        let mut model = FileManagerModel::new(dir.path().join("src"));
+        model.select(source.clone());
+        model.cut_selected().unwrap();
+        assert_eq!(model.clipboard().unwrap().mode, ClipboardMode::Cut);
+
+        model.navigate(dest.clone());
+        assert_eq!(model.paste().unwrap(), [dest.join("note.txt")]);
+        assert!(!source.exists());
+        assert_eq!(fs::read_to_string(dest.join("note.txt")).unwrap(), "hello");
+        assert!(model.clipboard().is_none());
+        assert_eq!(names(&model), ["note.txt"]);
+
+        model.undo().unwrap();
+        assert!(source.exists() && !dest.join("note.txt").exists());
+    }
+
+    #[test]
+    fn a_failed_move_keeps_the_clipboard() {
+        let dir = TestDir::new();
+        let source = dir.file("note.txt", "hello");
+        let dest = dir.dir("dest");
+        let mut model = FileManagerModel::new(dir.path().to_path_buf());
+        model.select(source.clone());
+        model.cut_selected().unwrap();
+        fs::remove_file(&source).unwrap();
+
+        model.navigate(dest);
+        assert!(model.paste().is_err());
+        assert_eq!(model.clipboard().unwrap().paths, [source]);
+
+        let copied = dir.file("copied.txt", "");
+        model.select(copied.clone());
+        model.copy_selected().unwrap();
+        model.paste().unwrap();
+        assert_eq!(model.clipboard().unwrap().paths, [copied]);
+    }
+
+    #[test]
+    fn pastes_through_a_job() {
+        let dir = TestDir::new();
+        let source = dir.file("note.txt", "hello");
+        let dest = dir.dir("dest");
+        let mut model = FileManagerModel::new(dir.path().to_path_buf());
+        assert!(matches!(model.paste_job(), Err(ModelError::ClipboardEmpty)));
+        model.select(source);
+        model.copy_selected().unwrap();
+        model.navigate(dest.clone());
+
+        let job = model.paste_job().unwrap();
+        let mut copied = 0;
+        let pasted = job
+            .run(&CancelToken::new(), |progress| {
+                copied = progress.copied_bytes
+            })
+            .unwrap();
+        assert_eq!(pasted, [dest.join("note.txt")]);
+        assert_eq!(copied, 5);
+    }
+
+    #[test]
+    fn deletes_the_selection_but_not_the_current_folder() {
+        let dir = TestDir::new();
+        let inner = dir.dir("a/b");
+        dir.file("a/b/c.txt", "");
+        dir.file("a/b/d/e.txt", "");
+        let mut model = FileManagerModel::new(inner.clone());
+
+        for path in [dir.path().join("a"), inner.clone()] {
+            model.select(path);
+            assert!(matches!(
+                model.delete_selected(),
+                Err(ModelError::ContainsCurrentFolder)
+            ));
+        }
+        assert!(inner.join("c.txt").exists());
+
+        model.select_all();
+        assert_eq!(model.delete_selected().unwrap().len(), 2);
+        assert!(model.entries().is_empty());
+        assert!(model.selection().is_empty());
+    }
+
+    #[test]
+    fn selection_is_cleared_when_entering_a_folder() {
+        let dir = TestDir::new();
+        let sub = dir.dir("sub");
+        dir.file("a.txt", "");
+        let mut model = FileManagerModel::new(dir.path().to_path_buf());
+        model.toggle_selection(sub.clone());
+        model.invert_selection();
+        assert_eq!(model.selected(), Some(&dir.path().join("a.txt")));
+        model.navigate(sub);
+        assert!(model.selection().is_empty());
+    }
+
+    #[test]
+    fn selects_all_inverts_and_deletes_in_bulk() {
+        let dir = TestDir::new();
+        let a = dir.file("a.txt", "");
+        let b = dir.file("b.txt", "");
+        let c = dir.dir("c");
+        dir.file("c/inner.txt", "");
+        let mut model = FileManagerModel::new(dir.path().to_path_buf());
+
+        model.select_all();
+        assert_eq!(model.selection().len(), 3);
+        assert!(model.selected().is_none());
+
+        model.toggle_selection(a.clone());
+        model.invert_selection();
+        assert_eq!(model.selected(), Some(&a));
+        model.toggle_selection(b.clone());
+        model.toggle_selection(c.clone());
+        model.invert_selection();
+        assert!(model.selection().is_empty());
+
+        model.invert_selection();
+        let mut deleted = model.delete_selected().unwrap();
+        deleted.sort();
+        assert_eq!(deleted, [a, b, c]);
+        assert!(model.entries().is_empty() && model.selection().is_empty());
+    }
+
+    #[test]
+    fn goes_back_forward_and_up() {
+        let dir = TestDir::new();
+        let root = dir.path().to_path_buf();
+        let sub = dir.dir("sub");
+        let mut model = FileManagerModel::new(root.clone());
+        assert!(matches!(model.go_back(), Err(ModelError::NoPreviousFolder)));
+
+        model.navigate(sub.clone());
+        assert_eq!(model.go_up().unwrap(), root);
+        assert_eq!(model.go_back().unwrap(), sub);
+        assert_eq!(model.go_back().unwrap(), root);
+        assert_eq!(model.go_forward().unwrap(), sub);
+        assert!(model.history().can_go_forward());
+    }
+
+    #[test]
+    fn tabs_keep_their_folder_selection_and_scroll() {
+        let dir = TestDir::new();
+        let root = dir.path().to_path_buf();
+        let sub = dir.dir("sub");
+        let file = dir.file("a.txt", "");
+        let mut model = FileManagerModel::new(root.clone());
+        model.select(file.clone());
+        model.set_scroll(3);
+
+        model.open_tab(sub.clone()).unwrap();
+        assert_eq!(model.active_tab(), 1);
+        assert_eq!(model.current_path(), sub);
+        assert_eq!(model.scroll(), 0);
+        assert!(model.selection().is_empty());
+        assert_eq!(model.tab_paths(), [root.as_path(), sub.as_path()]);
+
+        model.switch_tab(0);
+        assert_eq!(model.current_path(), root);
+        assert_eq!(model.selected(), Some(&file));
+        assert_eq!(model.scroll(), 3);
+        model.navigate(sub.clone());
+        assert_eq!(model.scroll(), 0);
+
+        for _ in 2..MAX_TABS {
+            model.open_tab(sub.clone()).unwrap();
+        }
+        assert!(matches!(
+            model.open_tab(sub.clone()),
+            Err(ModelError::TooManyTabs)
+        ));
+        while model.tab_count() > 1 {
+            model.close_tab(0).unwrap();
+        }
+        assert!(matches!(model.close_tab(0), Err(ModelError::LastTab)));
+    }
+
+    #[test]
+    fn copies_and_moves_between_tabs() {
+        let dir = TestDir::new();
+        let a = dir.file("src/a.txt", "a");
+        let b = dir.file("src/b.txt", "b");
+        let dest = dir.dir("dest");
+        let mut model = FileManagerModel::new(dir.path().join("src"));
+        model.open_tab(dest.clone()).unwrap();
+        model.switch_tab(0);
+        assert!(matches!(
+            model.transfer_job(1, ClipboardMode::Copy),
+            Err(ModelError::NothingSelected)
+        ));
+
+        model.select(a.clone());
+        model.copy_selected().unwrap();
+        let job = model.transfer_job(1, ClipboardMode::Copy).unwrap();
+        let result = job.run(&CancelToken::new(), |_| {});
+        model.finish_paste(&job, &result);
+        assert_eq!(result.unwrap(), [dest.join("a.txt")]);
+        assert!(a.exists());
+
+        model.select(b.clone());
+        let job = model.transfer_job(1, ClipboardMode::Cut).unwrap();
+        let result = job.run(&CancelToken::new(), |_| {});
+        model.finish_paste(&job, &result);
+        assert_eq!(result.unwrap(), [dest.join("b.txt")]);
+        assert!(!b.exists());
+        assert_eq!(names(&model), ["a.txt"]);
+        // The clipboard holds other entries and is kept.
+        assert_eq!(model.clipboard().unwrap().paths, [a]);
+
+        model.undo().unwrap();
+        assert!(b.exists() && !dest.join("b.txt").exists());
+    }
+
+    #[test]
+    fn applies_watched_changes() {
+        let dir = TestDir::new();
+        let a = dir.file("a.txt", "");
+        let mut model = FileManagerModel::new(dir.path().to_path_buf());
+        model.select(a.clone());
+
+        let b = dir.path().join("b.txt");
+        fs::rename(&a, &b).unwrap();
+        let c = dir.file("c.txt", "");
+        model.apply_changes(&[
+            DirChange::Renamed {
+                from: a,
+                to: b.clone(),
+            },
+            DirChange::Added(c),
+        ]);
+        assert_eq!(names(&model), ["b.txt", "c.txt"]);
+        assert_eq!(model.selected(), Some(&b));
+
+        fs::remove_file(&b).unwrap();
+        model.apply_changes(&[DirChange::Removed(b)]);
+        assert_eq!(names(&model), ["c.txt"]);
+        assert!(model.selection().is_empty());
+    }
+
+    #[test]
+    fn the_other_pane_browses_without_being_shown() {
+        let dir = TestDir::new();
+        let left = dir.dir("left");
+        let right = dir.dir("right");
+        dir.file("right/b.txt", "");
+        dir.file("right/.hidden", "");
+        dir.dir("right/a");
+        let mut model = FileManagerModel::new(left.clone());
+        model.open_tab(left.clone()).unwrap();
+        model.switch_tab(0);
+
+        model.navigate_tab(1, right.clone());
+        assert_eq!(model.current_path(), left);
+        assert_eq!(model.tab_paths(), [left.as_path(), right.as_path()]);
+        let names: Vec<_> = model.tab_entries(1).into_iter().map(|e| e.name).collect();
+        assert_eq!(names, ["a", "b.txt"]);
+
+        model.switch_tab(1);
+        assert_eq!(model.go_back().unwrap(), left);
+    }
+}
@@ -0,0 +1,611 @@
+use crate::error::{FileOp, FileOpError};
+use crate::model::{ClipboardMode, ModelError, ModelResult};
+use crate::transfer::{self, CancelToken, TransferProgress};
+use std::fs;
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+/// How to handle a paste whose destination already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictResolution {
+    /// Replace the existing entry.
+    Overwrite,
+    /// Leave the existing entry alone and do not paste this item.
+    Skip,
+    /// Paste under a free name such as `name (copy 2).ext`.
+    KeepBoth,
+    /// Paste the folder's contents into the existing folder, asking again
+    /// for every entry that exists on both sides.
+    Merge,
+}
+
+/// One entry of a paste.
+#[derive(Debug, Clone)]
+pub struct PasteItem {
+    pub source: PathBuf,
+    pub dest: PathBuf,
+    pub resolution: Option<ConflictResolution>,
+}
+
+impl PasteItem {
+    fn new(source: PathBuf, dest: PathBuf) -> Self {
+        Self {
+            source,
+            dest,
+            resolution: None,
+        }
+    }
+
+    /// Whether `dest` is taken and this item has no resolution yet.
+    pub fn is_conflict(&self, mode: ClipboardMode) -> bool {
+        if self.resolution.is_some() || fs::symlink_metadata(&self.dest).is_err() {
+            return false;
+        }
+        // Moving an entry onto itself is a no-op, not a conflict.
+        !(mode == ClipboardMode::Cut && self.source == self.dest)
+    }
+
+    /// Resolutions that are safe for this item. Overwrite and Merge are not
+    /// offered when source and destination overlap, as that would destroy
+    /// the source.
+    pub fn allowed_resolutions(&self) -> Vec<ConflictResolution> {
+        use ConflictResolution::*;
+
+        if self.source.starts_with(&self.dest) || self.dest.starts_with(&self.source) {
+            return vec![KeepBoth, Skip];
+        }
+        if self.source.is_dir() && self.dest.is_dir() {
+            vec![Merge, Overwrite, KeepBoth, Skip]
+        } else {
+            vec![Overwrite, KeepBoth, Skip]
+        }
+    }
+}
+
+/// A pending paste: copy or move every item to its destination once all
+/// conflicts have been resolved.
+#[derive(Debug, Clone)]
+pub struct PasteJob {
+    /// Entries that were on the clipboard.
+    pub sources: Vec<PathBuf>,
+    pub items: Vec<PasteItem>,
+    pub mode: ClipboardMode,
+    /// Resolution chosen with "apply to all", used for later conflicts.
+    apply_to_all: Option<ConflictResolution>,
+    /// Source folders whose contents were merged item by item.
+    merged: Vec<PathBuf>,
+}
+
+impl PasteJob {
+    /// Pastes each of `sources` into `dest_dir` under its own name.
+    pub fn new(sources: &[PathBuf], dest_dir: &Path, mode: ClipboardMode) -> Self {
+        let items = sources
+            .iter()
+            .map(|source| {
+                let dest = dest_dir.join(source.file_name().unwrap_or_default());
+                PasteItem::new(source.clone(), dest)
+            })
+            .collect();
+        let mut job = Self {
+            sources: sources.to_vec(),
+            items,
+            mode,
+            apply_to_all: None,
+            merged: Vec::new(),
+        };
+        // Nothing is merged yet, so this cannot fail.
+        let _ = job.auto_resolve();
+        job
+    }
+
+    /// The first item still waiting for a [`ConflictResolution`].
+    pub fn next_conflict(&self) -> Option<&PasteItem> {
+        self.items.iter().find(|item| item.is_conflict(self.mode))
+    }
+
+    pub fn conflicts_remaining(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|item| item.is_conflict(self.mode))
+            .count()
+    }
+
+    /// Applies `resolution` to the current conflict and, with
+    /// `apply_to_all`, to every later conflict that allows it.
+    pub fn resolve(
+        &mut self,
+        resolution: ConflictResolution,
+        apply_to_all: bool,
+    ) -> ModelResult<()> {
+        if apply_to_all {
+            self.apply_to_all = Some(resolution);
+        }
+        let mode = self.mode;
+        if let Some(index) = self.items.iter().position(|item| item.is_conflict(mode)) {
+            self.set_resolution(index, resolution)?;
+        }
+        self.auto_resolve()
+    }
+
+    fn auto_resolve(&mut self) -> ModelResult<()> {
+        let Some(resolution) = self.apply_to_all else {
+            return Ok(());
+        };
+        let mut index = 0;
+        while index < self.items.len() {
+            let item = &self.items[index];
+            if item.is_conflict(self.mode) && item.allowed_resolutions().contains(&resolution) {
+                self.set_resolution(index, resolution)?;
+                if resolution == ConflictResolution::Merge {
+                    // The folder was replaced by its children, check those next.
+                    continue;
+                }
+            }
+            index += 1;
+        }
+        Ok(())
+    }
+
+    fn set_resolution(&mut self, index: usize, resolution: ConflictResolution) -> ModelResult<()> {
+        match resolution {
+            ConflictResolution::Merge => {
+                // Replace the folder by its children so that each of them is
+                // checked for conflicts on its own.
+                let item = self.items.remove(index);
+                let reading = |e| FileOpError::new(FileOp::Read, &item.source, e);
+                let mut children = Vec::new();
+                for entry in fs::read_dir(&item.source).map_err(reading)? {
+                    let entry = entry.map_err(reading)?;
+                    children.push(PasteItem::new(
+                        entry.path(),
+                        item.dest.join(entry.file_name()),
+                    ));
+                }
+                self.items.splice(index..index, children);
+                self.merged.push(item.source);
+            }
+            ConflictResolution::KeepBoth => {
+                let item = &mut self.items[index];
+                item.dest = keep_both_path(&item.dest);
+                item.resolution = Some(resolution);
+            }
+            _ => self.items[index].resolution = Some(resolution),
+        }
+        Ok(())
+    }
+
+    /// Performs the transfer on the calling thread, reporting progress after
+    /// every chunk, and returns the paths that were written. When cancelled
+    /// or on error, the items already pasted are undone, see
+    /// [`Self::roll_back`], so a failed paste leaves nothing behind.
+    pub fn run(
+        &self,
+        cancel: &CancelToken,
+        mut on_progress: impl FnMut(&TransferProgress),
+    ) -> ModelResult<Vec<PathBuf>> {
+        if let Some(item) = self.next_conflict() {
+            let name = item.dest.file_name().unwrap_or_default();
+            return Err(ModelError::AlreadyExists(
+                name.to_string_lossy().to_string(),
+            ));
+        }
+
+        let items: Vec<&PasteItem> = self
+            .items
+            .iter()
+            .filter(|item| item.resolution != Some(ConflictResolution::Skip))
+            .collect();
+
+        let op = match self.mode {
+            ClipboardMode::Copy => FileOp::Copy,
+            ClipboardMode::Cut => FileOp::Move,
+        };
+        let mut progress = TransferProgress::default();
+        let mut needed = 0;
+        let mut sizes = Vec::with_capacity(items.len());
+        for item in &items {
+            let size = transfer::total_size(&item.source)
+                .map_err(|e| FileOpError::new(op, &item.source, e))?;
+            progress.total_bytes += size;
+            if self.writes_data(item) {
+                needed += size;
+            }
+            sizes.push(size);
+        }
+        self.check_space(op, &items, needed)?;
+
+        let mut done: Vec<Pasted> = Vec::new();
+        for (item, size) in items.into_iter().zip(sizes) {
+            // An entry being overwritten stays in place until the new one has
+            // been written next to it.
+            let overwrite = item.resolution == Some(ConflictResolution::Overwrite);
+            let mut pasted = Pasted {
+                item,
+                written: if overwrite {
+                    free_sibling(&item.dest, "partial")
+                } else {
+                    item.dest.clone()
+                },
+                replaced: None,
+            };
+            let result = match self.mode {
+                ClipboardMode::Copy => transfer::copy(
+                    &item.source,
+                    &pasted.written,
+                    cancel,
+                    &mut progress,
+                    &mut on_progress,
+                ),
+                ClipboardMode::Cut => transfer::move_path(
+                    &item.source,
+                    &pasted.written,
+                    Some(size),
+                    cancel,
+                    &mut progress,
+                    &mut on_progress,
+                ),
+            };
+            if let Err(e) = result {
+                self.roll_back(&done);
+                return Err(e.into());
+            }
+            if overwrite {
+                if let Err(e) = pasted.replace() {
+                    done.push(pasted);
+                    self.roll_back(&done);
+                    return Err(FileOpError::new(op, &item.dest, e).into());
+                }
+            }
+            done.push(pasted);
+        }
+
+        for pasted in &done {
+            if let Some(replaced) = &pasted.replaced {
+                if let Err(e) = transfer::remove_path(replaced) {
+                    tracing::warn!("Cannot remove the replaced {:?}: {}", replaced, e);
+                }
+            }
+        }
+        if self.mode == ClipboardMode::Cut {
+            // Merged folders are left behind once their contents moved out;
+            // folders that still hold skipped entries stay in place.
+            for dir in self.merged.iter().rev() {
+                let _ = fs::remove_dir(dir);
+            }
+        }
+        Ok(done.into_iter().map(|pasted| pasted.written).collect())
+    }
+
+    /// Undoes the items pasted before a cancel or an error, last first:
+    /// copies are removed, moved entries are moved back and overwritten
+    /// entries are put back in place. Failures are logged and the rest is
+    /// undone.
+    fn roll_back(&self, done: &[Pasted]) {
+        for pasted in done.iter().rev() {
+            let result = match self.mode {
+                ClipboardMode::Copy => {
+                    transfer::remove_path(&pasted.written).map_err(|e| e.to_string())
+                }
+                ClipboardMode::Cut => transfer::move_path(
+                    &pasted.written,
+                    &pasted.item.source,
+                    None,
+                    &CancelToken::new(),
+                    &mut TransferProgress::default(),
+                    &mut |_| {},
+                )
+                .map_err(|e| e.to_string()),
+            };
+            if let Err(e) = result {
+                tracing::warn!("Cannot undo the paste of {:?}: {}", pasted.written, e);
+                continue;
+            }
+            if let Some(replaced) = &pasted.replaced {
+                if let Err(e) = fs::rename(replaced, &pasted.item.dest) {
+                    tracing::warn!("Cannot restore {:?}: {}", pasted.item.dest, e);
+                }
+            }
+        }
+    }
+
+    /// Whether pasting `item` writes its data anew, rather than renaming it
+    /// within one filesystem.
+    fn writes_data(&self, item: &PasteItem) -> bool {
+        if self.mode == ClipboardMode::Copy {
+            return true;
+        }
+        let dev = |path: &Path| {
+            path.ancestors()
+                .find_map(|p| fs::metadata(p).ok())
+                .map(|meta| meta.dev())
+        };
+        dev(&item.source) != dev(&item.dest)
+    }
+
+    /// Fails before anything is written if the destination filesystem cannot
+    /// hold `needed` more bytes. Entries that get overwritten are only
+    /// removed once the paste has completed, so they free no space before.
+    fn check_space(&self, op: FileOp, items: &[&PasteItem], needed: u64) -> ModelResult<()> {
+        let Some(dest_dir) = items.first().and_then(|item| item.dest.parent()) else {
+            return Ok(());
+        };
+        match transfer::available_space(dest_dir) {
+            Ok(available) if available < needed => {
+                Err(FileOpError::no_space(op, dest_dir, needed, available).into())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// An item written by [`PasteJob::run`].
+struct Pasted<'a> {
+    item: &'a PasteItem,
+    /// Where the item was written; a temporary sibling of `item.dest` until
+    /// an overwritten entry has been replaced.
+    written: PathBuf,
+    /// The overwritten entry, set aside until the paste has completed.
+    replaced: Option<PathBuf>,
+}
+
+impl Pasted<'_> {
+    /// Sets the entry at `item.dest` aside and renames the written item over
+    /// it. On failure the entry is put back.
+    fn replace(&mut self) -> io::Result<()> {
+        let dest = &self.item.dest;
+        let aside = free_sibling(dest, "replaced");
+        fs::rename(dest, &aside)?;
+        if let Err(e) = fs::rename(&self.written, dest) {
+            let _ = fs::rename(&aside, dest);
+            return Err(e);
+        }
+        self.written = dest.clone();
+        self.replaced = Some(aside);
+        Ok(())
+    }
+}
+
+/// First free hidden sibling of `path` named `.name.tag`, `.name.tag-2`, and
+/// so on.
+fn free_sibling(path: &Path, tag: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let mut n = 1;
+    loop {
+        let candidate = if n == 1 {
+            path.with_file_name(format!(".{}.{}", name, tag))
+        } else {
+            path.with_file_name(format!(".{}.{}-{}", name, tag, n))
+        };
+        if fs::symlink_metadata(&candidate).is_err() {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+/// First free sibling of `path` named `name (copy).ext`, `name (copy 2).ext`,
+/// and so on.
+pub fn keep_both_path(path: &Path) -> PathBuf {
+    let is_dir = path.is_dir();
+    let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+    let (stem, ext) = match path.extension() {
+        Some(ext) if !is_dir => (
+            path.file_stem().unwrap_or_default().to_string_lossy(),
+            format!(".{}", ext.to_string_lossy()),
+        ),
+        _ => (file_name, String::new()),
+    };
+
+    let mut n = 1;
+    loop {
+        let suffix = if n == 1 {
+            " (copy)".to_string()
+        } else {
+            format!(" (copy {})", n)
+        };
+        let candidate = path.with_file_name(format!("{}{}{}", stem, suffix, ext));
+        if fs::symlink_metadata(&candidate).is_err() {
+            return candidate;
+        }
+        n += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn cancelling_removes_the_copies_made() {
+        let dir = TestDir::new();
+        let a = dir.file("src/a.txt", "a");
+        let b = dir.file("src/b.txt", "b");
+        let dest = dir.dir("dest");
+        let job = PasteJob::new(&[a.clone(), b.clone()], &dest, ClipboardMode::Copy);
+
+        let cancel = CancelToken::new();
+        let result = job.run(&cancel, |progress| {
+            if progress.current_file == b {
+                cancel.cancel();
+            }
+        });
+        assert!(matches!(result, Err(ModelError::Cancelled)));
+        assert_eq!(fs::read_dir(&dest).unwrap().count(), 0);
+        assert!(a.exists() && b.exists());
+    }
+
+    #[test]
+    fn an_error_undoes_the_items_already_pasted() {
+        for mode in [ClipboardMode::Copy, ClipboardMode::Cut] {
+            let dir = TestDir::new();
+            let a = dir.file("src/a.txt", "a");
+            let b = dir.file("src/b.txt", "b");
+            let dest = dir.dir("dest");
+            let job = PasteJob::new(&[a.clone(), b.clone()], &dest, mode);
+
+            // The second entry disappears while the first one is pasted.
+            let result = job.run(&CancelToken::new(), |_| {
+                let _ = fs::remove_file(&b);
+            });
+            assert!(matches!(result, Err(ModelError::FileOp(_))));
+            assert_eq!(fs::read_dir(&dest).unwrap().count(), 0);
+            assert_eq!(fs::read_to_string(&a).unwrap(), "a");
+        }
+    }
+
+    #[test]
+    fn keep_both_picks_a_free_name() {
+        let dir = TestDir::new();
+        let file = dir.file("notes.tar.gz", "");
+        assert_eq!(
+            keep_both_path(&file),
+            dir.path().join("notes.tar (copy).gz")
+        );
+        dir.file("notes.tar (copy).gz", "");
+        assert_eq!(
+            keep_both_path(&file),
+            dir.path().join("notes.tar (copy 2).gz")
+        );
+
+        // Folders keep their dots.
+        let folder = dir.dir("v1.2");
+        assert_eq!(keep_both_path(&folder), dir.path().join("v1.2 (copy)"));
+    }
+
+    #[test]
+    fn resolves_conflicts_one_by_one() {
+        let dir = TestDir::new();
+        let a = dir.file("src/a.txt", "new a");
+        let b = dir.file("src/b.txt", "new b");
+        let c = dir.file("src/c.txt", "new c");
+        let dest = dir.dir("dest");
+        dir.file("dest/a.txt", "old a");
+        dir.file("dest/b.txt", "old b");
+
+        let mut job = PasteJob::new(&[a, b, c], &dest, ClipboardMode::Copy);
+        assert_eq!(job.conflicts_remaining(), 2);
+        assert_eq!(job.next_conflict().unwrap().dest, dest.join("a.txt"));
+        assert!(matches!(
+            job.run(&CancelToken::new(), |_| {}),
+            Err(ModelError::AlreadyExists(_))
+        ));
+
+        job.resolve(ConflictResolution::Overwrite, false).unwrap();
+        job.resolve(ConflictResolution::Skip, false).unwrap();
+        assert!(job.next_conflict().is_none());
+        let written = job.run(&CancelToken::new(), |_| {}).unwrap();
+        assert_eq!(written, [dest.join("a.txt"), dest.join("c.txt")]);
+        let read = |name| fs::read_to_string(dest.join(name)).unwrap();
+        assert_eq!(read("a.txt"), "new a");
+        assert_eq!(read("b.txt"), "old b");
+        assert_eq!(read("c.txt"), "new c");
+    }
+
+    #[test]
+    fn overwrites_a_folder_with_a_file() {
+        let dir = TestDir::new();
+        let source = dir.file("src/x", "file");
+        let dest = dir.dir("dest");
+        dir.file("dest/x/inner.txt", "");
+
+        let mut job = PasteJob::new(&[source], &dest, ClipboardMode::Copy);
+        job.resolve(ConflictResolution::Overwrite, false).unwrap();
+        job.run(&CancelToken::new(), |_| {}).unwrap();
+        assert_eq!(fs::read_to_string(dest.join("x")).unwrap(), "file");
+        // Neither the copy in progress nor the replaced folder is left over.
+        assert_eq!(fs::read_dir(&dest).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn a_failed_paste_keeps_the_overwritten_entry() {
+        for mode in [ClipboardMode::Copy, ClipboardMode::Cut] {
+            let dir = TestDir::new();
+            let a = dir.file("src/a.txt", "new");
+            let b = dir.file("src/b.txt", "b");
+            let dest = dir.dir("dest");
+            dir.file("dest/a.txt", "old");
+
+            let mut job = PasteJob::new(&[a.clone(), b.clone()], &dest, mode);
+            job.resolve(ConflictResolution::Overwrite, false).unwrap();
+            let result = job.run(&CancelToken::new(), |_| {
+                let _ = fs::remove_file(&b);
+            });
+            assert!(result.is_err());
+            assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), "old");
+            assert_eq!(fs::read_dir(&dest).unwrap().count(), 1);
+            assert_eq!(fs::read_to_string(&a).unwrap(), "new");
+        }
+    }
+
+    #[test]
+    fn applies_a_resolution_to_all_conflicts() {
+        let dir = TestDir::new();
+        let a = dir.file("src/a.txt", "a");
+        let b = dir.file("src/b.txt", "b");
+        let dest = dir.dir("dest");
+        dir.file("dest/a.txt", "");
+        dir.file("dest/b.txt", "");
+
+        let mut job = PasteJob::new(&[a, b], &dest, ClipboardMode::Copy);
+        job.resolve(ConflictResolution::KeepBoth, true).unwrap();
+        assert_eq!(job.conflicts_remaining(), 0);
+        let written = job.run(&CancelToken::new(), |_| {}).unwrap();
+        assert_eq!(
+            written,
+            [dest.join("a (copy).txt"), dest.join("b (copy).txt")]
+        );
+    }
+
+    #[test]
+    fn merges_folders_entry_by_entry() {
+        let dir = TestDir::new();
+        let source = dir.dir("src/photos");
+        dir.file("src/photos/new.jpg", "new");
+        dir.file("src/photos/same.jpg", "theirs");
+        let dest = dir.dir("dest");
+        dir.file("dest/photos/same.jpg", "ours");
+        dir.file("dest/photos/old.jpg", "old");
+
+        let mut job = PasteJob::new(std::slice::from_ref(&source), &dest, ClipboardMode::Cut);
+        let conflict = job.next_conflict().unwrap();
+        assert!(conflict
+            .allowed_resolutions()
+            .contains(&ConflictResolution::Merge));
+        job.resolve(ConflictResolution::Merge, false).unwrap();
+        assert_eq!(
+            job.next_conflict().unwrap().dest,
+            dest.join("photos/same.jpg")
+        );
+        job.resolve(ConflictResolution::Skip, false).unwrap();
+        job.run(&CancelToken::new(), |_| {}).unwrap();
+
+        let merged = dest.join("photos");
+        assert_eq!(fs::read_to_string(merged.join("new.jpg")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(merged.join("same.jpg")).unwrap(), "ours");
+        assert!(merged.join("old.jpg").exists());
+        // The skipped entry keeps its folder in place.
+        assert!(source.join("same.jpg").exists());
+    }
+
+    #[test]
+    fn overlapping_paths_only_allow_keep_both_or_skip() {
+        let dir = TestDir::new();
+        let folder = dir.dir("folder");
+        let job = PasteJob::new(
+            std::slice::from_ref(&folder),
+            dir.path(),
+            ClipboardMode::Copy,
+        );
+        let item = job.next_conflict().unwrap();
+        assert_eq!(
+            item.allowed_resolutions(),
+            [ConflictResolution::KeepBoth, ConflictResolution::Skip]
+        );
+
+        // Moving an entry onto itself is no conflict.
+        let job = PasteJob::new(&[folder], dir.path(), ClipboardMode::Cut);
+        assert!(job.next_conflict().is_none());
+    }
+}
@@ -0,0 +1,414 @@
+//! Rendering of PDF pages with Pdfium, the PDF library of Chromium. The
+//! library is bundled into the executable by `build.rs` and extracted to the
+//! cache directory on first use. A copy placed next to the executable is
+//! preferred, and the one installed on the system is the last resort. Pages
+//! are rendered to PNG files for the viewer, by a worker thread that keeps
+//! the document loaded while it is shown.
+
+use crate::xdg;
+use image::ImageFormat;
+use pdfium_render::prelude::{
+    PdfDocument, PdfRenderConfig, Pdfium, PdfiumError, PdfiumInternalError,
+};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{mpsc, OnceLock};
+use std::thread;
+
+/// Rendered pages kept on disk for a document; see [`PdfPages::finish`].
+const MAX_RENDERED_PAGES: usize = 12;
+
+/// The Pdfium library for the target, see `build.rs`.
+static BUNDLED_PDFIUM: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/libpdfium.so"));
+
+/// A page rendered to a PNG file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedPage {
+    pub index: usize,
+    /// Number of pages in the document.
+    pub page_count: usize,
+    pub width: u32,
+    pub height: u32,
+    pub image: PathBuf,
+}
+
+#[derive(Debug)]
+pub enum PdfError {
+    /// The Pdfium library could not be loaded.
+    NoRenderer,
+    PasswordProtected,
+    /// Not a PDF document, or a damaged one.
+    Unreadable,
+    NoSuchPage(usize),
+    Io(io::Error),
+}
+
+impl fmt::Display for PdfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PdfError::NoRenderer => write!(f, "PDF viewing needs the Pdfium library."),
+            PdfError::PasswordProtected => write!(f, "The document is password protected."),
+            PdfError::Unreadable => write!(f, "The document is damaged or not a PDF."),
+            PdfError::NoSuchPage(index) => write!(f, "The document has no page {}.", index + 1),
+            PdfError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for PdfError {}
+
+impl From<io::Error> for PdfError {
+    fn from(e: io::Error) -> Self {
+        PdfError::Io(e)
+    }
+}
+
+impl From<PdfiumError> for PdfError {
+    fn from(e: PdfiumError) -> Self {
+        match e {
+            PdfiumError::PdfiumLibraryInternalError(PdfiumInternalError::PasswordError) => {
+                PdfError::PasswordProtected
+            }
+            PdfiumError::PdfiumLibraryInternalError(PdfiumInternalError::FileError) => {
+                PdfError::Io(io::Error::new(io::ErrorKind::NotFound, "File not found."))
+            }
+            _ => PdfError::Unreadable,
+        }
+    }
+}
+
+/// The Pdfium library, loaded on first use.
+fn pdfium() -> Result<&'static Pdfium, PdfError> {
+    static PDFIUM: OnceLock<Option<Pdfium>> = OnceLock::new();
+    PDFIUM
+        .get_or_init(|| {
+            let beside_exe = env::current_exe()
+                .ok()
+                .and_then(|exe| {
+                    exe.parent()
+                        .map(Pdfium::pdfium_platform_library_name_at_path)
+                })
+                .ok_or(PdfiumError::UnrecognizedPath);
+            match beside_exe
+                .and_then(Pdfium::bind_to_library)
+                .or_else(|_| match extract_bundled() {
+                    Ok(path) => Pdfium::bind_to_library(path),
+                    Err(e) => {
+                        tracing::warn!("Failed to extract the bundled Pdfium: {}", e);
+                        Err(PdfiumError::UnrecognizedPath)
+                    }
+                })
+                .or_else(|_| Pdfium::bind_to_system_library())
+            {
+                Ok(bindings) => Some(Pdfium::new(bindings)),
+                Err(e) => {
+                    tracing::warn!("Failed to load Pdfium: {}", e);
+                    None
+                }
+            }
+        })
+        .as_ref()
+        .ok_or(PdfError::NoRenderer)
+}
+
+/// Writes [`BUNDLED_PDFIUM`] to the cache directory, unless an earlier run
+/// did, and returns its path. The file name depends on the contents so that
+/// another build never loads a stale copy.
+fn extract_bundled() -> io::Result<PathBuf> {
+    let mut hasher = DefaultHasher::new();
+    BUNDLED_PDFIUM.hash(&mut hasher);
+    let dir = xdg::app_cache_dir().join("pdfium");
+    let path = dir.join(format!("libpdfium-{:016x}.so", hasher.finish()));
+    if fs::metadata(&path).is_ok_and(|meta| meta.len() == BUNDLED_PDFIUM.len() as u64) {
+        return Ok(path);
+    }
+    fs::create_dir_all(&dir)?;
+    // Written under a name of its own, as other instances may be loading
+    // the library at the same time.
+    let partial = dir.join(format!("libpdfium-{}.partial", process::id()));
+    fs::write(&partial, BUNDLED_PDFIUM)?;
+    fs::rename(&partial, &path)?;
+    Ok(path)
+}
+
+/// Renders page `index` of `document` `width` pixels wide to a PNG file in
+/// `dir`. The document is loaded for this page only; [`PdfPages`] keeps it
+/// loaded to render several pages.
+pub fn render_page(
+    document: &Path,
+    index: usize,
+    width: u32,
+    dir: &Path,
+) -> Result<RenderedPage, PdfError> {
+    render_loaded(&load(document)?, index, width, dir)
+}
+
+fn load(document: &Path) -> Result<PdfDocument<'static>, PdfError> {
+    Ok(pdfium()?.load_pdf_from_file(document, None)?)
+}
+
+fn render_loaded(
+    pdf: &PdfDocument,
+    index: usize,
+    width: u32,
+    dir: &Path,
+) -> Result<RenderedPage, PdfError> {
+    let pages = pdf.pages();
+    let page_count = pages.len() as usize;
+    if index >= page_count {
+        return Err(PdfError::NoSuchPage(index));
+    }
+    let config = PdfRenderConfig::new().set_target_width(width as i32);
+    let page = pages.get(index as u16)?;
+    let bitmap = page.render_with_config(&config)?;
+    let image = bitmap.as_image();
+
+    fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}-{}.png", index, width));
+    image
+        .save_with_format(&path, ImageFormat::Png)
+        .map_err(io::Error::other)?;
+    Ok(RenderedPage {
+        index,
+        page_count,
+        width: image.width(),
+        height: image.height(),
+        image: path,
+    })
+}
+
+/// The pages of a document rendered while it is shown, so that going back
+/// to a nearby page shows it at once. The files are removed on drop.
+#[derive(Debug)]
+pub struct PdfPages {
+    document: PathBuf,
+    /// Directory of this instance's files, under the cache directory.
+    dir: PathBuf,
+    page_count: Option<usize>,
+    /// Rendered pages by index and requested width.
+    rendered: HashMap<(usize, u32), RenderedPage>,
+    pending: HashSet<(usize, u32)>,
+    /// Pages to render on the worker thread, if one was spawned.
+    worker: Option<mpsc::Sender<(usize, u32)>>,
+}
+
+impl PdfPages {
+    pub fn new(document: PathBuf) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let dir = xdg::app_cache_dir()
+            .join("pdf")
+            .join(format!("{}-{}", process::id(), id));
+        Self {
+            document,
+            dir,
+            page_count: None,
+            rendered: HashMap::new(),
+            pending: HashSet::new(),
+            worker: None,
+        }
+    }
+
+    /// Starts the thread that renders the pages passed to
+    /// [`PdfPages::render`]. It loads the document on the first page and
+    /// keeps it loaded until `self` is dropped; a document that fails to
+    /// load is tried again on the next page. Each outcome goes to
+    /// `on_rendered`, to be handed back to [`PdfPages::finish`].
+    pub fn spawn_worker(
+        &mut self,
+        on_rendered: impl FnMut(usize, u32, Result<RenderedPage, PdfError>) + Send + 'static,
+    ) -> io::Result<()> {
+        self.spawn_worker_with(load, on_rendered)
+    }
+
+    /// [`PdfPages::spawn_worker`], loading the document with `load`.
+    fn spawn_worker_with(
+        &mut self,
+        load: fn(&Path) -> Result<PdfDocument<'static>, PdfError>,
+        mut on_rendered: impl FnMut(usize, u32, Result<RenderedPage, PdfError>) + Send + 'static,
+    ) -> io::Result<()> {
+        let (sender, requests) = mpsc::channel::<(usize, u32)>();
+        let document = self.document.clone();
+        let dir = self.dir.clone();
+        thread::Builder::new()
+            .name("pdf-render".into())
+            .spawn(move || {
+                let mut pdf = None;
+                // Ends once the sender is dropped with `self`.
+                for (index, width) in requests {
+                    if pdf.is_none() {
+                        match load(&document) {
+                            Ok(loaded) => pdf = Some(loaded),
+                            Err(e) => {
+                                on_rendered(index, width, Err(e));
+                                continue;
+                            }
+                        }
+                    }
+                    if let Some(pdf) = &pdf {
+                        on_rendered(index, width, render_loaded(pdf, index, width, &dir));
+                    }
+                }
+            })?;
+        self.worker = Some(sender);
+        Ok(())
+    }
+
+    /// Queues a page marked with [`PdfPages::start`] on the worker. False if
+    /// there is no worker, in which case the caller renders it.
+    pub fn render(&self, index: usize, width: u32) -> bool {
+        self.worker
+            .as_ref()
+            .is_some_and(|worker| worker.send((index, width)).is_ok())
+    }
+
+    pub fn document(&self) -> &Path {
+        &self.document
+    }
+
+    /// Where the pages are to be rendered.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Known once a page was rendered.
+    pub fn page_count(&self) -> Option<usize> {
+        self.page_count
+    }
+
+    pub fn get(&self, index: usize, width: u32) -> Option<&RenderedPage> {
+        self.rendered.get(&(index, width))
+    }
+
+    /// Marks the page as being rendered. False if it is rendered already,
+    /// being rendered or past the end of the document.
+    pub fn start(&mut self, index: usize, width: u32) -> bool {
+        if self.page_count.is_some_and(|count| index >= count)
+            || self.rendered.contains_key(&(index, width))
+        {
+            return false;
+        }
+        self.pending.insert((index, width))
+    }
+
+    /// Takes the outcome of rendering a page marked with [`PdfPages::start`].
+    /// Past [`MAX_RENDERED_PAGES`], the pages at another zoom and then the
+    /// ones farthest from this page are removed.
+    pub fn finish(&mut self, index: usize, width: u32, result: &Result<RenderedPage, PdfError>) {
+        self.pending.remove(&(index, width));
+        if let Ok(page) = result {
+            self.page_count = Some(page.page_count);
+            self.rendered.insert((index, width), page.clone());
+        }
+        while self.rendered.len() > MAX_RENDERED_PAGES {
+            let farthest = self
+                .rendered
+                .keys()
+                .copied()
+                .max_by_key(|&(other, other_width)| (other_width != width, other.abs_diff(index)));
+            let Some(key) = farthest else {
+                break;
+            };
+            if let Some(page) = self.rendered.remove(&key) {
+                let _ = fs::remove_file(&page.image);
+            }
+        }
+    }
+}
+
+impl Drop for PdfPages {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    fn rendered(dir: &TestDir, index: usize, width: u32) -> Result<RenderedPage, PdfError> {
+        Ok(RenderedPage {
+            index,
+            page_count: 40,
+            width,
+            height: width,
+            image: dir.file(&format!("{}-{}.png", index, width), ""),
+        })
+    }
+
+    #[test]
+    fn renders_each_page_once() {
+        let dir = TestDir::new();
+        let mut pages = PdfPages::new(dir.path().join("a.pdf"));
+        assert!(pages.start(0, 100));
+        assert!(!pages.start(0, 100));
+        pages.finish(0, 100, &Err(PdfError::Unreadable));
+        assert!(pages.start(0, 100));
+        pages.finish(0, 100, &rendered(&dir, 0, 100));
+        assert!(!pages.start(0, 100));
+        assert!(pages.start(0, 200));
+        assert_eq!(pages.page_count(), Some(40));
+        assert!(!pages.start(40, 100));
+        assert_eq!(pages.get(0, 100).unwrap().height, 100);
+    }
+
+    #[test]
+    fn evicts_other_zooms_then_far_pages() {
+        let dir = TestDir::new();
+        let mut pages = PdfPages::new(dir.path().join("a.pdf"));
+        let old_zoom = rendered(&dir, 5, 50);
+        pages.finish(5, 50, &old_zoom);
+        for index in 0..MAX_RENDERED_PAGES {
+            pages.finish(index, 100, &rendered(&dir, index, 100));
+        }
+        assert!(pages.get(5, 50).is_none());
+        assert!(!old_zoom.unwrap().image.exists());
+
+        pages.finish(
+            MAX_RENDERED_PAGES,
+            100,
+            &rendered(&dir, MAX_RENDERED_PAGES, 100),
+        );
+        assert!(pages.get(0, 100).is_none());
+        assert!(pages.get(1, 100).is_some());
+        assert_eq!(pages.rendered.len(), MAX_RENDERED_PAGES);
+    }
+
+    #[test]
+    fn renders_on_the_worker() {
+        let dir = TestDir::new();
+        let mut pages = PdfPages::new(dir.path().join("a.pdf"));
+        assert!(!pages.render(0, 100));
+
+        let (sender, results) = mpsc::channel();
+        // Pdfium itself is not loaded, which would extract it to the cache.
+        pages
+            .spawn_worker_with(
+                |_| Err(PdfError::Unreadable),
+                move |index, width, result| {
+                    let _ = sender.send((index, width, result));
+                },
+            )
+            .unwrap();
+        for width in [100, 200] {
+            assert!(pages.start(0, width));
+            assert!(pages.render(0, width));
+        }
+        for width in [100, 200] {
+            let (index, rendered_width, result) = results.recv().unwrap();
+            assert_eq!((index, rendered_width), (0, width));
+            assert!(matches!(result, Err(PdfError::Unreadable)));
+            pages.finish(index, width, &result);
+        }
+        assert!(pages.start(0, 100));
+    }
+}
@@ -0,0 +1,531 @@
+//! Images for the image viewer: decoding, turned upright from their EXIF
+//! orientation, and the part of an image shown in the viewer once it is
+//! zoomed, panned, rotated or flipped.
+
+use crate::sort::natural_cmp;
+use crate::xdg;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageDecoder, ImageFormat, ImageReader, RgbaImage};
+use resvg::{tiny_skia, usvg};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Extensions of the formats the viewer decodes.
+const EXTENSIONS: [&str; 9] = [
+    "png", "jpg", "jpeg", "gif", "webp", "bmp", "tif", "tiff", "svg",
+];
+/// Longest side images are reduced to after decoding, to bound memory.
+const MAX_DIMENSION: u32 = 4096;
+/// Longest side SVG images are drawn at.
+const SVG_DIMENSION: f32 = 2048.;
+/// Most an image can be zoomed past fitting the viewport.
+pub const MAX_ZOOM: f32 = 8.;
+
+/// Whether the viewer can show `path`, from its extension.
+pub fn is_image(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .is_some_and(|ext| EXTENSIONS.contains(&ext.as_str()))
+}
+
+/// The images in the folder of `path`, by name.
+pub fn folder_images(path: &Path) -> io::Result<Vec<PathBuf>> {
+    let Some(dir) = path.parent() else {
+        return Ok(Vec::new());
+    };
+    let mut images: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_image(path) && path.is_file())
+        .collect();
+    images.sort_by(|a, b| {
+        let name = |path: &Path| {
+            path.file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string()
+        };
+        natural_cmp(&name(a), &name(b))
+    });
+    Ok(images)
+}
+
+#[derive(Debug)]
+pub enum PictureError {
+    Io(io::Error),
+    /// Not an image, or one in a format that cannot be decoded.
+    Unsupported,
+    /// An image with damaged or invalid contents.
+    Invalid(String),
+}
+
+impl fmt::Display for PictureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PictureError::Io(e) => write!(f, "{}", e),
+            PictureError::Unsupported => write!(f, "This image format is not supported."),
+            PictureError::Invalid(e) => write!(f, "The image could not be read: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PictureError {}
+
+impl From<io::Error> for PictureError {
+    fn from(e: io::Error) -> Self {
+        PictureError::Io(e)
+    }
+}
+
+impl From<image::ImageError> for PictureError {
+    fn from(e: image::ImageError) -> Self {
+        match e {
+            image::ImageError::IoError(e) => PictureError::Io(e),
+            image::ImageError::Unsupported(_) => PictureError::Unsupported,
+            e => PictureError::Invalid(e.to_string().trim_end().to_string()),
+        }
+    }
+}
+
+/// Decodes the image at `path` the right way up. Animated images give
+/// their first frame.
+pub fn decode(path: &Path) -> Result<DynamicImage, PictureError> {
+    let is_svg = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"));
+    let image = if is_svg {
+        decode_svg(path)?
+    } else {
+        let mut decoder = ImageReader::open(path)?
+            .with_guessed_format()?
+            .into_decoder()?;
+        let orientation = decoder.orientation()?;
+        let mut image = DynamicImage::from_decoder(decoder)?;
+        image.apply_orientation(orientation);
+        image
+    };
+    if image.width() > MAX_DIMENSION || image.height() > MAX_DIMENSION {
+        return Ok(image.resize(MAX_DIMENSION, MAX_DIMENSION, FilterType::Triangle));
+    }
+    Ok(image)
+}
+
+/// Draws an SVG image with its longest side `SVG_DIMENSION` pixels long.
+fn decode_svg(path: &Path) -> Result<DynamicImage, PictureError> {
+    let data = fs::read(path)?;
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    let tree =
+        usvg::Tree::from_data(&data, &options).map_err(|e| PictureError::Invalid(e.to_string()))?;
+    let size = tree.size();
+    let scale = SVG_DIMENSION / size.width().max(size.height());
+    let width = (size.width() * scale).ceil() as u32;
+    let height = (size.height() * scale).ceil() as u32;
+    let mut pixmap = tiny_skia::Pixmap::new(width, height)
+        .ok_or_else(|| PictureError::Invalid("empty image".to_string()))?;
+    resvg::render(
+        &tree,
+        tiny_skia::Transform::from_scale(scale, scale),
+        &mut pixmap.as_mut(),
+    );
+    // The pixmap holds premultiplied colors.
+    let pixels = pixmap
+        .pixels()
+        .iter()
+        .flat_map(|pixel| {
+            let color = pixel.demultiply();
+            [color.red(), color.green(), color.blue(), color.alpha()]
+        })
+        .collect();
+    let image = RgbaImage::from_raw(width, height, pixels)
+        .ok_or_else(|| PictureError::Invalid("bad image size".to_string()))?;
+    Ok(DynamicImage::ImageRgba8(image))
+}
+
+/// Rotations and flips applied to an image: it is mirrored left to right
+/// first if `mirrored`, then turned clockwise by `quarter_turns`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Transform {
+    quarter_turns: u8,
+    mirrored: bool,
+}
+
+impl Transform {
+    pub fn rotate_clockwise(self) -> Self {
+        Self {
+            quarter_turns: (self.quarter_turns + 1) % 4,
+            ..self
+        }
+    }
+
+    pub fn rotate_counter_clockwise(self) -> Self {
+        Self {
+            quarter_turns: (self.quarter_turns + 3) % 4,
+            ..self
+        }
+    }
+
+    /// Mirrors the image as shown left to right. Mirroring first reverses
+    /// the direction of the turns made after it.
+    pub fn flip_horizontal(self) -> Self {
+        Self {
+            quarter_turns: (4 - self.quarter_turns) % 4,
+            mirrored: !self.mirrored,
+        }
+    }
+
+    /// Mirrors the image as shown top to bottom: a horizontal flip and a
+    /// half turn.
+    pub fn flip_vertical(self) -> Self {
+        let flipped = self.flip_horizontal();
+        Self {
+            quarter_turns: (flipped.quarter_turns + 2) % 4,
+            ..flipped
+        }
+    }
+
+    fn apply(self, image: &DynamicImage) -> DynamicImage {
+        let image = if self.mirrored {
+            image.fliph()
+        } else {
+            image.clone()
+        };
+        match self.quarter_turns {
+            1 => image.rotate90(),
+            2 => image.rotate180(),
+            3 => image.rotate270(),
+            _ => image,
+        }
+    }
+}
+
+/// A rendering of the visible part of an image, saved as a PNG file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub image: PathBuf,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// An image shown in a viewport of a fixed size. At zoom 1 it fits the
+/// viewport, or is shown at its own size if smaller; zooming in keeps the
+/// point under the finger in place. The frames are removed on drop.
+#[derive(Debug)]
+pub struct ImageView {
+    source: Arc<DynamicImage>,
+    /// `source` after `transform`, shared with frames being rendered.
+    shown: Arc<DynamicImage>,
+    transform: Transform,
+    zoom: f32,
+    /// Point of `shown` at the middle of the viewport.
+    center: (f32, f32),
+    viewport: (u32, u32),
+    /// Directory of this instance's frames, under the cache directory.
+    dir: PathBuf,
+    frames: u64,
+    frame: Option<Frame>,
+}
+
+impl ImageView {
+    pub fn new(source: Arc<DynamicImage>, viewport: (u32, u32)) -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let dir = xdg::app_cache_dir()
+            .join("images")
+            .join(format!("{}-{}", process::id(), id));
+        let shown = source.clone();
+        let center = (shown.width() as f32 / 2., shown.height() as f32 / 2.);
+        Self {
+            source,
+            shown,
+            transform: Transform::default(),
+            zoom: 1.,
+            center,
+            viewport,
+            dir,
+            frames: 0,
+            frame: None,
+        }
+    }
+
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    /// Rotates or flips the image, fitting it to the viewport again.
+    pub fn set_transform(&mut self, transform: Transform) {
+        self.transform = transform;
+        self.shown = Arc::new(transform.apply(&self.source));
+        self.zoom = 1.;
+        self.center = (
+            self.shown.width() as f32 / 2.,
+            self.shown.height() as f32 / 2.,
+        );
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Zooms to `zoom`, keeping the image point at `point` of the viewport
+    /// where it is.
+    pub fn zoom_at(&mut self, zoom: f32, point: (f32, f32)) {
+        let zoom = zoom.clamp(1., MAX_ZOOM);
+        let (dx, dy) = (
+            point.0 - self.viewport.0 as f32 / 2.,
+            point.1 - self.viewport.1 as f32 / 2.,
+        );
+        let before = self.scale();
+        let target = (self.center.0 + dx / before, self.center.1 + dy / before);
+        self.zoom = zoom;
+        let after = self.scale();
+        self.center = (target.0 - dx / after, target.1 - dy / after);
+        self.clamp_center();
+    }
+
+    /// Moves the image by `dx`, `dy` viewport pixels.
+    pub fn pan(&mut self, dx: f32, dy: f32) {
+        let scale = self.scale();
+        self.center = (self.center.0 - dx / scale, self.center.1 - dy / scale);
+        self.clamp_center();
+    }
+
+    /// Directory of this view's frames, which tells its frames apart from
+    /// those of another view.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Renders the visible part into a new frame on the calling thread,
+    /// removing the last one.
+    pub fn render(&mut self) -> io::Result<Frame> {
+        let frame = self.frame_job().render()?;
+        self.set_frame(frame.clone());
+        Ok(frame)
+    }
+
+    /// Describes the frame of the visible part, to be rendered on another
+    /// thread and then passed to [`Self::set_frame`].
+    pub fn frame_job(&mut self) -> FrameJob {
+        let scale = self.scale();
+        let (width, height) = self.frame_size();
+        let (region_width, region_height) = (width as f32 / scale, height as f32 / scale);
+        self.frames += 1;
+        FrameJob {
+            image: self.shown.clone(),
+            x: (self.center.0 - region_width / 2.).max(0.) as u32,
+            y: (self.center.1 - region_height / 2.).max(0.) as u32,
+            region: (
+                (region_width.round() as u32).max(1),
+                (region_height.round() as u32).max(1),
+            ),
+            size: (width, height),
+            shrink: scale < 1.,
+            dir: self.dir.clone(),
+            path: self.dir.join(format!("{}.png", self.frames)),
+        }
+    }
+
+    /// Makes `frame` the one shown, removing the last one.
+    pub fn set_frame(&mut self, frame: Frame) {
+        if let Some(last) = self.frame.replace(frame) {
+            let _ = fs::remove_file(last.image);
+        }
+    }
+
+    /// Size of the image once zoomed, cut to the viewport.
+    fn frame_size(&self) -> (u32, u32) {
+        let scale = self.scale();
+        let width = (self.shown.width() as f32 * scale).round() as u32;
+        let height = (self.shown.height() as f32 * scale).round() as u32;
+        (
+            width.clamp(1, self.viewport.0),
+            height.clamp(1, self.viewport.1),
+        )
+    }
+
+    /// Viewport pixels per image pixel.
+    fn scale(&self) -> f32 {
+        let fit = (self.viewport.0 as f32 / self.shown.width().max(1) as f32)
+            .min(self.viewport.1 as f32 / self.shown.height().max(1) as f32)
+            .min(1.);
+        fit * self.zoom
+    }
+
+    /// Keeps the viewport within the image.
+    fn clamp_center(&mut self) {
+        let scale = self.scale();
+        let (width, height) = self.frame_size();
+        let half = (width as f32 / scale / 2., height as f32 / scale / 2.);
+        let max = (
+            self.shown.width() as f32 - half.0,
+            self.shown.height() as f32 - half.1,
+        );
+        self.center = (
+            self.center.0.clamp(half.0, max.0.max(half.0)),
+            self.center.1.clamp(half.1, max.1.max(half.1)),
+        );
+    }
+}
+
+/// The crop and resize of a frame, without access to its [`ImageView`].
+#[derive(Debug)]
+pub struct FrameJob {
+    image: Arc<DynamicImage>,
+    x: u32,
+    y: u32,
+    /// Size of the part of `image` shown.
+    region: (u32, u32),
+    /// Size of the frame.
+    size: (u32, u32),
+    /// Whether the image is reduced rather than enlarged.
+    shrink: bool,
+    dir: PathBuf,
+    path: PathBuf,
+}
+
+impl FrameJob {
+    pub fn render(self) -> io::Result<Frame> {
+        let region = self
+            .image
+            .crop_imm(self.x, self.y, self.region.0, self.region.1);
+        let (width, height) = self.size;
+        let pixels = if self.shrink {
+            region.thumbnail_exact(width, height)
+        } else {
+            region.resize_exact(width, height, FilterType::Triangle)
+        };
+
+        fs::create_dir_all(&self.dir)?;
+        pixels
+            .save_with_format(&self.path, ImageFormat::Png)
+            .map_err(io::Error::other)?;
+        Ok(Frame {
+            image: self.path,
+            width,
+            height,
+        })
+    }
+}
+
+impl Drop for ImageView {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use image::Rgba;
+
+    /// A `width` by `height` image whose top-left pixel is red.
+    fn image(width: u32, height: u32) -> DynamicImage {
+        let mut image = RgbaImage::new(width, height);
+        image.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+        DynamicImage::ImageRgba8(image)
+    }
+
+    /// Corner of the transformed 3 by 2 image holding the red pixel.
+    fn red_corner(transform: Transform) -> (u32, u32) {
+        let shown = transform.apply(&image(3, 2)).to_rgba8();
+        let (x, y, _) = shown
+            .enumerate_pixels()
+            .find(|(_, _, pixel)| pixel[0] == 255)
+            .unwrap();
+        (x * 2 / (shown.width() - 1), y * 2 / (shown.height() - 1))
+    }
+
+    #[test]
+    fn transforms_compose() {
+        let none = Transform::default();
+        let cw = none.rotate_clockwise();
+        assert_eq!(cw.rotate_counter_clockwise(), none);
+        assert_eq!(
+            cw.rotate_clockwise().rotate_clockwise().rotate_clockwise(),
+            none
+        );
+        assert_eq!(cw.flip_horizontal().flip_horizontal(), cw);
+        assert_eq!(none.flip_vertical().flip_vertical(), none);
+        assert_eq!(
+            none.flip_horizontal().flip_vertical(),
+            cw.rotate_clockwise()
+        );
+
+        assert_eq!(red_corner(none), (0, 0));
+        assert_eq!(red_corner(cw), (2, 0));
+        assert_eq!(red_corner(none.rotate_counter_clockwise()), (0, 2));
+        assert_eq!(red_corner(none.flip_horizontal()), (2, 0));
+        assert_eq!(red_corner(none.flip_vertical()), (0, 2));
+        assert_eq!(red_corner(cw.flip_horizontal()), (0, 0));
+    }
+
+    #[test]
+    fn finds_and_decodes_images() {
+        let dir = TestDir::new();
+        let png = dir.path().join("img10.PNG");
+        image(3, 2)
+            .save_with_format(&png, ImageFormat::Png)
+            .unwrap();
+        let second = dir.file("img9.jpg", "not really");
+        dir.file("notes.txt", "");
+        dir.dir("folder.png");
+        assert_eq!(folder_images(&png).unwrap(), [second.clone(), png.clone()]);
+
+        let decoded = decode(&png).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (3, 2));
+        assert!(matches!(decode(&second), Err(PictureError::Invalid(_))));
+        assert!(matches!(
+            decode(&dir.path().join("missing.png")),
+            Err(PictureError::Io(_))
+        ));
+
+        let svg = dir.file(
+            "a.svg",
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10"/>"#,
+        );
+        let decoded = decode(&svg).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (2048, 1024));
+    }
+
+    #[test]
+    fn zooms_and_pans_within_the_image() {
+        let mut view = ImageView::new(Arc::new(image(400, 200)), (100, 100));
+        assert_eq!(view.frame_size(), (100, 50));
+        view.zoom_at(100., (0., 50.));
+        assert_eq!(view.zoom(), MAX_ZOOM);
+        assert_eq!(view.frame_size(), (100, 100));
+        assert_eq!(view.center, (25., 100.));
+
+        view.pan(1000., 1000.);
+        assert_eq!(view.center, (25., 25.));
+        view.pan(-10_000., 0.);
+        assert_eq!(view.center, (375., 25.));
+
+        view.set_transform(view.transform().rotate_clockwise());
+        assert_eq!(view.zoom(), 1.);
+        assert_eq!(view.frame_size(), (50, 100));
+    }
+
+    #[test]
+    fn renders_frames() {
+        let dir = TestDir::new();
+        let mut view = ImageView::new(Arc::new(image(40, 20)), (100, 100));
+        view.dir = dir.path().join("frames");
+        let first = view.render().unwrap();
+        assert_eq!((first.width, first.height), (40, 20));
+
+        view.zoom_at(2., (50., 50.));
+        let second = view.frame_job().render().unwrap();
+        assert_eq!((second.width, second.height), (80, 40));
+        assert!(first.image.exists());
+        view.set_frame(second.clone());
+        assert!(!first.image.exists());
+        assert_eq!(decode(&second.image).unwrap().width(), 80);
+    }
+}
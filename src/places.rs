@@ -0,0 +1,312 @@
+//! Folders offered for quick navigation: the XDG user directories and the
+//! bookmarks shared with GTK applications.
+
+use crate::trash::{decode_path, encode_path};
+use crate::xdg;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// A named folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Place {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+/// Keys of `user-dirs.dirs` in display order, with their labels. The label
+/// is also the folder in home used when the file does not name one.
+const USER_DIRS: [(&str, &str); 8] = [
+    ("DESKTOP", "Desktop"),
+    ("DOCUMENTS", "Documents"),
+    ("DOWNLOAD", "Downloads"),
+    ("MUSIC", "Music"),
+    ("PICTURES", "Pictures"),
+    ("VIDEOS", "Videos"),
+    ("TEMPLATES", "Templates"),
+    ("PUBLICSHARE", "Public"),
+];
+
+/// Home followed by the user directories of
+/// `$XDG_CONFIG_HOME/user-dirs.dirs` that exist. Directories set to the home
+/// itself are disabled and left out.
+pub fn user_dirs() -> Vec<Place> {
+    let home = xdg::home_dir();
+    let contents = fs::read_to_string(xdg::config_home().join("user-dirs.dirs")).ok();
+    let configured = contents
+        .as_deref()
+        .map(|contents| parse_user_dirs(contents, &home))
+        .unwrap_or_default();
+
+    let mut places = vec![Place {
+        name: "Home".to_string(),
+        path: home.clone(),
+    }];
+    for (key, label) in USER_DIRS {
+        let path = match configured.iter().find(|(k, _)| k == key) {
+            Some((_, path)) => path.clone(),
+            None => home.join(label),
+        };
+        if path != home && path.is_dir() {
+            places.push(Place {
+                name: label.to_string(),
+                path,
+            });
+        }
+    }
+    places
+}
+
+/// Reads the `XDG_<KEY>_DIR="$HOME/..."` lines of `user-dirs.dirs`.
+fn parse_user_dirs(contents: &str, home: &Path) -> Vec<(String, PathBuf)> {
+    contents
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            if line.starts_with('#') {
+                return None;
+            }
+            let (key, value) = line.split_once('=')?;
+            let key = key.trim().strip_prefix("XDG_")?.strip_suffix("_DIR")?;
+            let value = unquote(value.trim())?;
+            let path = match value.strip_prefix("$HOME") {
+                Some(rest) => home.join(rest.trim_start_matches('/')),
+                None if value.starts_with('/') => PathBuf::from(value),
+                None => return None,
+            };
+            Some((key.to_string(), path))
+        })
+        .collect()
+}
+
+/// Strips the double quotes around a shell value and its backslash escapes.
+fn unquote(value: &str) -> Option<String> {
+    let inner = value.strip_prefix('"')?.strip_suffix('"')?;
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    Some(out)
+}
+
+/// The `file://` URI of a local path.
+pub(crate) fn file_uri(path: &Path) -> String {
+    format!("file://{}", encode_path(path))
+}
+
+/// The local path of a `file://` URI.
+pub(crate) fn uri_path(uri: &str) -> Option<PathBuf> {
+    let rest = uri.strip_prefix("file://")?;
+    // Skip the host, usually empty or `localhost`.
+    let start = rest.find('/')?;
+    Some(decode_path(&rest[start..]))
+}
+
+/// A line of the GTK bookmarks file: a URI and an optional label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    pub uri: String,
+    pub label: Option<String>,
+}
+
+impl Bookmark {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            uri: file_uri(path),
+            label: None,
+        }
+    }
+
+    /// The local folder of a `file://` bookmark.
+    pub fn path(&self) -> Option<PathBuf> {
+        uri_path(&self.uri)
+    }
+
+    /// The label, or the folder name for bookmarks without one.
+    pub fn name(&self) -> String {
+        if let Some(label) = &self.label {
+            return label.clone();
+        }
+        match self.path() {
+            Some(path) => path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string()),
+            None => self.uri.clone(),
+        }
+    }
+}
+
+/// The user's bookmarks, in the format of `~/.config/gtk-3.0/bookmarks` so
+/// that they are shared with other applications. Bookmarks of remote
+/// locations are kept as they are.
+#[derive(Debug, Clone, Default)]
+pub struct Bookmarks {
+    /// File the bookmarks are saved to; `None` keeps them in memory only.
+    path: Option<PathBuf>,
+    entries: Vec<Bookmark>,
+}
+
+impl Bookmarks {
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Reads the bookmarks stored at `path`; a missing file has none.
+    pub fn load(path: PathBuf) -> Self {
+        let contents = fs::read_to_string(&path).unwrap_or_default();
+        let entries = contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| match line.split_once(' ') {
+                Some((uri, label)) => Bookmark {
+                    uri: uri.to_string(),
+                    label: Some(label.to_string()),
+                },
+                None => Bookmark {
+                    uri: line.to_string(),
+                    label: None,
+                },
+            })
+            .collect();
+        Self {
+            path: Some(path),
+            entries,
+        }
+    }
+
+    /// `$XDG_CONFIG_HOME/gtk-3.0/bookmarks`.
+    pub fn default_path() -> PathBuf {
+        xdg::config_home().join("gtk-3.0").join("bookmarks")
+    }
+
+    pub fn entries(&self) -> &[Bookmark] {
+        &self.entries
+    }
+
+    /// Whether `dir` is bookmarked.
+    pub fn contains(&self, dir: &Path) -> bool {
+        self.position(dir).is_some()
+    }
+
+    /// Bookmarks `dir` at the end of the list and saves the bookmarks.
+    pub fn add(&mut self, dir: &Path) -> io::Result<()> {
+        if self.contains(dir) {
+            return Ok(());
+        }
+        self.entries.push(Bookmark::new(dir));
+        self.save()
+    }
+
+    /// Forgets the bookmark of `dir` and saves the bookmarks.
+    pub fn remove(&mut self, dir: &Path) -> io::Result<()> {
+        let Some(index) = self.position(dir) else {
+            return Ok(());
+        };
+        self.entries.remove(index);
+        self.save()
+    }
+
+    fn position(&self, dir: &Path) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|bookmark| bookmark.path().as_deref() == Some(dir))
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp)?;
+        for bookmark in &self.entries {
+            match &bookmark.label {
+                Some(label) => writeln!(file, "{} {}", bookmark.uri, label)?,
+                None => writeln!(file, "{}", bookmark.uri)?,
+            }
+        }
+        fs::rename(&tmp, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn parses_user_dirs() {
+        let contents = r#"
+# written by xdg-user-dirs-update
+XDG_DESKTOP_DIR="$HOME/Desktop"
+XDG_DOWNLOAD_DIR="$HOME"
+XDG_MUSIC_DIR="/media/My \"Music\""
+XDG_VIDEOS_DIR=relative
+XDG_PICTURES_DIR=unquoted
+OTHER="$HOME/x"
+"#;
+        let dirs = parse_user_dirs(contents, Path::new("/home/me"));
+        assert_eq!(
+            dirs,
+            [
+                ("DESKTOP".to_string(), PathBuf::from("/home/me/Desktop")),
+                ("DOWNLOAD".to_string(), PathBuf::from("/home/me")),
+                ("MUSIC".to_string(), PathBuf::from("/media/My \"Music\"")),
+            ]
+        );
+        assert_eq!(unquote(r#""a\\b""#).as_deref(), Some(r"a\b"));
+        assert_eq!(unquote("\"open"), None);
+    }
+
+    #[test]
+    fn converts_uris() {
+        let path = Path::new("/home/me/My Files/100%");
+        let uri = file_uri(path);
+        assert!(!uri.contains(' '));
+        assert_eq!(uri_path(&uri).as_deref(), Some(path));
+        assert_eq!(
+            uri_path("file://localhost/tmp").as_deref(),
+            Some(Path::new("/tmp"))
+        );
+        assert_eq!(uri_path("sftp://host/tmp"), None);
+    }
+
+    #[test]
+    fn names_bookmarks() {
+        let labelled = Bookmark {
+            uri: "sftp://host/srv".into(),
+            label: Some("Server".into()),
+        };
+        assert_eq!(labelled.name(), "Server");
+        assert_eq!(labelled.path(), None);
+        assert_eq!(
+            Bookmark::new(Path::new("/home/me/Projects")).name(),
+            "Projects"
+        );
+        assert_eq!(Bookmark::new(Path::new("/")).name(), "/");
+    }
+
+    #[test]
+    fn keeps_bookmarks_in_the_gtk_file() {
+        let dir = TestDir::new();
+        let path = dir.file("bookmarks", "sftp://host/srv Server\n\nfile:///tmp\n");
+        let projects = dir.dir("My Projects");
+        let mut bookmarks = Bookmarks::load(path.clone());
+        assert!(bookmarks.contains(Path::new("/tmp")));
+        bookmarks.add(&projects).unwrap();
+        bookmarks.add(&projects).unwrap();
+        bookmarks.remove(Path::new("/tmp")).unwrap();
+
+        let bookmarks = Bookmarks::load(path);
+        let names: Vec<_> = bookmarks.entries().iter().map(Bookmark::name).collect();
+        assert_eq!(names, ["Server", "My Projects"]);
+        assert!(bookmarks.contains(&projects));
+    }
+}
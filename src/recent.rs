@@ -0,0 +1,475 @@
+//! Recently used files, kept in the freedesktop `recently-used.xbel` so
+//! that the list is shared with other desktop applications.
+
+use crate::places::{file_uri, uri_path};
+use crate::xdg;
+use chrono::{DateTime, SecondsFormat, Utc};
+use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name this application registers its uses under.
+const APP_NAME: &str = "file-manager";
+/// Owner of the metadata block holding MIME types and applications.
+const METADATA_OWNER: &str = "http://freedesktop.org";
+
+/// A local file from the list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentFile {
+    pub path: PathBuf,
+    pub modified: DateTime<Utc>,
+    pub visited: DateTime<Utc>,
+}
+
+impl RecentFile {
+    pub fn last_used(&self) -> DateTime<Utc> {
+        self.modified.max(self.visited)
+    }
+}
+
+/// How a file was used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Usage {
+    Opened,
+    Modified,
+}
+
+/// The `recently-used.xbel` file. Every change reads the file again so that
+/// entries other applications added meanwhile are kept.
+#[derive(Debug, Clone)]
+pub struct RecentFiles {
+    path: PathBuf,
+}
+
+impl RecentFiles {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    /// `$XDG_DATA_HOME/recently-used.xbel`.
+    pub fn default_path() -> PathBuf {
+        xdg::data_home().join("recently-used.xbel")
+    }
+
+    /// Local files of the list, newest first.
+    pub fn list(&self) -> io::Result<Vec<RecentFile>> {
+        let document = self.read()?;
+        let mut files: Vec<RecentFile> = bookmarks(&document)
+            .filter_map(|bookmark| {
+                let path = uri_path(bookmark.attribute("href")?)?;
+                Some(RecentFile {
+                    path,
+                    modified: timestamp(bookmark.attribute("modified")),
+                    visited: timestamp(bookmark.attribute("visited")),
+                })
+            })
+            .collect();
+        files.sort_by_key(|file| std::cmp::Reverse(file.last_used()));
+        Ok(files)
+    }
+
+    /// Records a use of `path` by this application.
+    pub fn record(&self, path: &Path, usage: Usage) -> io::Result<()> {
+        let mut document = self.read()?;
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Micros, true);
+        let bookmark = xbel(&mut document).child_with("bookmark", "href", &file_uri(path));
+        let is_new = bookmark.attribute("added").is_none();
+        if is_new {
+            bookmark.set_attribute("added", &now);
+        }
+        if is_new || usage == Usage::Modified {
+            bookmark.set_attribute("modified", &now);
+        }
+        bookmark.set_attribute("visited", &now);
+
+        let metadata = bookmark
+            .child("info")
+            .child_with("metadata", "owner", METADATA_OWNER);
+        metadata
+            .child("mime:mime-type")
+            .set_attribute("type", mime_type(path));
+        let application = metadata.child("bookmark:applications").child_with(
+            "bookmark:application",
+            "name",
+            APP_NAME,
+        );
+        let count = application
+            .attribute("count")
+            .and_then(|count| count.parse::<u64>().ok())
+            .unwrap_or(0);
+        application.set_attribute("exec", &format!("'{} %u'", APP_NAME));
+        application.set_attribute("modified", &now);
+        application.set_attribute("count", &(count + 1).to_string());
+
+        self.write(&document)
+    }
+
+    /// Drops `path` from the list.
+    pub fn remove(&self, path: &Path) -> io::Result<()> {
+        let uri = file_uri(path);
+        self.retain(|node| !is_bookmark(node, Some(&uri)))
+    }
+
+    /// Empties the list.
+    pub fn clear(&self) -> io::Result<()> {
+        self.retain(|node| !is_bookmark(node, None))
+    }
+
+    fn retain(&self, keep: impl Fn(&Node) -> bool) -> io::Result<()> {
+        let mut document = self.read()?;
+        xbel(&mut document).children.retain(keep);
+        self.write(&document)
+    }
+
+    /// Parses the file; a missing file is an empty list.
+    fn read(&self) -> io::Result<Vec<Node>> {
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => parse(&contents).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", self.path.display(), e),
+                )
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn write(&self, document: &[Node]) -> io::Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
+        for node in document {
+            write_node(&mut writer, node)?;
+        }
+        let mut contents = writer.into_inner();
+        contents.push(b'\n');
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, contents)?;
+        fs::rename(&tmp, &self.path)
+    }
+}
+
+fn timestamp(value: Option<&str>) -> DateTime<Utc> {
+    value
+        .and_then(|value| DateTime::parse_from_rfc3339(value).ok())
+        .map(|time| time.with_timezone(&Utc))
+        .unwrap_or_default()
+}
+
+/// MIME type of a file guessed from its extension, as the spec requires one.
+fn mime_type(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "txt" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "json" => "application/json",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "flac" => "audio/flac",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        _ => "application/octet-stream",
+    }
+}
+
+/// A node of the parsed file. Everything this module does not look into is
+/// kept as it was read.
+#[derive(Debug, Clone)]
+enum Node {
+    Element(Element),
+    Text(String),
+    Other(Event<'static>),
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Node>,
+}
+
+impl Element {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            attributes: Vec::new(),
+            children: Vec::new(),
+        }
+    }
+
+    fn with_attribute(mut self, key: &str, value: &str) -> Self {
+        self.set_attribute(key, value);
+        self
+    }
+
+    fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn set_attribute(&mut self, key: &str, value: &str) {
+        match self.attributes.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value.to_string(),
+            None => self.attributes.push((key.to_string(), value.to_string())),
+        }
+    }
+
+    /// The first child element named `name`, added if missing.
+    fn child(&mut self, name: &str) -> &mut Element {
+        self.find_or_add(name, |_| true, || Element::new(name))
+    }
+
+    /// The first child element named `name` whose `key` is `value`, added
+    /// if missing.
+    fn child_with(&mut self, name: &str, key: &str, value: &str) -> &mut Element {
+        self.find_or_add(
+            name,
+            |element| element.attribute(key) == Some(value),
+            || Element::new(name).with_attribute(key, value),
+        )
+    }
+
+    fn find_or_add(
+        &mut self,
+        name: &str,
+        matches: impl Fn(&Element) -> bool,
+        new: impl FnOnce() -> Element,
+    ) -> &mut Element {
+        let index = self.children.iter().position(|node| {
+            matches!(node, Node::Element(element) if element.name == name && matches(element))
+        });
+        let index = index.unwrap_or_else(|| {
+            self.children.push(Node::Element(new()));
+            self.children.len() - 1
+        });
+        match &mut self.children[index] {
+            Node::Element(element) => element,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// The `<xbel>` root of the document, created if the document is empty.
+fn xbel(document: &mut Vec<Node>) -> &mut Element {
+    let index = document
+        .iter()
+        .position(|node| matches!(node, Node::Element(root) if root.name == "xbel"));
+    let index = index.unwrap_or_else(|| {
+        let decl = BytesDecl::new("1.0", Some("UTF-8"), None);
+        let root = Element::new("xbel")
+            .with_attribute("version", "1.0")
+            .with_attribute(
+                "xmlns:bookmark",
+                "http://www.freedesktop.org/standards/desktop-bookmarks",
+            )
+            .with_attribute(
+                "xmlns:mime",
+                "http://www.freedesktop.org/standards/shared-mime-info",
+            );
+        document.clear();
+        document.push(Node::Other(Event::Decl(decl)));
+        document.push(Node::Element(root));
+        1
+    });
+    match &mut document[index] {
+        Node::Element(root) => root,
+        _ => unreachable!(),
+    }
+}
+
+fn bookmarks(document: &[Node]) -> impl Iterator<Item = &Element> {
+    document
+        .iter()
+        .filter_map(|node| match node {
+            Node::Element(root) if root.name == "xbel" => Some(root.children.iter()),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|node| match node {
+            Node::Element(element) if element.name == "bookmark" => Some(element),
+            _ => None,
+        })
+}
+
+/// Whether `node` is a bookmark, of `uri` if given.
+fn is_bookmark(node: &Node, uri: Option<&str>) -> bool {
+    match node {
+        Node::Element(element) if element.name == "bookmark" => {
+            uri.is_none() || element.attribute("href") == uri
+        }
+        _ => false,
+    }
+}
+
+fn parse(xml: &str) -> quick_xml::Result<Vec<Node>> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut document = Vec::new();
+    // Elements whose end tag has not been read yet.
+    let mut open: Vec<Element> = Vec::new();
+    loop {
+        let node = match reader.read_event()? {
+            Event::Start(start) => {
+                open.push(element(&start)?);
+                continue;
+            }
+            Event::End(_) => match open.pop() {
+                Some(element) => Node::Element(element),
+                None => continue,
+            },
+            Event::Empty(start) => Node::Element(element(&start)?),
+            Event::Text(text) => Node::Text(text.unescape()?.into_owned()),
+            Event::Eof => break,
+            other => Node::Other(other.into_owned()),
+        };
+        match open.last_mut() {
+            Some(parent) => parent.children.push(node),
+            None => document.push(node),
+        }
+    }
+    Ok(document)
+}
+
+fn element(start: &BytesStart) -> quick_xml::Result<Element> {
+    let mut element = Element::new(&String::from_utf8_lossy(start.name().as_ref()));
+    for attribute in start.attributes() {
+        let attribute = attribute?;
+        element.attributes.push((
+            String::from_utf8_lossy(attribute.key.as_ref()).into_owned(),
+            attribute.unescape_value()?.into_owned(),
+        ));
+    }
+    Ok(element)
+}
+
+fn write_node(writer: &mut Writer<Vec<u8>>, node: &Node) -> io::Result<()> {
+    match node {
+        Node::Element(element) => {
+            let start = BytesStart::new(element.name.as_str()).with_attributes(
+                element
+                    .attributes
+                    .iter()
+                    .map(|(k, v)| (k.as_str(), v.as_str())),
+            );
+            if element.children.is_empty() {
+                return writer.write_event(Event::Empty(start));
+            }
+            writer.write_event(Event::Start(start))?;
+            for child in &element.children {
+                write_node(writer, child)?;
+            }
+            writer.write_event(Event::End(BytesEnd::new(element.name.as_str())))
+        }
+        Node::Text(text) => writer.write_event(Event::Text(BytesText::new(text))),
+        Node::Other(event) => writer.write_event(event.borrow()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    const OTHER_APP: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<xbel version="1.0" xmlns:bookmark="http://www.freedesktop.org/standards/desktop-bookmarks" xmlns:mime="http://www.freedesktop.org/standards/shared-mime-info">
+  <bookmark href="file:///home/me/old%20notes.txt" added="2024-01-01T10:00:00Z" modified="2024-01-01T10:00:00Z" visited="2024-01-02T10:00:00Z">
+    <title>Old &amp; notes</title>
+    <info>
+      <metadata owner="http://freedesktop.org">
+        <mime:mime-type type="text/plain"/>
+        <bookmark:applications>
+          <bookmark:application name="gedit" exec="&apos;gedit %u&apos;" modified="2024-01-01T10:00:00Z" count="3"/>
+        </bookmark:applications>
+      </metadata>
+    </info>
+  </bookmark>
+  <bookmark href="https://example.com/" added="2024-01-01T10:00:00Z" modified="2024-01-01T10:00:00Z" visited="2024-01-01T10:00:00Z"/>
+</xbel>
+"#;
+
+    #[test]
+    fn lists_local_files_newest_first() {
+        let dir = TestDir::new();
+        let recent = RecentFiles::new(dir.file("recently-used.xbel", OTHER_APP));
+        let files = recent.list().unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, PathBuf::from("/home/me/old notes.txt"));
+        assert_eq!(
+            files[0].last_used().to_rfc3339(),
+            "2024-01-02T10:00:00+00:00"
+        );
+
+        recent
+            .record(Path::new("/home/me/new.pdf"), Usage::Opened)
+            .unwrap();
+        let files = recent.list().unwrap();
+        assert_eq!(files[0].path, PathBuf::from("/home/me/new.pdf"));
+        assert_eq!(files.len(), 2);
+    }
+
+    #[test]
+    fn keeps_what_other_applications_wrote() {
+        let dir = TestDir::new();
+        let path = dir.file("recently-used.xbel", OTHER_APP);
+        let recent = RecentFiles::new(path.clone());
+        let old = Path::new("/home/me/old notes.txt");
+        recent.record(old, Usage::Opened).unwrap();
+        recent.record(old, Usage::Modified).unwrap();
+
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(contents.contains("<title>Old &amp; notes</title>"));
+        assert!(contents.contains("https://example.com/"));
+        assert!(contents.contains(r#"name="gedit""#));
+        assert!(contents.contains(r#"name="file-manager" exec="&apos;file-manager %u&apos;""#));
+        assert!(contents.contains(r#"count="2""#));
+        assert!(contents.contains(r#"added="2024-01-01T10:00:00Z""#));
+        let modified = recent.list().unwrap()[0].modified;
+        assert!(modified > timestamp(Some("2024-01-02T10:00:00Z")));
+    }
+
+    #[test]
+    fn creates_removes_and_clears() {
+        let dir = TestDir::new();
+        let recent = RecentFiles::new(dir.path().join("data/recently-used.xbel"));
+        assert!(recent.list().unwrap().is_empty());
+        recent.record(Path::new("/a.png"), Usage::Modified).unwrap();
+        recent.record(Path::new("/b"), Usage::Opened).unwrap();
+        let contents = fs::read_to_string(dir.path().join("data/recently-used.xbel")).unwrap();
+        assert!(contents.starts_with("<?xml"));
+        assert!(contents.contains(r#"<mime:mime-type type="image/png"/>"#));
+        assert!(contents.contains(r#"type="application/octet-stream""#));
+
+        recent.remove(Path::new("/a.png")).unwrap();
+        let paths: Vec<_> = recent.list().unwrap().into_iter().map(|f| f.path).collect();
+        assert_eq!(paths, [PathBuf::from("/b")]);
+        recent.clear().unwrap();
+        assert!(recent.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn reports_malformed_files() {
+        let dir = TestDir::new();
+        let recent =
+            RecentFiles::new(dir.file("recently-used.xbel", "<xbel><bookmark href='x' =>"));
+        let e = recent.list().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,552 @@
+//! Recursive search under a directory, by file name or by file contents.
+
+use crate::entry::DirEntryInfo;
+use crate::filter::glob_match;
+use crate::transfer::CancelToken;
+use regex::{Regex, RegexBuilder};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Matches are handed over once this many are pending...
+const BATCH_SIZE: usize = 32;
+/// ...or once the oldest pending one has waited this long.
+const BATCH_DELAY: Duration = Duration::from_millis(100);
+
+/// Bytes sniffed at the start of a file to tell text from binary.
+const SNIFF_LEN: usize = 8192;
+/// Larger files are not scanned for contents.
+const MAX_CONTENT_SIZE: u64 = 32 * 1024 * 1024;
+/// Lines reported per file in a content search.
+const MAX_LINES_PER_FILE: usize = 20;
+/// Characters of a matching line shown around the match.
+const SNIPPET_LEN: usize = 80;
+/// Files scanned at the same time in a content search.
+const MAX_WORKERS: usize = 8;
+
+/// How the query text is matched. All modes ignore case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchMode {
+    #[default]
+    Substring,
+    Glob,
+    Regex,
+    /// The text is looked for in the lines of text files.
+    Content,
+}
+
+impl SearchMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            SearchMode::Substring => "Text",
+            SearchMode::Glob => "Glob",
+            SearchMode::Regex => "Regex",
+            SearchMode::Content => "Content",
+        }
+    }
+
+    /// The mode after this one, for a button cycling through them.
+    pub fn next(self) -> Self {
+        match self {
+            SearchMode::Substring => SearchMode::Glob,
+            SearchMode::Glob => SearchMode::Regex,
+            SearchMode::Regex => SearchMode::Content,
+            SearchMode::Content => SearchMode::Substring,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchQuery {
+    pub text: String,
+    pub mode: SearchMode,
+    /// Descend into and report dot files.
+    pub show_hidden: bool,
+    /// Glob patterns of names to skip, folders with their contents.
+    pub excludes: Vec<String>,
+}
+
+/// A name, or a line of a file, matching the query.
+#[derive(Debug, Clone)]
+pub struct SearchMatch {
+    pub entry: DirEntryInfo,
+    /// Path of the match relative to the searched folder.
+    pub relative_path: PathBuf,
+    /// The matching line, for a content search.
+    pub line: Option<LineMatch>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LineMatch {
+    /// 1-based number of the line in the file.
+    pub number: usize,
+    /// The line, shortened around the match if it is long.
+    pub snippet: String,
+    /// Byte range of the match within `snippet`.
+    pub highlight: Range<usize>,
+}
+
+/// Outcome of a finished search.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchSummary {
+    pub matches: usize,
+    /// Folders that could not be read and were skipped.
+    pub unreadable: usize,
+    pub cancelled: bool,
+    /// Whether the matches were looked up in the search index rather than
+    /// found by walking the folders.
+    pub indexed: bool,
+    /// Whether more files matched than the lookup returned.
+    pub truncated: bool,
+}
+
+#[derive(Debug)]
+enum Pattern {
+    Substring(String),
+    Glob(String),
+    Regex(Regex),
+    Content(Regex),
+}
+
+/// A query compiled for a walk of `root`.
+#[derive(Debug)]
+pub struct Search {
+    root: PathBuf,
+    pattern: Pattern,
+    show_hidden: bool,
+    excludes: Vec<String>,
+}
+
+impl Search {
+    /// Prepares `query` for searching under `root`. Fails if the query is
+    /// an invalid regular expression.
+    pub fn new(root: PathBuf, query: &SearchQuery) -> Result<Self, regex::Error> {
+        let text = query.text.trim();
+        let pattern = match query.mode {
+            SearchMode::Substring => Pattern::Substring(text.to_lowercase()),
+            SearchMode::Glob => Pattern::Glob(text.to_lowercase()),
+            SearchMode::Regex => {
+                Pattern::Regex(RegexBuilder::new(text).case_insensitive(true).build()?)
+            }
+            SearchMode::Content => Pattern::Content(
+                RegexBuilder::new(&regex::escape(text))
+                    .case_insensitive(true)
+                    .build()?,
+            ),
+        };
+        Ok(Self {
+            root,
+            pattern,
+            show_hidden: query.show_hidden,
+            excludes: query.excludes.iter().map(|e| e.to_lowercase()).collect(),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub(crate) fn name_matches(&self, name: &str) -> bool {
+        match &self.pattern {
+            Pattern::Substring(text) => name.to_lowercase().contains(text.as_str()),
+            Pattern::Glob(pattern) => glob_match(pattern, &name.to_lowercase()),
+            Pattern::Regex(regex) => regex.is_match(name),
+            Pattern::Content(_) => false,
+        }
+    }
+
+    /// Whether entries named `name` are left out, with their contents.
+    pub(crate) fn skipped(&self, name: &str) -> bool {
+        if name.starts_with('.') && !self.show_hidden {
+            return true;
+        }
+        let name = name.to_lowercase();
+        self.excludes
+            .iter()
+            .any(|pattern| glob_match(pattern, &name))
+    }
+
+    /// Walks the tree under the root, passing matches to `on_matches` in
+    /// batches as they are found. Symlinked folders are not followed.
+    /// Stops early when `cancel` is triggered.
+    pub fn run(
+        &self,
+        cancel: &CancelToken,
+        on_matches: impl FnMut(Vec<SearchMatch>),
+    ) -> SearchSummary {
+        let mut batcher = Batcher::new(on_matches);
+        let mut summary = match &self.pattern {
+            Pattern::Content(regex) => self.run_content(regex, cancel, &mut batcher),
+            _ => self.walk(cancel, |path, name| {
+                if !self.name_matches(name) {
+                    return;
+                }
+                if let Ok(info) = DirEntryInfo::read(path.to_path_buf()) {
+                    batcher.push(self.found(info, None));
+                }
+            }),
+        };
+        summary.matches = batcher.finish();
+        summary
+    }
+
+    /// Scans the regular files of the tree on worker threads while the
+    /// walk goes on.
+    fn run_content(
+        &self,
+        regex: &Regex,
+        cancel: &CancelToken,
+        batcher: &mut Batcher<impl FnMut(Vec<SearchMatch>)>,
+    ) -> SearchSummary {
+        let workers = thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .min(MAX_WORKERS);
+        let (path_tx, path_rx) = mpsc::channel::<PathBuf>();
+        let path_rx = Mutex::new(path_rx);
+        let (match_tx, match_rx) = mpsc::channel();
+
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                let match_tx = match_tx.clone();
+                let path_rx = &path_rx;
+                scope.spawn(move || loop {
+                    let next = path_rx.lock().ok().and_then(|rx| rx.recv().ok());
+                    let Some(path) = next else {
+                        break;
+                    };
+                    if cancel.is_cancelled() {
+                        continue;
+                    }
+                    let lines = match scan_file(&path, regex) {
+                        Ok(lines) if !lines.is_empty() => lines,
+                        _ => continue,
+                    };
+                    let Ok(info) = DirEntryInfo::read(path) else {
+                        continue;
+                    };
+                    let matches = lines
+                        .into_iter()
+                        .map(|line| self.found(info.clone(), Some(line)))
+                        .collect::<Vec<_>>();
+                    if match_tx.send(matches).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(match_tx);
+
+            let walker = scope.spawn(move || {
+                self.walk(cancel, |path, _| {
+                    let is_file = fs::symlink_metadata(path).is_ok_and(|m| m.is_file());
+                    if is_file {
+                        let _ = path_tx.send(path.to_path_buf());
+                    }
+                })
+            });
+
+            // Matches are forwarded from this thread so that `on_matches`
+            // does not need to be `Send`.
+            loop {
+                match match_rx.recv_timeout(BATCH_DELAY) {
+                    Ok(matches) => {
+                        for found in matches {
+                            batcher.push(found);
+                        }
+                    }
+                    Err(mpsc::RecvTimeoutError::Timeout) => batcher.flush_if_due(),
+                    Err(mpsc::RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            walker.join().unwrap_or_default()
+        })
+    }
+
+    /// Calls `visit` with the path and name of every entry of the tree that
+    /// is not skipped, descending into real folders.
+    fn walk(&self, cancel: &CancelToken, mut visit: impl FnMut(&Path, &str)) -> SearchSummary {
+        let mut summary = SearchSummary::default();
+        let mut dirs = vec![self.root.clone()];
+        while let Some(dir) = dirs.pop() {
+            let Ok(read_dir) = fs::read_dir(&dir) else {
+                summary.unreadable += 1;
+                continue;
+            };
+            for entry in read_dir.flatten() {
+                if cancel.is_cancelled() {
+                    summary.cancelled = true;
+                    return summary;
+                }
+                let name = entry.file_name().to_string_lossy().to_string();
+                if self.skipped(&name) {
+                    continue;
+                }
+                let path = entry.path();
+                if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                    dirs.push(path.clone());
+                }
+                visit(&path, &name);
+            }
+        }
+        summary
+    }
+
+    /// The pattern lines are matched against, for a content search.
+    pub(crate) fn content_regex(&self) -> Option<&Regex> {
+        match &self.pattern {
+            Pattern::Content(regex) => Some(regex),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn found(&self, entry: DirEntryInfo, line: Option<LineMatch>) -> SearchMatch {
+        SearchMatch {
+            relative_path: entry
+                .path
+                .strip_prefix(&self.root)
+                .unwrap_or(&entry.path)
+                .to_path_buf(),
+            entry,
+            line,
+        }
+    }
+}
+
+/// Collects matches and hands them over in batches.
+struct Batcher<F> {
+    on_matches: F,
+    pending: Vec<SearchMatch>,
+    pending_since: Instant,
+    total: usize,
+}
+
+impl<F: FnMut(Vec<SearchMatch>)> Batcher<F> {
+    fn new(on_matches: F) -> Self {
+        Self {
+            on_matches,
+            pending: Vec::new(),
+            pending_since: Instant::now(),
+            total: 0,
+        }
+    }
+
+    fn push(&mut self, found: SearchMatch) {
+        if self.pending.is_empty() {
+            self.pending_since = Instant::now();
+        }
+        self.pending.push(found);
+        self.total += 1;
+        if self.pending.len() >= BATCH_SIZE {
+            self.flush();
+        } else {
+            self.flush_if_due();
+        }
+    }
+
+    fn flush_if_due(&mut self) {
+        if !self.pending.is_empty() && self.pending_since.elapsed() >= BATCH_DELAY {
+            self.flush();
+        }
+    }
+
+    fn flush(&mut self) {
+        (self.on_matches)(std::mem::take(&mut self.pending));
+    }
+
+    /// Hands over what is left and returns the number of matches.
+    fn finish(mut self) -> usize {
+        if !self.pending.is_empty() {
+            self.flush();
+        }
+        self.total
+    }
+}
+
+/// Matching lines of a text file. Binary and very large files have none.
+fn scan_file(path: &Path, regex: &Regex) -> io::Result<Vec<LineMatch>> {
+    let mut file = File::open(path)?;
+    if file.metadata()?.len() > MAX_CONTENT_SIZE {
+        return Ok(Vec::new());
+    }
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+    if is_binary(&contents) {
+        return Ok(Vec::new());
+    }
+    Ok(scan_text(&contents, regex))
+}
+
+/// Matching lines of `contents`, up to [`MAX_LINES_PER_FILE`].
+pub(crate) fn scan_text(contents: &[u8], regex: &Regex) -> Vec<LineMatch> {
+    let mut lines = Vec::new();
+    for (index, line) in contents.split(|&b| b == b'\n').enumerate() {
+        let line = String::from_utf8_lossy(line);
+        if let Some(found) = regex.find(&line) {
+            lines.push(snippet(index + 1, &line, found.range()));
+            if lines.len() == MAX_LINES_PER_FILE {
+                break;
+            }
+        }
+    }
+    lines
+}
+
+/// Text files have no NUL byte near their start.
+pub(crate) fn is_binary(contents: &[u8]) -> bool {
+    contents[..contents.len().min(SNIFF_LEN)].contains(&0)
+}
+
+/// Cuts `line` down to about [`SNIPPET_LEN`] characters around `found`.
+fn snippet(number: usize, line: &str, found: Range<usize>) -> LineMatch {
+    let line = line.trim_end_matches('\r');
+    let found = found.start.min(line.len())..found.end.min(line.len());
+    let indent = line.len() - line.trim_start().len();
+    let mut start = indent.min(found.start);
+    let mut end = line.len();
+    if line[start..end].chars().count() > SNIPPET_LEN {
+        let context = SNIPPET_LEN.saturating_sub(line[found.clone()].chars().count()) / 2;
+        start = line[..found.start]
+            .char_indices()
+            .rev()
+            .nth(context.saturating_sub(1))
+            .map_or(found.start, |(i, _)| i)
+            .max(start);
+        end = line[found.end..]
+            .char_indices()
+            .nth(context)
+            .map_or(line.len(), |(i, _)| found.end + i);
+    }
+    let prefix = if start > indent { "…" } else { "" };
+    let suffix = if end < line.len() { "…" } else { "" };
+    let highlight_start = prefix.len() + found.start - start;
+    LineMatch {
+        number,
+        snippet: format!("{}{}{}", prefix, &line[start..end], suffix),
+        highlight: highlight_start..highlight_start + found.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    fn query(text: &str, mode: SearchMode) -> SearchQuery {
+        SearchQuery {
+            text: text.into(),
+            mode,
+            ..SearchQuery::default()
+        }
+    }
+
+    /// Relative paths of the matches of `query` under `dir`, sorted.
+    fn search(dir: &TestDir, query: &SearchQuery) -> Vec<String> {
+        let search = Search::new(dir.path().to_path_buf(), query).unwrap();
+        let mut found = Vec::new();
+        let summary = search.run(&CancelToken::new(), |matches| {
+            found.extend(matches.into_iter().map(|m| {
+                let mut path = m.relative_path.to_string_lossy().to_string();
+                if let Some(line) = m.line {
+                    path = format!("{}:{}", path, line.number);
+                }
+                path
+            }))
+        });
+        assert_eq!(summary.matches, found.len());
+        found.sort();
+        found
+    }
+
+    fn tree() -> TestDir {
+        let dir = TestDir::new();
+        dir.file("Report.txt", "");
+        dir.file("docs/report-2.md", "");
+        dir.file("docs/notes.txt", "");
+        dir.file(".cache/report.txt", "");
+        dir.file("target/report.txt", "");
+        dir
+    }
+
+    #[test]
+    fn finds_names() {
+        let dir = tree();
+        assert_eq!(
+            search(&dir, &query("REPORT", SearchMode::Substring)),
+            ["Report.txt", "docs/report-2.md", "target/report.txt"]
+        );
+        assert_eq!(
+            search(&dir, &query("*.txt", SearchMode::Glob)),
+            ["Report.txt", "docs/notes.txt", "target/report.txt"]
+        );
+        assert_eq!(
+            search(&dir, &query("^report-\\d", SearchMode::Regex)),
+            ["docs/report-2.md"]
+        );
+        assert!(Search::new(dir.path().to_path_buf(), &query("(", SearchMode::Regex)).is_err());
+    }
+
+    #[test]
+    fn skips_hidden_and_excluded_entries() {
+        let dir = tree();
+        let mut query = query("report", SearchMode::Substring);
+        query.show_hidden = true;
+        query.excludes = vec!["TARGET".into(), "*.md".into()];
+        assert_eq!(search(&dir, &query), [".cache/report.txt", "Report.txt"]);
+    }
+
+    #[test]
+    fn stops_when_cancelled() {
+        let dir = tree();
+        let search = Search::new(dir.path().to_path_buf(), &query("*", SearchMode::Glob)).unwrap();
+        let cancel = CancelToken::new();
+        cancel.cancel();
+        let summary = search.run(&cancel, |_| panic!("no matches expected"));
+        assert!(summary.cancelled);
+        assert_eq!(summary.matches, 0);
+    }
+
+    #[test]
+    fn finds_lines_of_text_files() {
+        let dir = TestDir::new();
+        dir.file("a.conf", "port = 80\r\nHOST = x\nhost_name = y\n");
+        dir.file("sub/b.log", "no match\nlast host");
+        dir.file("binary", b"host\0");
+        assert_eq!(
+            search(&dir, &query("host", SearchMode::Content)),
+            ["a.conf:2", "a.conf:3", "sub/b.log:2"]
+        );
+        assert_eq!(
+            search(&dir, &query("(", SearchMode::Content)),
+            [] as [&str; 0]
+        );
+    }
+
+    #[test]
+    fn limits_lines_per_file() {
+        let regex = Regex::new("x").unwrap();
+        let lines = scan_text("x\n".repeat(MAX_LINES_PER_FILE * 2).as_bytes(), &regex);
+        assert_eq!(lines.len(), MAX_LINES_PER_FILE);
+        assert_eq!(lines[MAX_LINES_PER_FILE - 1].number, MAX_LINES_PER_FILE);
+        assert!(is_binary(b"text\0"));
+        assert!(!is_binary(b"text"));
+    }
+
+    #[test]
+    fn shortens_long_lines_around_the_match() {
+        let line = format!("    {}needle{}\r", "a".repeat(100), "é".repeat(100));
+        let found = line.find("needle").unwrap();
+        let matched = snippet(7, &line, found..found + 6);
+        assert_eq!(matched.number, 7);
+        assert!(matched.snippet.starts_with('…') && matched.snippet.ends_with('…'));
+        assert_eq!(&matched.snippet[matched.highlight.clone()], "needle");
+        assert!(matched.snippet.chars().count() <= SNIPPET_LEN + 2);
+
+        let matched = snippet(1, "  short needle", 8..14);
+        assert_eq!(matched.snippet, "short needle");
+        assert_eq!(matched.highlight, 6..12);
+    }
+}
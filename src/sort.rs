@@ -0,0 +1,324 @@
+//! Ordering of directory listings and the per-directory choice of it.
+
+use crate::entry::DirEntryInfo;
+use crate::trash::{decode_path, encode_path};
+use crate::xdg;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    Name,
+    /// File size; folders compare by their number of entries.
+    Size,
+    Modified,
+    /// Extension, then name.
+    Type,
+}
+
+impl SortKey {
+    pub const ALL: [SortKey; 4] = [
+        SortKey::Name,
+        SortKey::Size,
+        SortKey::Modified,
+        SortKey::Type,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SortKey::Name => "Name",
+            SortKey::Size => "Size",
+            SortKey::Modified => "Modified",
+            SortKey::Type => "Type",
+        }
+    }
+
+    fn id(self) -> &'static str {
+        match self {
+            SortKey::Name => "name",
+            SortKey::Size => "size",
+            SortKey::Modified => "modified",
+            SortKey::Type => "type",
+        }
+    }
+
+    fn from_id(id: &str) -> Option<Self> {
+        SortKey::ALL.into_iter().find(|key| key.id() == id)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SortOrder {
+    pub key: SortKey,
+    pub descending: bool,
+    /// List folders before files whatever the key and direction.
+    pub folders_first: bool,
+}
+
+impl Default for SortOrder {
+    fn default() -> Self {
+        Self {
+            key: SortKey::Name,
+            descending: false,
+            folders_first: true,
+        }
+    }
+}
+
+impl SortOrder {
+    /// Total order of two entries. Ties on the key are broken by name so
+    /// that the listing is stable.
+    pub fn compare(&self, a: &DirEntryInfo, b: &DirEntryInfo) -> Ordering {
+        if self.folders_first && a.is_dir() != b.is_dir() {
+            return b.is_dir().cmp(&a.is_dir());
+        }
+        let by_key = match self.key {
+            SortKey::Name => Ordering::Equal,
+            SortKey::Size => sort_size(a).cmp(&sort_size(b)),
+            SortKey::Modified => a.modified.cmp(&b.modified),
+            SortKey::Type => extension(a).cmp(&extension(b)),
+        };
+        let ordering = by_key
+            .then_with(|| natural_cmp(&a.name, &b.name))
+            .then_with(|| a.name.cmp(&b.name));
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+fn sort_size(entry: &DirEntryInfo) -> u64 {
+    if entry.is_dir() {
+        entry.child_count.unwrap_or(0) as u64
+    } else {
+        entry.size
+    }
+}
+
+fn extension(entry: &DirEntryInfo) -> String {
+    if entry.is_dir() {
+        return String::new();
+    }
+    entry
+        .path
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase())
+        .unwrap_or_default()
+}
+
+/// Compares names the way people read them: runs of digits by their value
+/// (`File2` before `File10`), everything else case-insensitively and by the
+/// collation rules of the `LC_COLLATE` locale set by the program.
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a, b);
+    while !a.is_empty() && !b.is_empty() {
+        let (a_chunk, a_rest) = split_chunk(a);
+        let (b_chunk, b_rest) = split_chunk(b);
+        let a_digits = a_chunk.starts_with(|c: char| c.is_ascii_digit());
+        let b_digits = b_chunk.starts_with(|c: char| c.is_ascii_digit());
+        let ordering = match (a_digits, b_digits) {
+            (true, true) => {
+                let a_num = a_chunk.trim_start_matches('0');
+                let b_num = b_chunk.trim_start_matches('0');
+                a_num.len().cmp(&b_num.len()).then_with(|| a_num.cmp(b_num))
+            }
+            // Numbers sort before letters.
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => collate(&a_chunk.to_lowercase(), &b_chunk.to_lowercase()),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+        (a, b) = (a_rest, b_rest);
+    }
+    a.len().cmp(&b.len())
+}
+
+/// Splits off the leading run of digits or of non-digits.
+fn split_chunk(s: &str) -> (&str, &str) {
+    let digits = s.starts_with(|c: char| c.is_ascii_digit());
+    let end = s
+        .find(|c: char| c.is_ascii_digit() != digits)
+        .unwrap_or(s.len());
+    s.split_at(end)
+}
+
+fn collate(a: &str, b: &str) -> Ordering {
+    match (CString::new(a), CString::new(b)) {
+        // SAFETY: both strings are NUL-terminated and outlive the call.
+        (Ok(a), Ok(b)) => unsafe { libc::strcoll(a.as_ptr(), b.as_ptr()) }.cmp(&0),
+        _ => a.cmp(b),
+    }
+}
+
+/// Sort order chosen for each directory, persisted to a file.
+#[derive(Debug, Clone, Default)]
+pub struct SortPreferences {
+    /// File the preferences are saved to; `None` keeps them in memory only.
+    path: Option<PathBuf>,
+    orders: HashMap<PathBuf, SortOrder>,
+}
+
+impl SortPreferences {
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+
+    /// Reads the preferences stored at `path`; malformed lines are dropped.
+    pub fn load(path: PathBuf) -> Self {
+        let contents = fs::read_to_string(&path).unwrap_or_default();
+        let orders = contents
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split('\t');
+                let dir = decode_path(fields.next()?);
+                let key = SortKey::from_id(fields.next()?)?;
+                let descending = fields.next()? == "desc";
+                let folders_first = fields.next()? == "folders-first";
+                let order = SortOrder {
+                    key,
+                    descending,
+                    folders_first,
+                };
+                Some((dir, order))
+            })
+            .collect();
+        Self {
+            path: Some(path),
+            orders,
+        }
+    }
+
+    /// `$XDG_STATE_HOME/file-manager/sort-orders`.
+    pub fn default_path() -> PathBuf {
+        xdg::app_state_dir().join("sort-orders")
+    }
+
+    /// The order for `dir`, or the default one.
+    pub fn get(&self, dir: &Path) -> SortOrder {
+        self.orders.get(dir).copied().unwrap_or_default()
+    }
+
+    /// Remembers `order` for `dir` and saves the preferences.
+    pub fn set(&mut self, dir: &Path, order: SortOrder) {
+        if order == SortOrder::default() {
+            self.orders.remove(dir);
+        } else {
+            self.orders.insert(dir.to_path_buf(), order);
+        }
+        if let Err(e) = self.save() {
+            tracing::warn!("Failed to save sort orders: {}", e);
+        }
+    }
+
+    fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("tmp");
+        let mut file = fs::File::create(&tmp)?;
+        for (dir, order) in &self.orders {
+            writeln!(
+                file,
+                "{}\t{}\t{}\t{}",
+                encode_path(dir),
+                order.key.id(),
+                if order.descending { "desc" } else { "asc" },
+                if order.folders_first {
+                    "folders-first"
+                } else {
+                    "mixed"
+                }
+            )?;
+        }
+        fs::rename(&tmp, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn compares_names_naturally() {
+        let mut names = vec![
+            "file10", "File2", "file1", "b", "A", "10", "9", "file01a", "file1a",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            ["9", "10", "A", "b", "file1", "file01a", "file1a", "File2", "file10"]
+        );
+        assert_eq!(natural_cmp("abc", "ABC"), Ordering::Equal);
+        assert_eq!(natural_cmp("a", "a1"), Ordering::Less);
+        assert_eq!(natural_cmp("a\0b", "a\0c"), Ordering::Less);
+    }
+
+    #[test]
+    fn orders_entries() {
+        let dir = TestDir::new();
+        dir.file("b.txt", "12345");
+        dir.file("c.md", "1");
+        dir.file("B.txt", "123");
+        dir.dir("z");
+        let mut entries: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| DirEntryInfo::read(entry.unwrap().path()).unwrap())
+            .collect();
+        let mut sorted = |order: SortOrder| {
+            entries.sort_by(|a, b| order.compare(a, b));
+            entries.iter().map(|e| e.name.clone()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            sorted(SortOrder::default()),
+            ["z", "B.txt", "b.txt", "c.md"]
+        );
+        let by_size = SortOrder {
+            key: SortKey::Size,
+            descending: true,
+            folders_first: false,
+        };
+        assert_eq!(sorted(by_size), ["b.txt", "B.txt", "c.md", "z"]);
+        let by_type = SortOrder {
+            key: SortKey::Type,
+            ..SortOrder::default()
+        };
+        assert_eq!(sorted(by_type), ["z", "c.md", "B.txt", "b.txt"]);
+    }
+
+    #[test]
+    fn remembers_orders_per_directory() {
+        let dir = TestDir::new();
+        let path = dir.path().join("state/sort-orders");
+        let order = SortOrder {
+            key: SortKey::Modified,
+            descending: true,
+            folders_first: false,
+        };
+        let mut preferences = SortPreferences::load(path.clone());
+        preferences.set(Path::new("/a\tb"), order);
+        preferences.set(Path::new("/c"), order);
+        preferences.set(Path::new("/c"), SortOrder::default());
+
+        let preferences = SortPreferences::load(path.clone());
+        assert_eq!(preferences.get(Path::new("/a\tb")), order);
+        assert_eq!(preferences.get(Path::new("/c")), SortOrder::default());
+        assert_eq!(preferences.orders.len(), 1);
+
+        fs::write(&path, "/d\tname\tdesc\n/e\tcolour\tasc\tmixed\n").unwrap();
+        let preferences = SortPreferences::load(path);
+        assert_eq!(preferences.orders.len(), 0);
+    }
+}
@@ -0,0 +1,579 @@
+//! Reading of text files too large to load at once. Files are read in chunks
+//! and an index of where lines start lets any range of lines be read without
+//! decoding what comes before it. The encoding and line endings are detected
+//! from the start of the file.
+
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8};
+use std::fmt;
+use std::fs::{self, File};
+use std::io;
+use std::os::unix::fs::{FileExt, MetadataExt};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+const CHUNK_SIZE: usize = 256 * 1024;
+/// Bytes the encoding is detected from.
+const SAMPLE_SIZE: usize = 64 * 1024;
+/// Lines between two offsets kept by the index.
+const CHECKPOINT_LINES: usize = 64;
+/// Longest part of a line that is read; the rest is cut off.
+pub const MAX_LINE_BYTES: usize = 4096;
+
+#[derive(Debug)]
+pub enum TextError {
+    /// The file holds binary data rather than text.
+    Binary,
+    Io(io::Error),
+}
+
+impl fmt::Display for TextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TextError::Binary => write!(f, "Not a text file."),
+            TextError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for TextError {}
+
+impl From<io::Error> for TextError {
+    fn from(e: io::Error) -> Self {
+        TextError::Io(e)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    Cr,
+    Mixed,
+    /// The file has a single line.
+    None,
+}
+
+impl LineEnding {
+    pub fn label(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::CrLf => "CRLF",
+            LineEnding::Cr => "CR",
+            LineEnding::Mixed => "Mixed",
+            LineEnding::None => "No line breaks",
+        }
+    }
+}
+
+/// How a file's text is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextFormat {
+    pub encoding: &'static Encoding,
+    /// Length of the byte order mark the file starts with.
+    bom_len: u64,
+    /// Character lines end with: `\n`, or `\r` in files without any `\n`.
+    separator: u16,
+}
+
+impl TextFormat {
+    /// Detects the format from the first bytes of a file. `complete` tells
+    /// whether `sample` is the whole file.
+    pub fn detect(sample: &[u8], complete: bool) -> Result<Self, TextError> {
+        let (encoding, bom_len) = match Encoding::for_bom(sample) {
+            Some(found) => found,
+            None => match utf16_without_bom(sample) {
+                Some(encoding) => (encoding, 0),
+                None if sample.contains(&0) => return Err(TextError::Binary),
+                None => (guess_encoding(sample, complete), 0),
+            },
+        };
+        let mut format = TextFormat {
+            encoding,
+            bom_len: bom_len as u64,
+            separator: b'\n' as u16,
+        };
+        let units = || {
+            sample[bom_len..]
+                .chunks_exact(format.unit_len())
+                .map(|unit| format.unit(unit))
+        };
+        if !units().any(|unit| unit == b'\n' as u16) && units().any(|unit| unit == b'\r' as u16) {
+            format.separator = b'\r' as u16;
+        }
+        Ok(format)
+    }
+
+    /// Bytes per code unit: 2 for UTF-16, 1 for the ASCII based encodings.
+    fn unit_len(&self) -> usize {
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn unit(&self, bytes: &[u8]) -> u16 {
+        if self.encoding == UTF_16LE {
+            u16::from_le_bytes([bytes[0], bytes[1]])
+        } else if self.encoding == UTF_16BE {
+            u16::from_be_bytes([bytes[0], bytes[1]])
+        } else {
+            bytes[0] as u16
+        }
+    }
+
+    fn decode(&self, bytes: &[u8]) -> String {
+        let text = self.encoding.decode_without_bom_handling(bytes).0;
+        let text = text.strip_suffix('\r').unwrap_or(&text);
+        text.replace('\t', "    ")
+    }
+}
+
+/// UTF-16 without a byte order mark, recognized by the zero high bytes of
+/// the ASCII characters that make up most text.
+fn utf16_without_bom(sample: &[u8]) -> Option<&'static Encoding> {
+    let pairs = sample.len() / 2;
+    if pairs < 2 {
+        return None;
+    }
+    let zeros_at = |parity: usize| {
+        sample
+            .iter()
+            .skip(parity)
+            .step_by(2)
+            .take(pairs)
+            .filter(|&&byte| byte == 0)
+            .count()
+    };
+    let (even, odd) = (zeros_at(0), zeros_at(1));
+    // Mostly zero on one side and (almost) never on the other.
+    if odd * 10 >= pairs * 4 && even * 20 <= pairs {
+        Some(UTF_16LE)
+    } else if even * 10 >= pairs * 4 && odd * 20 <= pairs {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+fn guess_encoding(sample: &[u8], complete: bool) -> &'static Encoding {
+    match std::str::from_utf8(sample) {
+        Ok(_) => UTF_8,
+        // Cut off in the middle of a character.
+        Err(e) if e.error_len().is_none() && !complete => UTF_8,
+        Err(_) => {
+            let mut detector = EncodingDetector::new();
+            detector.feed(sample, complete);
+            detector.guess(None, true)
+        }
+    }
+}
+
+/// Where the lines of a file start, kept for every `CHECKPOINT_LINES`th line.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    format: TextFormat,
+    /// The opening of the file indexed, told apart from later ones.
+    opening: u64,
+    checkpoints: Vec<u64>,
+    /// Lines ended by a separator.
+    complete_lines: usize,
+    /// Bytes indexed.
+    len: u64,
+    /// Start of the line after the last separator.
+    last_start: u64,
+    /// Whether the last unit indexed is a `\r`, which may start a `\r\n`.
+    after_cr: bool,
+    lf_count: usize,
+    crlf_count: usize,
+}
+
+impl LineIndex {
+    fn new(format: TextFormat) -> Self {
+        static NEXT_OPENING: AtomicU64 = AtomicU64::new(0);
+        Self {
+            format,
+            opening: NEXT_OPENING.fetch_add(1, Ordering::Relaxed),
+            checkpoints: vec![format.bom_len],
+            complete_lines: 0,
+            len: format.bom_len,
+            last_start: format.bom_len,
+            after_cr: false,
+            lf_count: 0,
+            crlf_count: 0,
+        }
+    }
+
+    /// Number of lines indexed, counting an unterminated last line.
+    pub fn line_count(&self) -> usize {
+        self.complete_lines + usize::from(self.len > self.last_start)
+    }
+
+    /// Bytes of the file indexed.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.line_count() == 0
+    }
+
+    pub fn line_ending(&self) -> LineEnding {
+        if self.format.separator == b'\r' as u16 {
+            return match self.complete_lines {
+                0 => LineEnding::None,
+                _ => LineEnding::Cr,
+            };
+        }
+        match (self.lf_count, self.crlf_count) {
+            (0, 0) => LineEnding::None,
+            (_, 0) => LineEnding::Lf,
+            (0, _) => LineEnding::CrLf,
+            _ => LineEnding::Mixed,
+        }
+    }
+
+    /// Indexes `file` from where the index ends up to `end`.
+    fn extend(&mut self, file: &File, end: u64) -> io::Result<()> {
+        let format = self.format;
+        let unit_len = format.unit_len();
+        let mut buf = vec![0; CHUNK_SIZE];
+        while self.len < end {
+            let n = read_units(file, &mut buf, self.len, end, unit_len)?;
+            if n == 0 {
+                break;
+            }
+            for (i, unit) in buf[..n].chunks_exact(unit_len).enumerate() {
+                let unit = format.unit(unit);
+                if unit == format.separator {
+                    if self.after_cr && unit == b'\n' as u16 {
+                        self.crlf_count += 1;
+                    } else {
+                        self.lf_count += 1;
+                    }
+                    self.complete_lines += 1;
+                    self.last_start = self.len + ((i + 1) * unit_len) as u64;
+                    if self.complete_lines.is_multiple_of(CHECKPOINT_LINES) {
+                        self.checkpoints.push(self.last_start);
+                    }
+                }
+                self.after_cr = unit == b'\r' as u16;
+            }
+            self.len += n as u64;
+        }
+        Ok(())
+    }
+}
+
+/// Reads whole code units at `offset`, stopping at `end`.
+fn read_units(
+    file: &File,
+    buf: &mut [u8],
+    offset: u64,
+    end: u64,
+    unit_len: usize,
+) -> io::Result<usize> {
+    let wanted = buf.len().min((end - offset) as usize);
+    loop {
+        match file.read_at(&mut buf[..wanted], offset) {
+            Ok(n) => return Ok(n - n % unit_len),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// A text file opened for viewing. Only the start of the file is indexed
+/// when it is opened; the rest is indexed with [`TextFile::index_rest`],
+/// which can run in the background.
+#[derive(Debug)]
+pub struct TextFile {
+    path: PathBuf,
+    file: File,
+    ino: u64,
+    /// Size of the file when last looked at.
+    size: u64,
+    index: LineIndex,
+}
+
+impl TextFile {
+    pub fn open(path: &Path) -> Result<Self, TextError> {
+        let file = File::open(path)?;
+        let meta = file.metadata()?;
+        let mut sample = vec![0; SAMPLE_SIZE];
+        let n = read_units(&file, &mut sample, 0, meta.len(), 1)?;
+        let format = TextFormat::detect(&sample[..n], n as u64 == meta.len())?;
+
+        let mut index = LineIndex::new(format);
+        index.extend(&file, meta.len().min(CHUNK_SIZE as u64))?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            ino: meta.ino(),
+            size: meta.len(),
+            index,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn format(&self) -> TextFormat {
+        self.index.format
+    }
+
+    pub fn index(&self) -> &LineIndex {
+        &self.index
+    }
+
+    /// Whether the index reaches the end the file had when last looked at.
+    pub fn is_indexed(&self) -> bool {
+        self.index.len >= self.size
+    }
+
+    /// Indexes the rest of the file, starting from `index`.
+    pub fn index_rest(path: &Path, mut index: LineIndex) -> io::Result<LineIndex> {
+        let file = File::open(path)?;
+        let end = file.metadata()?.len();
+        index.extend(&file, end)?;
+        Ok(index)
+    }
+
+    /// Takes an index made by [`TextFile::index_rest`], unless the file was
+    /// reopened or indexed further in the meantime.
+    pub fn set_index(&mut self, index: LineIndex) {
+        if index.opening == self.index.opening && index.len > self.index.len {
+            self.index = index;
+        }
+    }
+
+    /// Follows a growing file: indexes what was appended, or reopens the file
+    /// when it was truncated or replaced, as logs are when rotated. As on
+    /// opening, more than a chunk is left to [`TextFile::index_rest`]. True
+    /// if the file changed.
+    pub fn refresh(&mut self) -> Result<bool, TextError> {
+        let meta = fs::metadata(&self.path)?;
+        if meta.ino() != self.ino || meta.len() < self.index.len {
+            *self = TextFile::open(&self.path)?;
+            return Ok(true);
+        }
+        let changed = meta.len() != self.size;
+        self.size = meta.len();
+        let indexed = self.index.len;
+        if self.size - indexed <= CHUNK_SIZE as u64 {
+            let end = self.size;
+            self.index.extend(&self.file, end)?;
+        }
+        Ok(changed || self.index.len != indexed)
+    }
+
+    /// Reads up to `count` lines from line `first` (0-based) that are
+    /// indexed. Lines longer than [`MAX_LINE_BYTES`] are cut off.
+    pub fn lines(&self, first: usize, count: usize) -> io::Result<Vec<String>> {
+        let index = &self.index;
+        let format = index.format;
+        let unit_len = format.unit_len();
+        let Some(&start) = index.checkpoints.get(first / CHECKPOINT_LINES) else {
+            return Ok(Vec::new());
+        };
+        let mut skip = first % CHECKPOINT_LINES;
+        let mut lines = Vec::new();
+        let mut line = Vec::new();
+        let mut cut = false;
+        let mut offset = start;
+        let mut buf = vec![0; CHUNK_SIZE];
+
+        'read: while offset < index.len && lines.len() < count {
+            let n = read_units(&self.file, &mut buf, offset, index.len, unit_len)?;
+            if n == 0 {
+                break;
+            }
+            for unit in buf[..n].chunks_exact(unit_len) {
+                if format.unit(unit) == format.separator {
+                    if skip > 0 {
+                        skip -= 1;
+                    } else {
+                        lines.push(finish_line(&format, &line, cut));
+                        if lines.len() == count {
+                            break 'read;
+                        }
+                    }
+                    line.clear();
+                    cut = false;
+                } else if skip == 0 {
+                    if line.len() < MAX_LINE_BYTES {
+                        line.extend_from_slice(unit);
+                    } else {
+                        cut = true;
+                    }
+                }
+            }
+            offset += n as u64;
+        }
+        // The last line, when not ended by a separator.
+        if offset >= index.len && skip == 0 && lines.len() < count && index.len > index.last_start {
+            lines.push(finish_line(&format, &line, cut));
+        }
+        Ok(lines)
+    }
+}
+
+fn finish_line(format: &TextFormat, bytes: &[u8], cut: bool) -> String {
+    let mut line = format.decode(bytes);
+    if cut {
+        line.push('…');
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use encoding_rs::WINDOWS_1252;
+    use std::io::Write;
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    #[test]
+    fn detects_the_encoding() {
+        let format = |sample: &[u8]| TextFormat::detect(sample, true).unwrap();
+        assert_eq!(format(b"plain").encoding, UTF_8);
+        assert_eq!(format(b"\xEF\xBB\xBFbom").bom_len, 3);
+        assert_eq!(format(b"\xFF\xFEa\0").encoding, UTF_16LE);
+        assert_eq!(format(&utf16le("no bom here")).encoding, UTF_16LE);
+        assert_eq!(format(b"\0n\0o\0 \0b\0o\0m").encoding, UTF_16BE);
+        let latin = b"Le caf\xE9 \xE9tait d\xE9j\xE0 ferm\xE9 \xE0 cette heure-l\xE0.";
+        assert_eq!(format(latin).encoding, WINDOWS_1252);
+        assert!(matches!(
+            TextFormat::detect(b"\x7FELF\0\0\x01", true),
+            Err(TextError::Binary)
+        ));
+        // A character cut off by the end of the sample.
+        assert_eq!(
+            TextFormat::detect(b"caf\xC3", false).unwrap().encoding,
+            UTF_8
+        );
+    }
+
+    #[test]
+    fn tells_line_endings() {
+        let dir = TestDir::new();
+        let ending = |contents: &[u8]| {
+            let path = dir.file("text", contents);
+            TextFile::open(&path).unwrap().index().line_ending()
+        };
+        assert_eq!(ending(b"a\nb\n"), LineEnding::Lf);
+        assert_eq!(ending(b"a\r\nb\r\n"), LineEnding::CrLf);
+        assert_eq!(ending(b"a\r\nb\n"), LineEnding::Mixed);
+        assert_eq!(ending(b"a\rb\r"), LineEnding::Cr);
+        assert_eq!(ending(b"a"), LineEnding::None);
+    }
+
+    #[test]
+    fn reads_any_range_of_lines() {
+        let dir = TestDir::new();
+        let contents: String = (0..200).map(|i| format!("line {}\r\n", i)).collect();
+        let path = dir.file("text", format!("{}\tlast", contents));
+        let file = TextFile::open(&path).unwrap();
+        assert_eq!(file.index().line_count(), 201);
+        assert_eq!(file.lines(0, 2).unwrap(), ["line 0", "line 1"]);
+        assert_eq!(file.lines(127, 2).unwrap(), ["line 127", "line 128"]);
+        assert_eq!(file.lines(199, 5).unwrap(), ["line 199", "    last"]);
+        assert!(file.lines(201, 1).unwrap().is_empty());
+        assert!(file.lines(500, 1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reads_other_encodings() {
+        let dir = TestDir::new();
+        let mut contents = vec![0xFF, 0xFE];
+        contents.extend(utf16le("première\nligne ✓\n"));
+        let path = dir.file("utf16", contents);
+        let file = TextFile::open(&path).unwrap();
+        assert_eq!(file.lines(0, 5).unwrap(), ["première", "ligne ✓"]);
+
+        let path = dir.file("old mac", b"caf\xE9\rbar\r");
+        let file = TextFile::open(&path).unwrap();
+        assert_eq!(file.lines(0, 5).unwrap(), ["caf\u{e9}", "bar"]);
+    }
+
+    #[test]
+    fn cuts_long_lines() {
+        let dir = TestDir::new();
+        let path = dir.file("text", format!("{}\nnext", "x".repeat(MAX_LINE_BYTES + 10)));
+        let lines = TextFile::open(&path).unwrap().lines(0, 2).unwrap();
+        assert_eq!(lines[0], format!("{}…", "x".repeat(MAX_LINE_BYTES)));
+        assert_eq!(lines[1], "next");
+    }
+
+    #[test]
+    fn indexes_large_files_in_the_background() {
+        let dir = TestDir::new();
+        let line = "0123456789abcdef\n";
+        let lines = CHUNK_SIZE / line.len() * 3;
+        let path = dir.file("big", line.repeat(lines));
+        let mut file = TextFile::open(&path).unwrap();
+        assert!(!file.is_indexed());
+        assert!(file.index().line_count() < lines);
+
+        let index = TextFile::index_rest(&path, file.index().clone()).unwrap();
+        file.set_index(index);
+        assert!(file.is_indexed());
+        assert_eq!(file.index().line_count(), lines);
+        assert_eq!(file.lines(lines - 1, 2).unwrap(), ["0123456789abcdef"]);
+    }
+
+    #[test]
+    fn follows_growing_and_rotated_files() {
+        let dir = TestDir::new();
+        let path = dir.file("log", "one\n");
+        let mut file = TextFile::open(&path).unwrap();
+        assert!(!file.refresh().unwrap());
+
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .unwrap()
+            .write_all(b"two\nthr")
+            .unwrap();
+        assert!(file.refresh().unwrap());
+        assert_eq!(file.lines(0, 5).unwrap(), ["one", "two", "thr"]);
+
+        fs::remove_file(&path).unwrap();
+        dir.file("log", "new\n");
+        assert!(file.refresh().unwrap());
+        assert_eq!(file.lines(0, 5).unwrap(), ["new"]);
+    }
+
+    #[test]
+    fn leaves_a_rotated_large_file_to_the_background() {
+        let dir = TestDir::new();
+        let path = dir.file("log", "old\n".repeat(CHUNK_SIZE));
+        let mut file = TextFile::open(&path).unwrap();
+        let stale = TextFile::index_rest(&path, file.index().clone()).unwrap();
+
+        let line = "0123456789abcdef\n";
+        let lines = CHUNK_SIZE / line.len() * 3;
+        fs::remove_file(&path).unwrap();
+        dir.file("log", line.repeat(lines));
+        assert!(file.refresh().unwrap());
+        assert!(!file.is_indexed());
+        assert!(file.index().line_count() < lines);
+        // Nor is more than a chunk indexed on the next refresh.
+        assert!(!file.refresh().unwrap());
+        assert!(!file.is_indexed());
+
+        // An index of the file before it was rotated is not taken.
+        file.set_index(stale);
+        assert!(!file.is_indexed());
+        let index = TextFile::index_rest(&path, file.index().clone()).unwrap();
+        file.set_index(index);
+        assert!(file.is_indexed());
+        assert_eq!(file.index().line_count(), lines);
+    }
+}
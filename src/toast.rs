@@ -0,0 +1,188 @@
+//! Short notices about operation results, shown one at a time.
+//!
+//! Notices wait in a queue while another one is on screen and are kept in a
+//! bounded history afterwards. Timing is left to the front-end: it asks
+//! [`Toasts::current`] for the notice on screen and calls [`Toasts::dismiss`]
+//! once that notice's [`Severity::timeout`] has passed.
+
+use chrono::{Local, NaiveDateTime};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Notices waiting behind the one on screen; older ones are dropped first.
+const MAX_QUEUED: usize = 5;
+/// Notices kept in the history.
+const MAX_HISTORY: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    /// How long a notice stays on screen. Problems stay longer so there is
+    /// time to read them.
+    pub fn timeout(self) -> Duration {
+        match self {
+            Severity::Info | Severity::Success => Duration::from_secs(4),
+            Severity::Warning => Duration::from_secs(6),
+            Severity::Error => Duration::from_secs(8),
+        }
+    }
+}
+
+/// A notice, with an optional action such as "Undo" of type `A`.
+#[derive(Debug, Clone)]
+pub struct Toast<A> {
+    pub id: u64,
+    pub text: String,
+    pub severity: Severity,
+    pub action: Option<A>,
+    pub time: NaiveDateTime,
+}
+
+impl<A> Toast<A> {
+    /// Time on screen; notices offering an action stay a little longer.
+    pub fn timeout(&self) -> Duration {
+        let extra = if self.action.is_some() { 4 } else { 0 };
+        self.severity.timeout() + Duration::from_secs(extra)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Toasts<A> {
+    next_id: u64,
+    /// The notice on screen first, then the waiting ones.
+    queue: VecDeque<Toast<A>>,
+    /// Newest last.
+    history: VecDeque<Toast<A>>,
+}
+
+impl<A> Default for Toasts<A> {
+    fn default() -> Self {
+        Self {
+            next_id: 0,
+            queue: VecDeque::new(),
+            history: VecDeque::new(),
+        }
+    }
+}
+
+impl<A: Clone> Toasts<A> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a notice and returns its id.
+    pub fn push(&mut self, severity: Severity, text: impl Into<String>, action: Option<A>) -> u64 {
+        let toast = Toast {
+            id: self.next_id,
+            text: text.into(),
+            severity,
+            action,
+            time: Local::now().naive_local(),
+        };
+        self.next_id += 1;
+
+        self.history.push_back(toast.clone());
+        if self.history.len() > MAX_HISTORY {
+            self.history.pop_front();
+        }
+        self.queue.push_back(toast);
+        if self.queue.len() > MAX_QUEUED + 1 {
+            // Keep the one on screen, drop the oldest waiting one.
+            self.queue.remove(1);
+        }
+        self.next_id - 1
+    }
+
+    /// The notice on screen.
+    pub fn current(&self) -> Option<&Toast<A>> {
+        self.queue.front()
+    }
+
+    /// Notices waiting behind the current one.
+    pub fn queued(&self) -> usize {
+        self.queue.len().saturating_sub(1)
+    }
+
+    /// Removes notice `id` from the screen or the queue. Returns whether the
+    /// current notice changed.
+    pub fn dismiss(&mut self, id: u64) -> bool {
+        match self.queue.iter().position(|toast| toast.id == id) {
+            Some(index) => {
+                self.queue.remove(index);
+                index == 0
+            }
+            None => false,
+        }
+    }
+
+    /// Past notices, newest first.
+    pub fn history(&self) -> impl Iterator<Item = &Toast<A>> {
+        self.history.iter().rev()
+    }
+
+    pub fn clear_history(&mut self) {
+        self.history.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shows_one_notice_at_a_time() {
+        let mut toasts = Toasts::<()>::new();
+        let first = toasts.push(Severity::Info, "first", None);
+        let second = toasts.push(Severity::Error, "second", None);
+        assert_eq!(toasts.current().unwrap().id, first);
+        assert_eq!(toasts.queued(), 1);
+
+        assert!(!toasts.dismiss(second));
+        assert_eq!(toasts.current().unwrap().id, first);
+        assert!(toasts.dismiss(first));
+        assert!(toasts.current().is_none());
+        assert!(!toasts.dismiss(first));
+    }
+
+    #[test]
+    fn drops_the_oldest_waiting_notice() {
+        let mut toasts = Toasts::<()>::new();
+        for i in 0..MAX_QUEUED + 2 {
+            toasts.push(Severity::Info, i.to_string(), None);
+        }
+        assert_eq!(toasts.queued(), MAX_QUEUED);
+        assert_eq!(toasts.current().unwrap().text, "0");
+        toasts.dismiss(0);
+        assert_eq!(toasts.current().unwrap().text, "2");
+    }
+
+    #[test]
+    fn keeps_a_bounded_history() {
+        let mut toasts = Toasts::<()>::new();
+        for i in 0..MAX_HISTORY + 1 {
+            let id = toasts.push(Severity::Success, i.to_string(), None);
+            toasts.dismiss(id);
+        }
+        let texts: Vec<_> = toasts.history().map(|toast| toast.text.as_str()).collect();
+        assert_eq!(texts.len(), MAX_HISTORY);
+        assert_eq!(texts[0], MAX_HISTORY.to_string());
+        assert_eq!(texts[MAX_HISTORY - 1], "1");
+        toasts.clear_history();
+        assert_eq!(toasts.history().count(), 0);
+    }
+
+    #[test]
+    fn actions_extend_the_timeout() {
+        let mut toasts = Toasts::new();
+        toasts.push(Severity::Warning, "undoable", Some("undo"));
+        let toast = toasts.current().unwrap();
+        assert_eq!(toast.timeout(), Duration::from_secs(10));
+        assert!(Severity::Error.timeout() > Severity::Info.timeout());
+    }
+}
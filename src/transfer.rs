@@ -0,0 +1,403 @@
+use crate::error::{FileOp, FileOpError};
+use std::ffi::CString;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+const CHUNK_SIZE: usize = 256 * 1024;
+
+/// Snapshot of a running copy, reported after every chunk.
+#[derive(Debug, Clone, Default)]
+pub struct TransferProgress {
+    pub copied_bytes: u64,
+    pub total_bytes: u64,
+    pub current_file: PathBuf,
+}
+
+impl TransferProgress {
+    /// Completed share of the transfer in `0.0..=1.0`.
+    pub fn fraction(&self) -> f32 {
+        if self.total_bytes == 0 {
+            return 1.;
+        }
+        (self.copied_bytes as f64 / self.total_bytes as f64).min(1.) as f32
+    }
+}
+
+/// Shared flag used to stop a transfer from another thread.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug)]
+pub enum TransferError {
+    Cancelled,
+    Failed(FileOpError),
+}
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransferError::Cancelled => write!(f, "Transfer cancelled."),
+            TransferError::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for TransferError {}
+
+impl From<FileOpError> for TransferError {
+    fn from(e: FileOpError) -> Self {
+        TransferError::Failed(e)
+    }
+}
+
+/// Total number of bytes stored under `path`, without following symlinks.
+pub fn total_size(path: &Path) -> io::Result<u64> {
+    let meta = fs::symlink_metadata(path)?;
+    if !meta.is_dir() {
+        return Ok(meta.len());
+    }
+    let mut total = 0;
+    for entry in fs::read_dir(path)? {
+        total += total_size(&entry?.path())?;
+    }
+    Ok(total)
+}
+
+/// Bytes available to unprivileged users on the filesystem holding `path`.
+pub fn available_space(path: &Path) -> io::Result<u64> {
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `c_path` is NUL-terminated and `stat` is only read after
+    // statvfs reported success.
+    let stat = unsafe {
+        if libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        stat.assume_init()
+    };
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+/// Copies `source` (file or folder) to `dest`, adding to `progress` and
+/// calling `on_progress` after every chunk. `progress.total_bytes` is left to
+/// the caller so several copies can share one progress report. When
+/// cancelled or on error, a `dest` created by this call is removed again.
+pub fn copy(
+    source: &Path,
+    dest: &Path,
+    cancel: &CancelToken,
+    progress: &mut TransferProgress,
+    on_progress: &mut impl FnMut(&TransferProgress),
+) -> Result<(), TransferError> {
+    copy_as(FileOp::Copy, source, dest, cancel, progress, on_progress)
+}
+
+/// [`copy`], reporting errors as part of `op`.
+fn copy_as(
+    op: FileOp,
+    source: &Path,
+    dest: &Path,
+    cancel: &CancelToken,
+    progress: &mut TransferProgress,
+    on_progress: &mut impl FnMut(&TransferProgress),
+) -> Result<(), TransferError> {
+    if dest.starts_with(source) {
+        return Err(FileOpError::into_itself(op, source).into());
+    }
+
+    let existed = fs::symlink_metadata(dest).is_ok();
+    if let Err(e) = copy_entry(op, source, dest, cancel, progress, on_progress) {
+        if !existed {
+            let _ = remove_path(dest);
+        }
+        if cancel.is_cancelled() {
+            return Err(TransferError::Cancelled);
+        }
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+/// Moves `source` to `dest`. A plain rename is used when both are on the same
+/// filesystem; otherwise the data is copied with progress and the source is
+/// removed once the copy has completed.
+///
+/// `size` is the number of bytes under `source` when the caller has already
+/// counted them. It is added to `progress` when the move is a rename, so the
+/// tree is only walked for a copy across filesystems.
+pub fn move_path(
+    source: &Path,
+    dest: &Path,
+    size: Option<u64>,
+    cancel: &CancelToken,
+    progress: &mut TransferProgress,
+    on_progress: &mut impl FnMut(&TransferProgress),
+) -> Result<(), TransferError> {
+    if source == dest {
+        return Ok(());
+    }
+    if dest.starts_with(source) {
+        return Err(FileOpError::into_itself(FileOp::Move, source).into());
+    }
+    match fs::rename(source, dest) {
+        Ok(()) => {
+            progress.current_file = source.to_path_buf();
+            progress.copied_bytes += size.unwrap_or(0);
+            on_progress(progress);
+            Ok(())
+        }
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            copy_as(FileOp::Move, source, dest, cancel, progress, on_progress)?;
+            remove_path(source).map_err(|e| FileOpError::new(FileOp::Move, source, e))?;
+            Ok(())
+        }
+        Err(e) => Err(FileOpError::new(FileOp::Move, source, e).into()),
+    }
+}
+
+/// Copies one entry. Errors name the path they happened on: `source` for
+/// reads, `dest` for writes.
+fn copy_entry(
+    op: FileOp,
+    source: &Path,
+    dest: &Path,
+    cancel: &CancelToken,
+    progress: &mut TransferProgress,
+    on_progress: &mut impl FnMut(&TransferProgress),
+) -> Result<(), FileOpError> {
+    let reading = |e| FileOpError::new(op, source, e);
+    let writing = |e| FileOpError::new(op, dest, e);
+
+    let meta = fs::symlink_metadata(source).map_err(reading)?;
+    if meta.is_dir() {
+        fs::create_dir(dest).map_err(writing)?;
+        for entry in fs::read_dir(source).map_err(reading)? {
+            let entry = entry.map_err(reading)?;
+            copy_entry(
+                op,
+                &entry.path(),
+                &dest.join(entry.file_name()),
+                cancel,
+                progress,
+                on_progress,
+            )?;
+        }
+        fs::set_permissions(dest, meta.permissions()).map_err(writing)
+    } else if meta.file_type().is_symlink() {
+        let target = fs::read_link(source).map_err(reading)?;
+        std::os::unix::fs::symlink(target, dest).map_err(writing)
+    } else {
+        copy_file(op, source, dest, cancel, progress, on_progress)?;
+        fs::set_permissions(dest, meta.permissions()).map_err(writing)
+    }
+}
+
+fn copy_file(
+    op: FileOp,
+    source: &Path,
+    dest: &Path,
+    cancel: &CancelToken,
+    progress: &mut TransferProgress,
+    on_progress: &mut impl FnMut(&TransferProgress),
+) -> Result<(), FileOpError> {
+    let reading = |e| FileOpError::new(op, source, e);
+    let writing = |e| FileOpError::new(op, dest, e);
+
+    let mut reader = File::open(source).map_err(reading)?;
+    let mut writer = File::create(dest).map_err(writing)?;
+    let mut buf = vec![0; CHUNK_SIZE];
+    progress.current_file = source.to_path_buf();
+
+    loop {
+        if cancel.is_cancelled() {
+            return Err(writing(io::ErrorKind::Interrupted.into()));
+        }
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(reading(e)),
+        };
+        writer.write_all(&buf[..n]).map_err(writing)?;
+        progress.copied_bytes += n as u64;
+        on_progress(progress);
+    }
+    writer.flush().map_err(writing)
+}
+
+/// Removes a file, symlink or folder tree.
+pub fn remove_path(path: &Path) -> io::Result<()> {
+    if fs::symlink_metadata(path)?.is_dir() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn copies_a_folder_tree_with_progress() {
+        let dir = TestDir::new();
+        let source = dir.dir("source");
+        dir.file("source/a.txt", "hello");
+        dir.file("source/sub/b.bin", vec![7; CHUNK_SIZE + 10]);
+        std::os::unix::fs::symlink("a.txt", source.join("link")).unwrap();
+        let dest = dir.path().join("dest");
+
+        let mut progress = TransferProgress {
+            total_bytes: total_size(&source).unwrap(),
+            ..Default::default()
+        };
+        let mut reports = 0;
+        copy(
+            &source,
+            &dest,
+            &CancelToken::new(),
+            &mut progress,
+            &mut |_| reports += 1,
+        )
+        .unwrap();
+
+        assert_eq!(fs::read_to_string(dest.join("a.txt")).unwrap(), "hello");
+        assert_eq!(
+            fs::read(dest.join("sub/b.bin")).unwrap().len(),
+            CHUNK_SIZE + 10
+        );
+        assert_eq!(
+            fs::read_link(dest.join("link")).unwrap(),
+            Path::new("a.txt")
+        );
+        assert_eq!(progress.copied_bytes, 5 + CHUNK_SIZE as u64 + 10);
+        assert_eq!(reports, 3);
+        assert!(source.join("a.txt").exists());
+    }
+
+    #[test]
+    fn refuses_to_copy_a_folder_into_itself() {
+        let dir = TestDir::new();
+        let source = dir.dir("source");
+        let result = copy(
+            &source,
+            &source.join("inner"),
+            &CancelToken::new(),
+            &mut TransferProgress::default(),
+            &mut |_| {},
+        );
+        assert!(matches!(result, Err(TransferError::Failed(_))));
+        assert!(!source.join("inner").exists());
+    }
+
+    #[test]
+    fn cancelling_removes_the_partial_copy() {
+        let dir = TestDir::new();
+        let source = dir.file("big.bin", vec![1; 3 * CHUNK_SIZE]);
+        let dest = dir.path().join("copy.bin");
+        let cancel = CancelToken::new();
+        let result = copy(
+            &source,
+            &dest,
+            &cancel,
+            &mut TransferProgress::default(),
+            &mut |_| cancel.cancel(),
+        );
+        assert!(matches!(result, Err(TransferError::Cancelled)));
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn moves_by_renaming() {
+        let dir = TestDir::new();
+        let source = dir.file("a/b.txt", "b");
+        let dest = dir.path().join("c.txt");
+        let mut progress = TransferProgress::default();
+        let mut reports = 0;
+        move_path(
+            &source,
+            &dest,
+            Some(1),
+            &CancelToken::new(),
+            &mut progress,
+            &mut |_| reports += 1,
+        )
+        .unwrap();
+        assert!(!source.exists() && dest.exists());
+        assert_eq!((progress.copied_bytes, reports), (1, 1));
+
+        // A rename does not count the bytes itself.
+        let moved = dir.path().join("d.txt");
+        let mut progress = TransferProgress::default();
+        move_path(
+            &dest,
+            &moved,
+            None,
+            &CancelToken::new(),
+            &mut progress,
+            &mut |_| {},
+        )
+        .unwrap();
+        assert_eq!(progress.copied_bytes, 0);
+        move_path(
+            &moved,
+            &moved,
+            None,
+            &CancelToken::new(),
+            &mut progress,
+            &mut |_| {},
+        )
+        .unwrap();
+        assert!(moved.exists());
+        let result = move_path(
+            &source,
+            &moved,
+            None,
+            &CancelToken::new(),
+            &mut progress,
+            &mut |_| {},
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn removes_files_and_folders() {
+        let dir = TestDir::new();
+        dir.file("a/b.txt", "b");
+        let file = dir.file("c.txt", "c");
+        remove_path(&dir.path().join("a")).unwrap();
+        remove_path(&file).unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn progress_fraction() {
+        let mut progress = TransferProgress::default();
+        assert_eq!(progress.fraction(), 1.);
+        progress.total_bytes = 4;
+        progress.copied_bytes = 1;
+        assert_eq!(progress.fraction(), 0.25);
+        progress.copied_bytes = 8;
+        assert_eq!(progress.fraction(), 1.);
+    }
+}
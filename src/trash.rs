@@ -0,0 +1,431 @@
+//! Freedesktop.org Trash specification (version 1.0).
+//!
+//! Files on the home volume go to `$XDG_DATA_HOME/Trash`; files on other
+//! volumes go to `$topdir/.Trash/$uid` when the administrator created a
+//! shared, sticky `.Trash` directory, or to `$topdir/.Trash-$uid` otherwise.
+
+use crate::transfer;
+use crate::xdg;
+use chrono::{Local, NaiveDateTime, Timelike};
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+const INFO_EXTENSION: &str = ".trashinfo";
+const DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// An entry stored in one of the trash directories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrashedItem {
+    /// Trash directory holding the `files` and `info` subdirectories.
+    pub trash_dir: PathBuf,
+    /// Name of the entry inside `files`.
+    pub name: OsString,
+    pub original_path: PathBuf,
+    pub deletion_date: Option<NaiveDateTime>,
+}
+
+impl TrashedItem {
+    /// Location of the trashed data.
+    pub fn files_path(&self) -> PathBuf {
+        self.trash_dir.join("files").join(&self.name)
+    }
+
+    pub fn info_path(&self) -> PathBuf {
+        let mut name = self.name.clone();
+        name.push(INFO_EXTENSION);
+        self.trash_dir.join("info").join(name)
+    }
+}
+
+/// `$XDG_DATA_HOME/Trash`, falling back to `~/.local/share/Trash`.
+pub fn home_trash_dir() -> PathBuf {
+    xdg::data_home().join("Trash")
+}
+
+/// Moves `path` to the trash of its volume and returns the new trash entry.
+pub fn trash(path: &Path) -> io::Result<TrashedItem> {
+    let path = absolute(path)?;
+    let meta = fs::symlink_metadata(&path)?;
+    let trash_dir = trash_dir_for(&path, meta.dev())?;
+    fs::create_dir_all(trash_dir.join("files"))?;
+    fs::create_dir_all(trash_dir.join("info"))?;
+
+    // Home trash entries record the absolute path; volume trashes record the
+    // path relative to the volume's top directory.
+    let recorded = match volume_top(&trash_dir) {
+        Some(top) => path.strip_prefix(top).unwrap_or(&path).to_path_buf(),
+        None => path.clone(),
+    };
+
+    let base = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "cannot trash a root"))?;
+    // The info file only keeps whole seconds.
+    let now = Local::now().naive_local();
+    let deletion_date = now.with_nanosecond(0).unwrap_or(now);
+    let mut n = 1;
+    loop {
+        let mut name = base.to_os_string();
+        if n > 1 {
+            name.push(format!(".{}", n));
+        }
+        let item = TrashedItem {
+            trash_dir: trash_dir.clone(),
+            name,
+            original_path: path.clone(),
+            deletion_date: Some(deletion_date),
+        };
+
+        // The info file is created exclusively first, which reserves the
+        // name against other applications trashing at the same time.
+        let mut info = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(item.info_path())
+        {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                n += 1;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        if fs::symlink_metadata(item.files_path()).is_ok() {
+            let _ = fs::remove_file(item.info_path());
+            n += 1;
+            continue;
+        }
+
+        let written = write!(
+            info,
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            encode_path(&recorded),
+            deletion_date.format(DATE_FORMAT)
+        )
+        .and_then(|_| info.sync_all())
+        .and_then(|_| fs::rename(&path, item.files_path()));
+        if let Err(e) = written {
+            let _ = fs::remove_file(item.info_path());
+            return Err(e);
+        }
+        return Ok(item);
+    }
+}
+
+/// Lists the entries of the home trash and of every mounted volume's trash,
+/// newest first.
+pub fn list() -> Vec<TrashedItem> {
+    let mut items = Vec::new();
+    for trash_dir in trash_dirs() {
+        let Ok(infos) = fs::read_dir(trash_dir.join("info")) else {
+            continue;
+        };
+        for info in infos.flatten() {
+            let file_name = info.file_name();
+            let Some(name) = file_name.as_bytes().strip_suffix(INFO_EXTENSION.as_bytes()) else {
+                continue;
+            };
+            if let Some(item) = read_info(&trash_dir, OsStr::from_bytes(name), &info.path()) {
+                items.push(item);
+            }
+        }
+    }
+    items.sort_by_key(|item| std::cmp::Reverse(item.deletion_date));
+    items
+}
+
+/// Moves `item` back to where it was trashed from and returns that path.
+pub fn restore(item: &TrashedItem) -> io::Result<PathBuf> {
+    let dest = &item.original_path;
+    if fs::symlink_metadata(dest).is_ok() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", dest.display()),
+        ));
+    }
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(item.files_path(), dest)?;
+    fs::remove_file(item.info_path())?;
+    Ok(dest.clone())
+}
+
+/// Removes `item` and its metadata for good.
+pub fn delete_permanently(item: &TrashedItem) -> io::Result<()> {
+    match transfer::remove_path(&item.files_path()) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+        _ => {}
+    }
+    fs::remove_file(item.info_path())
+}
+
+/// Permanently deletes everything in every trash directory.
+pub fn empty() -> io::Result<()> {
+    for trash_dir in trash_dirs() {
+        for sub in ["files", "info"] {
+            let Ok(entries) = fs::read_dir(trash_dir.join(sub)) else {
+                continue;
+            };
+            for entry in entries {
+                transfer::remove_path(&entry?.path())?;
+            }
+        }
+        // Cached sizes of the removed folders are stale now.
+        let _ = fs::remove_file(trash_dir.join("directorysizes"));
+    }
+    Ok(())
+}
+
+fn uid() -> u32 {
+    // SAFETY: getuid has no preconditions and cannot fail.
+    unsafe { libc::getuid() }
+}
+
+fn absolute(path: &Path) -> io::Result<PathBuf> {
+    if path.is_absolute() {
+        Ok(path.to_path_buf())
+    } else {
+        Ok(env::current_dir()?.join(path))
+    }
+}
+
+/// Trash directory to use for a file on device `dev`.
+fn trash_dir_for(path: &Path, dev: u64) -> io::Result<PathBuf> {
+    let home_trash = home_trash_dir();
+    if existing_ancestor_dev(&home_trash) == Some(dev) {
+        return Ok(home_trash);
+    }
+
+    let top = top_dir(path, dev);
+    let shared = top.join(".Trash");
+    if let Ok(meta) = fs::symlink_metadata(&shared) {
+        // Only a real, sticky directory may be used as the shared trash.
+        if meta.is_dir() && meta.permissions().mode() & 0o1000 != 0 {
+            let dir = shared.join(uid().to_string());
+            if fs::create_dir_all(&dir).is_ok() {
+                return Ok(dir);
+            }
+        }
+    }
+    let dir = top.join(format!(".Trash-{}", uid()));
+    fs::create_dir_all(&dir)?;
+    fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))?;
+    Ok(dir)
+}
+
+/// Top directory of the volume a volume trash belongs to; `None` for the
+/// home trash.
+fn volume_top(trash_dir: &Path) -> Option<&Path> {
+    if trash_dir == home_trash_dir() {
+        None
+    } else if trash_dir.ends_with(format!(".Trash-{}", uid())) {
+        trash_dir.parent()
+    } else {
+        // `$topdir/.Trash/$uid`
+        trash_dir.parent().and_then(Path::parent)
+    }
+}
+
+fn existing_ancestor_dev(path: &Path) -> Option<u64> {
+    path.ancestors()
+        .find_map(|p| fs::metadata(p).ok())
+        .map(|meta| meta.dev())
+}
+
+/// Topmost directory of the volume containing `path`.
+fn top_dir(path: &Path, dev: u64) -> PathBuf {
+    let mut top = path.to_path_buf();
+    for ancestor in path.ancestors().skip(1) {
+        match fs::metadata(ancestor) {
+            Ok(meta) if meta.dev() == dev => top = ancestor.to_path_buf(),
+            _ => break,
+        }
+    }
+    top
+}
+
+/// Every trash directory that currently exists.
+fn trash_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![home_trash_dir()];
+    let mounts = fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+    for mount in mounts.lines().filter_map(|line| line.split(' ').nth(1)) {
+        let top = PathBuf::from(decode_mount_field(mount));
+        for dir in [
+            top.join(".Trash").join(uid().to_string()),
+            top.join(format!(".Trash-{}", uid())),
+        ] {
+            if dir.join("info").is_dir() && !dirs.contains(&dir) {
+                dirs.push(dir);
+            }
+        }
+    }
+    dirs
+}
+
+fn read_info(trash_dir: &Path, name: &OsStr, info_path: &Path) -> Option<TrashedItem> {
+    let contents = fs::read_to_string(info_path).ok()?;
+    let mut lines = contents.lines().map(str::trim);
+    if lines.next() != Some("[Trash Info]") {
+        return None;
+    }
+    let mut original_path = None;
+    let mut deletion_date = None;
+    for line in lines {
+        if let Some(value) = line.strip_prefix("Path=") {
+            original_path = Some(decode_path(value));
+        } else if let Some(value) = line.strip_prefix("DeletionDate=") {
+            deletion_date = NaiveDateTime::parse_from_str(value, DATE_FORMAT).ok();
+        }
+    }
+
+    let mut original_path = original_path?;
+    if original_path.is_relative() {
+        original_path = volume_top(trash_dir)?.join(original_path);
+    }
+    Some(TrashedItem {
+        trash_dir: trash_dir.to_path_buf(),
+        name: name.to_os_string(),
+        original_path,
+        deletion_date,
+    })
+}
+
+/// Percent-encodes a path as required for the `Path` key.
+pub(crate) fn encode_path(path: &Path) -> String {
+    let mut out = String::new();
+    for &b in path.as_os_str().as_bytes() {
+        if b.is_ascii_alphanumeric() || b"/-_.~!$&'()*+,;=:@".contains(&b) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+pub(crate) fn decode_path(value: &str) -> PathBuf {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    PathBuf::from(OsString::from_vec(out))
+}
+
+/// Undoes the octal escapes (`\040` for a space) used in `/proc/self/mounts`.
+fn decode_mount_field(field: &str) -> OsString {
+    let bytes = field.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let octal = bytes
+            .get(i + 1..i + 4)
+            .and_then(|o| std::str::from_utf8(o).ok())
+            .and_then(|o| u8::from_str_radix(o, 8).ok());
+        match (bytes[i], octal) {
+            (b'\\', Some(b)) => {
+                out.push(b);
+                i += 4;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    OsString::from_vec(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+
+    #[test]
+    fn paths_survive_percent_encoding() {
+        let path = PathBuf::from(OsString::from_vec(b"/tmp/a b%/\xff.txt".to_vec()));
+        let encoded = encode_path(&path);
+        assert_eq!(encoded, "/tmp/a%20b%25/%FF.txt");
+        assert_eq!(decode_path(&encoded), path);
+        // A stray percent sign is kept as it is.
+        assert_eq!(decode_path("/a%zz"), Path::new("/a%zz"));
+    }
+
+    #[test]
+    fn reads_trash_info_files() {
+        let dir = TestDir::new();
+        let trash_dir = dir.dir(&format!(".Trash-{}", uid()));
+        let info = dir.file(
+            &format!(".Trash-{}/info/a.txt.trashinfo", uid()),
+            "[Trash Info]\nPath=docs/a%20b.txt\nDeletionDate=2024-03-01T12:30:05\n",
+        );
+        let item = read_info(&trash_dir, OsStr::new("a.txt"), &info).unwrap();
+        // Volume trashes record paths relative to the volume's top folder.
+        assert_eq!(item.original_path, dir.path().join("docs/a b.txt"));
+        assert_eq!(
+            item.deletion_date.unwrap().to_string(),
+            "2024-03-01 12:30:05"
+        );
+        assert_eq!(item.files_path(), trash_dir.join("files/a.txt"));
+        assert_eq!(item.info_path(), info);
+
+        let info = dir.file("bad.trashinfo", "Path=/a\n");
+        assert_eq!(read_info(&trash_dir, OsStr::new("bad"), &info), None);
+    }
+
+    #[test]
+    fn restores_and_deletes_items() {
+        let dir = TestDir::new();
+        let trash_dir = dir.dir("Trash");
+        let item = |name: &str| TrashedItem {
+            trash_dir: trash_dir.clone(),
+            name: name.into(),
+            original_path: dir.path().join("restored").join(name),
+            deletion_date: None,
+        };
+        for name in ["a", "b"] {
+            dir.file(&format!("Trash/files/{}", name), name);
+            dir.file(&format!("Trash/info/{}.trashinfo", name), "");
+        }
+
+        let restored = restore(&item("a")).unwrap();
+        assert_eq!(fs::read_to_string(restored).unwrap(), "a");
+        assert!(!item("a").info_path().exists());
+
+        dir.file("restored/b", "taken");
+        let e = restore(&item("b")).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::AlreadyExists);
+        delete_permanently(&item("b")).unwrap();
+        assert_eq!(fs::read_dir(trash_dir.join("files")).unwrap().count(), 0);
+        assert_eq!(fs::read_dir(trash_dir.join("info")).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn decodes_mount_points() {
+        assert_eq!(
+            decode_mount_field("/media/USB\\040Stick"),
+            "/media/USB Stick"
+        );
+        assert_eq!(decode_mount_field("/plain"), "/plain");
+    }
+}
@@ -0,0 +1,267 @@
+//! Live updates of a directory listing through inotify.
+//!
+//! Events are read on the tokio runtime and collected until the directory
+//! has been quiet for a moment, so that a burst such as an extraction or a
+//! camera writing a file results in a single batch of [`DirChange`]s.
+
+use futures_core::Stream;
+use inotify::{EventMask, Inotify, WatchMask};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::future::poll_fn;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use tokio::task::JoinHandle;
+use tokio::time::{self, Duration, Instant};
+
+/// Quiet period that ends a batch of events.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+/// Longest a batch is held back while events keep arriving.
+const MAX_DELAY: Duration = Duration::from_secs(1);
+
+/// A change to the watched directory, relative to the last batch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirChange {
+    /// `path` exists now. It may already be listed if it was only modified.
+    Added(PathBuf),
+    Removed(PathBuf),
+    Renamed { from: PathBuf, to: PathBuf },
+    /// Events were lost; the listing has to be read again.
+    Rescan,
+    /// The watched directory itself was removed or moved away.
+    Gone,
+}
+
+/// Watch on one directory. Dropping it stops the watch.
+#[derive(Debug)]
+pub struct DirWatcher {
+    dir: PathBuf,
+    task: JoinHandle<()>,
+}
+
+impl DirWatcher {
+    /// Starts watching `dir` on the current tokio runtime. `on_changes` is
+    /// called from the runtime with each debounced batch.
+    pub fn new(
+        dir: PathBuf,
+        on_changes: impl Fn(Vec<DirChange>) + Send + 'static,
+    ) -> io::Result<Self> {
+        let inotify = Inotify::init()?;
+        inotify.watches().add(
+            &dir,
+            WatchMask::CREATE
+                | WatchMask::DELETE
+                | WatchMask::MOVED_FROM
+                | WatchMask::MOVED_TO
+                | WatchMask::CLOSE_WRITE
+                | WatchMask::ATTRIB
+                | WatchMask::DELETE_SELF
+                | WatchMask::MOVE_SELF
+                | WatchMask::ONLYDIR,
+        )?;
+        let mut stream = inotify.into_event_stream(vec![0; 4096])?;
+
+        let watched = dir.clone();
+        let task = tokio::spawn(async move {
+            loop {
+                let mut batch = Batch::default();
+                match next_event(&mut stream).await {
+                    Some(Ok(event)) => batch.add(event.mask, event.cookie, event.name),
+                    _ => break,
+                }
+
+                let deadline = Instant::now() + MAX_DELAY;
+                let mut closed = false;
+                while Instant::now() < deadline {
+                    match time::timeout(DEBOUNCE, next_event(&mut stream)).await {
+                        Ok(Some(Ok(event))) => batch.add(event.mask, event.cookie, event.name),
+                        Ok(_) => {
+                            closed = true;
+                            break;
+                        }
+                        // Quiet for long enough.
+                        Err(_) => break,
+                    }
+                }
+
+                let changes = batch.changes(&watched);
+                let gone = changes.contains(&DirChange::Gone);
+                if !changes.is_empty() {
+                    on_changes(changes);
+                }
+                if closed || gone {
+                    break;
+                }
+            }
+        });
+        Ok(Self { dir, task })
+    }
+
+    /// The watched directory.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+impl Drop for DirWatcher {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+pub(crate) async fn next_event<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+    poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+}
+
+/// Events collected during one debounce period.
+#[derive(Default)]
+struct Batch {
+    /// Names touched by an event, in order of first appearance.
+    names: Vec<OsString>,
+    /// `MOVED_FROM` names waiting for their `MOVED_TO`, by cookie.
+    moved_from: HashMap<u32, OsString>,
+    renames: Vec<(OsString, OsString)>,
+    rescan: bool,
+    gone: bool,
+}
+
+impl Batch {
+    fn add(&mut self, mask: EventMask, cookie: u32, name: Option<OsString>) {
+        if mask.contains(EventMask::Q_OVERFLOW) {
+            self.rescan = true;
+        }
+        if mask.intersects(EventMask::DELETE_SELF | EventMask::MOVE_SELF | EventMask::IGNORED) {
+            self.gone = true;
+        }
+        let Some(name) = name else {
+            return;
+        };
+        if mask.contains(EventMask::MOVED_FROM) {
+            self.moved_from.insert(cookie, name.clone());
+        } else if mask.contains(EventMask::MOVED_TO) {
+            if let Some(from) = self.moved_from.remove(&cookie) {
+                self.renames.push((from, name.clone()));
+            }
+        }
+        if !self.names.contains(&name) {
+            self.names.push(name);
+        }
+    }
+
+    /// Net effect of the batch, judged by what exists on disk now.
+    fn changes(self, dir: &Path) -> Vec<DirChange> {
+        if self.gone {
+            return vec![DirChange::Gone];
+        }
+        if self.rescan {
+            return vec![DirChange::Rescan];
+        }
+
+        let exists = |name: &OsString| std::fs::symlink_metadata(dir.join(name)).is_ok();
+        let mut changes = Vec::new();
+        let mut handled = Vec::new();
+        for (from, to) in &self.renames {
+            if !exists(from) && exists(to) {
+                changes.push(DirChange::Renamed {
+                    from: dir.join(from),
+                    to: dir.join(to),
+                });
+                handled.extend([from, to]);
+            }
+        }
+        for name in &self.names {
+            if handled.contains(&name) {
+                continue;
+            }
+            let path = dir.join(name);
+            changes.push(if exists(name) {
+                DirChange::Added(path)
+            } else {
+                DirChange::Removed(path)
+            });
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_dir::TestDir;
+    use std::sync::mpsc;
+
+    fn name(s: &str) -> Option<OsString> {
+        Some(s.into())
+    }
+
+    #[test]
+    fn reports_what_exists_now() {
+        let dir = TestDir::new();
+        let new = dir.file("new", "");
+        let mut batch = Batch::default();
+        batch.add(EventMask::CREATE, 0, name("new"));
+        batch.add(EventMask::CLOSE_WRITE, 0, name("new"));
+        batch.add(EventMask::CREATE, 0, name("temp"));
+        batch.add(EventMask::DELETE, 0, name("temp"));
+        assert_eq!(
+            batch.changes(dir.path()),
+            [
+                DirChange::Added(new),
+                DirChange::Removed(dir.path().join("temp"))
+            ]
+        );
+    }
+
+    #[test]
+    fn pairs_moves_into_renames() {
+        let dir = TestDir::new();
+        let to = dir.file("b", "");
+        let mut batch = Batch::default();
+        batch.add(EventMask::MOVED_FROM, 7, name("a"));
+        batch.add(EventMask::MOVED_FROM, 8, name("moved away"));
+        batch.add(EventMask::MOVED_TO, 7, name("b"));
+        assert_eq!(
+            batch.changes(dir.path()),
+            [
+                DirChange::Renamed {
+                    from: dir.path().join("a"),
+                    to,
+                },
+                DirChange::Removed(dir.path().join("moved away")),
+            ]
+        );
+    }
+
+    #[test]
+    fn overflow_and_removal_replace_the_changes() {
+        let dir = TestDir::new();
+        let mut batch = Batch::default();
+        batch.add(EventMask::CREATE, 0, name("a"));
+        batch.add(EventMask::Q_OVERFLOW, 0, None);
+        assert_eq!(batch.changes(dir.path()), [DirChange::Rescan]);
+
+        let mut batch = Batch::default();
+        batch.add(EventMask::Q_OVERFLOW, 0, None);
+        batch.add(EventMask::DELETE_SELF, 0, None);
+        assert_eq!(batch.changes(dir.path()), [DirChange::Gone]);
+    }
+
+    #[tokio::test]
+    async fn watches_a_directory() {
+        let dir = TestDir::new();
+        let (tx, rx) = mpsc::channel();
+        let watcher = DirWatcher::new(dir.path().to_path_buf(), move |changes| {
+            tx.send(changes).unwrap();
+        })
+        .unwrap();
+        assert_eq!(watcher.dir(), dir.path());
+
+        let file = dir.file("a", "contents");
+        let changes = tokio::task::spawn_blocking(move || rx.recv_timeout(Duration::from_secs(5)))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(changes, [DirChange::Added(file)]);
+    }
+}
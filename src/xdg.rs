@@ -0,0 +1,54 @@
+//! XDG base directories, with the fallbacks of the Base Directory
+//! specification. Relative values in the environment are ignored.
+
+use std::env;
+use std::path::PathBuf;
+
+/// Name of this application's subdirectory in the base directories.
+const APP_DIR: &str = "file-manager";
+
+pub fn home_dir() -> PathBuf {
+    PathBuf::from(env::var_os("HOME").unwrap_or_default())
+}
+
+/// `$XDG_DATA_HOME`, falling back to `~/.local/share`.
+pub fn data_home() -> PathBuf {
+    base_dir("XDG_DATA_HOME", ".local/share")
+}
+
+/// `$XDG_CONFIG_HOME`, falling back to `~/.config`.
+pub fn config_home() -> PathBuf {
+    base_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// `$XDG_STATE_HOME`, falling back to `~/.local/state`.
+pub fn state_home() -> PathBuf {
+    base_dir("XDG_STATE_HOME", ".local/state")
+}
+
+/// `$XDG_CACHE_HOME`, falling back to `~/.cache`.
+pub fn cache_home() -> PathBuf {
+    base_dir("XDG_CACHE_HOME", ".cache")
+}
+
+/// This application's directory for settings.
+pub fn app_config_dir() -> PathBuf {
+    config_home().join(APP_DIR)
+}
+
+/// This application's directory for state that survives restarts.
+pub fn app_state_dir() -> PathBuf {
+    state_home().join(APP_DIR)
+}
+
+/// This application's directory for data that can be rebuilt.
+pub fn app_cache_dir() -> PathBuf {
+    cache_home().join(APP_DIR)
+}
+
+fn base_dir(var: &str, fallback: &str) -> PathBuf {
+    env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .unwrap_or_else(|| home_dir().join(fallback))
+}